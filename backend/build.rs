@@ -0,0 +1,6 @@
+// `sqlx::migrate!` embeds the migrations at compile time, so a new migration
+// has to trigger a rebuild
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=migrations_sqlite");
+}
@@ -0,0 +1,35 @@
+-- Create members table
+CREATE TABLE members (
+    id SERIAL PRIMARY KEY,
+    display_name VARCHAR(50) NOT NULL UNIQUE,
+    color VARCHAR(7) NOT NULL DEFAULT '#6c757d',
+    initials VARCHAR(3) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create trigger to auto-update updated_at
+CREATE TRIGGER update_members_updated_at
+    BEFORE UPDATE ON members
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Turn every existing free-text assignee into a member
+INSERT INTO members (display_name, initials)
+SELECT DISTINCT assignee, UPPER(LEFT(assignee, 1))
+FROM todos
+WHERE assignee <> 'Unassigned';
+
+-- Point todos at members; a NULL assignee means unassigned
+ALTER TABLE todos
+    ADD COLUMN assignee_id INTEGER REFERENCES members(id) ON DELETE SET NULL;
+
+UPDATE todos
+SET assignee_id = members.id
+FROM members
+WHERE members.display_name = todos.assignee;
+
+DROP INDEX idx_todos_assignee;
+ALTER TABLE todos DROP COLUMN assignee;
+
+CREATE INDEX idx_todos_assignee_id ON todos(assignee_id);
@@ -0,0 +1,6 @@
+-- Recurrence rule for repeating chores, e.g. {"freq": "weekly", "weekdays": ["Tue"]}
+ALTER TABLE todos ADD COLUMN recurrence JSONB;
+
+-- Occurrence spawned when this todo was completed, so re-toggling does not spawn twice
+ALTER TABLE todos
+    ADD COLUMN next_occurrence_id INTEGER REFERENCES todos(id) ON DELETE SET NULL;
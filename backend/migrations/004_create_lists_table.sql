@@ -0,0 +1,26 @@
+-- Create lists table
+CREATE TABLE lists (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create trigger to auto-update updated_at
+CREATE TRIGGER update_lists_updated_at
+    BEFORE UPDATE ON lists
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Existing todos move to a default list
+INSERT INTO lists (name) VALUES ('General');
+
+ALTER TABLE todos
+    ADD COLUMN list_id INTEGER REFERENCES lists(id) ON DELETE CASCADE;
+
+UPDATE todos SET list_id = (SELECT id FROM lists WHERE name = 'General');
+
+ALTER TABLE todos ALTER COLUMN list_id SET NOT NULL;
+
+CREATE INDEX idx_todos_list_id ON todos(list_id);
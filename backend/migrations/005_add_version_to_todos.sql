@@ -0,0 +1,4 @@
+-- Every write bumps the version; clients send it back in If-Match so
+-- concurrent edits from two devices are detected instead of overwritten
+ALTER TABLE todos
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,10 @@
+-- Create events table: every change broadcast over the WebSocket, so clients
+-- that reconnect with ?since=<seq> can be sent what they missed
+CREATE TABLE events (
+    seq BIGSERIAL PRIMARY KEY,
+    event_type VARCHAR(50) NOT NULL,
+    -- No foreign key: events about a list outlive the list
+    list_id INTEGER,
+    data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,60 @@
+-- Create households table: a household owns its lists (and through them its
+-- todos), members and event history
+CREATE TABLE households (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create trigger to auto-update updated_at
+CREATE TRIGGER update_households_updated_at
+    BEFORE UPDATE ON households
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Everything created before accounts existed; the first account to register joins it
+INSERT INTO households (name) VALUES ('Family');
+
+ALTER TABLE lists
+    ADD COLUMN household_id INTEGER REFERENCES households(id) ON DELETE CASCADE;
+UPDATE lists SET household_id = (SELECT MIN(id) FROM households);
+ALTER TABLE lists ALTER COLUMN household_id SET NOT NULL;
+
+ALTER TABLE members
+    ADD COLUMN household_id INTEGER REFERENCES households(id) ON DELETE CASCADE;
+UPDATE members SET household_id = (SELECT MIN(id) FROM households);
+ALTER TABLE members ALTER COLUMN household_id SET NOT NULL;
+
+-- Names only need to be unique within a household
+ALTER TABLE lists DROP CONSTRAINT lists_name_key;
+ALTER TABLE lists ADD CONSTRAINT lists_household_id_name_key UNIQUE (household_id, name);
+ALTER TABLE members DROP CONSTRAINT members_display_name_key;
+ALTER TABLE members ADD CONSTRAINT members_household_id_display_name_key UNIQUE (household_id, display_name);
+
+-- Events are only replayed to connections of the same household
+ALTER TABLE events ADD COLUMN household_id INTEGER;
+UPDATE events SET household_id = (SELECT MIN(id) FROM households);
+ALTER TABLE events ALTER COLUMN household_id SET NOT NULL;
+
+-- Create users table: accounts that sign in to a household
+CREATE TABLE users (
+    id SERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    email VARCHAR(254) NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_users_household_id ON users(household_id);
+
+-- Create sessions table. Only a SHA-256 of the cookie value is stored, so the
+-- table alone cannot be used to sign in.
+CREATE TABLE sessions (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
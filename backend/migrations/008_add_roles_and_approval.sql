@@ -0,0 +1,17 @@
+-- Roles decide what a member's account may do: parents manage everything,
+-- children can add and tick off todos, guests can only look
+ALTER TABLE members
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'parent'
+        CHECK (role IN ('parent', 'child', 'guest'));
+
+-- The member an account signs in as; accounts without one act as parents.
+-- Removing the member removes the account, rather than leaving a child's
+-- account behind with parent rights
+ALTER TABLE users
+    ADD COLUMN member_id INTEGER REFERENCES members(id) ON DELETE CASCADE;
+
+-- A child completing a todo that needs approval only marks it pending
+-- (approval_requested_by) until a parent approves it
+ALTER TABLE todos
+    ADD COLUMN needs_approval BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN approval_requested_by INTEGER REFERENCES members(id) ON DELETE SET NULL;
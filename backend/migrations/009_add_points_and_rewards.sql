@@ -0,0 +1,36 @@
+-- Points a todo earns its assignee when it is completed
+ALTER TABLE todos
+    ADD COLUMN points INTEGER NOT NULL DEFAULT 0 CHECK (points >= 0);
+
+-- Things members can spend their points on
+CREATE TABLE rewards (
+    id SERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    cost INTEGER NOT NULL CHECK (cost > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_rewards_updated_at
+    BEFORE UPDATE ON rewards
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE INDEX idx_rewards_household_id ON rewards(household_id);
+
+-- Every change to a member's points; their balance is the sum. Entries
+-- keep a description so they still read well once the todo or reward is gone
+CREATE TABLE point_entries (
+    id SERIAL PRIMARY KEY,
+    member_id INTEGER NOT NULL REFERENCES members(id) ON DELETE CASCADE,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL CHECK (reason IN ('todo_completed', 'todo_reopened', 'reward_redeemed')),
+    description TEXT NOT NULL,
+    todo_id INTEGER REFERENCES todos(id) ON DELETE SET NULL,
+    reward_id INTEGER REFERENCES rewards(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_point_entries_member_id ON point_entries(member_id);
+CREATE INDEX idx_point_entries_todo_id ON point_entries(todo_id);
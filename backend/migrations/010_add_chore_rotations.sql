@@ -0,0 +1,32 @@
+-- Members who are away can be skipped when chores rotate
+ALTER TABLE members
+    ADD COLUMN away BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The order in which a recurring chore passes between members
+CREATE TABLE rotations (
+    id SERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    skip_away BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_rotations_updated_at
+    BEFORE UPDATE ON rotations
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE INDEX idx_rotations_household_id ON rotations(household_id);
+
+CREATE TABLE rotation_members (
+    rotation_id INTEGER NOT NULL REFERENCES rotations(id) ON DELETE CASCADE,
+    member_id INTEGER NOT NULL REFERENCES members(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (rotation_id, member_id)
+);
+
+CREATE INDEX idx_rotation_members_member_id ON rotation_members(member_id);
+
+-- Each new occurrence of a todo on a rotation goes to the next member
+ALTER TABLE todos
+    ADD COLUMN rotation_id INTEGER REFERENCES rotations(id) ON DELETE SET NULL;
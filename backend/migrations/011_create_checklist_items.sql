@@ -0,0 +1,21 @@
+-- Steps within a todo, such as the things to pack for a trip
+CREATE TABLE checklist_items (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_checklist_items_updated_at
+    BEFORE UPDATE ON checklist_items
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE INDEX idx_checklist_items_todo_id ON checklist_items(todo_id);
+
+-- Complete the todo once every item on its checklist is done
+ALTER TABLE todos
+    ADD COLUMN auto_complete BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- Notes left on a todo, such as "bought the wrong size, returning it"
+CREATE TABLE todo_comments (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    author_id INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    text TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todo_comments_todo_id ON todo_comments(todo_id);
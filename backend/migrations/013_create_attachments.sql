@@ -0,0 +1,16 @@
+-- Files attached to todos. The bytes live on disk under ATTACHMENTS_DIR,
+-- named by their SHA-256; several rows may share one file.
+CREATE TABLE attachments (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    sha256 TEXT NOT NULL,
+    has_thumbnail BOOLEAN NOT NULL DEFAULT FALSE,
+    uploaded_by INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_attachments_todo_id ON attachments(todo_id);
+CREATE INDEX idx_attachments_sha256 ON attachments(sha256);
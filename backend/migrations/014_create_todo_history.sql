@@ -0,0 +1,18 @@
+-- Every change made to a todo: who made it, when, and the old and new value
+-- of each field that changed. No foreign key on todo_id, so the history of a
+-- deleted todo is kept
+CREATE TABLE todo_history (
+    id BIGSERIAL PRIMARY KEY,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    todo_id INTEGER NOT NULL,
+    -- The todo's text at the time, for the activity feed
+    todo_text TEXT NOT NULL,
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    member_id INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    action VARCHAR(50) NOT NULL,
+    changes JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todo_history_todo_id ON todo_history(todo_id);
+CREATE INDEX idx_todo_history_household_id ON todo_history(household_id, id);
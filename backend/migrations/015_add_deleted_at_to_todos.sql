@@ -0,0 +1,5 @@
+-- Deleting a todo moves it to the trash; it is purged for good once it has
+-- been there longer than TRASH_RETENTION_DAYS
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_todos_deleted_at ON todos(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Free-form labels such as "school" or "groceries"; GET /api/todos can
+-- filter on one
+CREATE TABLE todo_tags (
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    tag VARCHAR(30) NOT NULL,
+    PRIMARY KEY (todo_id, tag)
+);
+
+CREATE INDEX idx_todo_tags_tag ON todo_tags(tag);
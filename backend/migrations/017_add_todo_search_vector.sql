@@ -0,0 +1,68 @@
+-- Full-text search for GET /api/search: each todo keeps a tsvector of its
+-- text (weighted highest), tags, checklist and comments, maintained by the
+-- triggers below
+ALTER TABLE todos ADD COLUMN search_vector TSVECTOR NOT NULL DEFAULT ''::tsvector;
+
+CREATE FUNCTION todo_search_vector(for_todo INTEGER, todo_text TEXT)
+RETURNS TSVECTOR AS $$
+    SELECT setweight(to_tsvector('english', todo_text), 'A')
+        || setweight(to_tsvector('english', COALESCE((SELECT string_agg(tag, ' ') FROM todo_tags WHERE todo_id = for_todo), '')), 'B')
+        || setweight(to_tsvector('english', COALESCE((SELECT string_agg(text, ' ') FROM checklist_items WHERE todo_id = for_todo), '')), 'C')
+        || setweight(to_tsvector('english', COALESCE((SELECT string_agg(text, ' ') FROM todo_comments WHERE todo_id = for_todo), '')), 'D')
+$$ LANGUAGE sql STABLE;
+
+CREATE FUNCTION update_todo_search_vector()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector = todo_search_vector(NEW.id, NEW.text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_search_vector_update
+    BEFORE INSERT OR UPDATE OF text ON todos
+    FOR EACH ROW
+    EXECUTE FUNCTION update_todo_search_vector();
+
+-- Tags, checklist items and comments refresh the vector of the todo they belong to
+CREATE FUNCTION refresh_todo_search_vector()
+RETURNS TRIGGER AS $$
+DECLARE
+    changed_todo INTEGER;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_todo = OLD.todo_id;
+    ELSE
+        changed_todo = NEW.todo_id;
+    END IF;
+    UPDATE todos SET search_vector = todo_search_vector(id, text) WHERE id = changed_todo;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todo_tags_search_vector_refresh
+    AFTER INSERT OR UPDATE OR DELETE ON todo_tags
+    FOR EACH ROW
+    EXECUTE FUNCTION refresh_todo_search_vector();
+
+CREATE TRIGGER checklist_items_search_vector_refresh
+    AFTER INSERT OR UPDATE OF text OR DELETE ON checklist_items
+    FOR EACH ROW
+    EXECUTE FUNCTION refresh_todo_search_vector();
+
+CREATE TRIGGER todo_comments_search_vector_refresh
+    AFTER INSERT OR UPDATE OR DELETE ON todo_comments
+    FOR EACH ROW
+    EXECUTE FUNCTION refresh_todo_search_vector();
+
+-- Refreshing the vector alone is not an edit, so it leaves updated_at be
+DROP TRIGGER update_todos_updated_at ON todos;
+CREATE TRIGGER update_todos_updated_at
+    BEFORE UPDATE ON todos
+    FOR EACH ROW
+    WHEN (OLD.search_vector IS NOT DISTINCT FROM NEW.search_vector OR OLD.text IS DISTINCT FROM NEW.text)
+    EXECUTE FUNCTION update_updated_at_column();
+
+UPDATE todos SET search_vector = todo_search_vector(id, text);
+
+CREATE INDEX idx_todos_search_vector ON todos USING GIN (search_vector);
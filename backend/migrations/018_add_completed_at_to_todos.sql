@@ -0,0 +1,9 @@
+-- When a todo was completed, so listings can leave out todos finished long
+-- ago; existing completed todos count from their last change
+ALTER TABLE todos ADD COLUMN completed_at TIMESTAMPTZ;
+
+UPDATE todos SET completed_at = updated_at WHERE completed;
+
+-- Keyset pagination walks these in either direction
+CREATE INDEX idx_todos_due_date_id ON todos(due_date, id);
+CREATE INDEX idx_todos_created_at_id ON todos(created_at, id);
@@ -0,0 +1,5 @@
+-- An optional time on the due date, read in the household's timezone
+ALTER TABLE todos ADD COLUMN due_time TIME;
+
+-- IANA name, such as Europe/London, that "today" and "overdue" are judged in
+ALTER TABLE households ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
@@ -0,0 +1,2 @@
+-- HistoryAction is read back as TEXT, which a VARCHAR column does not decode as
+ALTER TABLE todo_history ALTER COLUMN action TYPE TEXT;
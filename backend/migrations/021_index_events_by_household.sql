@@ -0,0 +1,2 @@
+-- Replay and pruning both work through one household's events at a time
+CREATE INDEX idx_events_household_seq ON events(household_id, seq);
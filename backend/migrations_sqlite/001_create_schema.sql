@@ -0,0 +1,44 @@
+-- SQLite schema, equivalent to the Postgres migrations 001-004.
+-- Timestamps are written by the application, so there are no update triggers.
+
+-- Create lists table
+CREATE TABLE lists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(50) NOT NULL UNIQUE,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+INSERT INTO lists (name, position, created_at, updated_at)
+VALUES ('General', 0, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+
+-- Create members table
+CREATE TABLE members (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    display_name VARCHAR(50) NOT NULL UNIQUE,
+    color VARCHAR(7) NOT NULL DEFAULT '#6c757d',
+    initials VARCHAR(3) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- Create todos table
+CREATE TABLE todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_id INTEGER NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    assignee_id INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    due_date DATE,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    recurrence TEXT,
+    next_occurrence_id INTEGER REFERENCES todos(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- Create index for common queries
+CREATE INDEX idx_todos_list_id ON todos(list_id);
+CREATE INDEX idx_todos_assignee_id ON todos(assignee_id);
+CREATE INDEX idx_todos_due_date ON todos(due_date);
+CREATE INDEX idx_todos_completed ON todos(completed);
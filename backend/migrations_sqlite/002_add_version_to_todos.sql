@@ -0,0 +1,3 @@
+-- Every write bumps the version; see migrations/005_add_version_to_todos.sql
+ALTER TABLE todos
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,8 @@
+-- Create events table; see migrations/006_create_events_table.sql
+CREATE TABLE events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type VARCHAR(50) NOT NULL,
+    list_id INTEGER,
+    data TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,96 @@
+-- Households and accounts; see migrations/007_add_households_and_users.sql.
+--
+-- SQLite cannot drop the old global UNIQUE constraints on list and member
+-- names, so those tables are rebuilt. Everything that references them is
+-- renamed out of the way first: dropping a table that todos still point at
+-- would cascade-delete the todos.
+
+CREATE TABLE households (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR(50) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- Everything created before accounts existed; the first account to register joins it
+INSERT INTO households (name, created_at, updated_at)
+VALUES ('Family', strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+
+ALTER TABLE todos RENAME TO todos_old;
+ALTER TABLE lists RENAME TO lists_old;
+ALTER TABLE members RENAME TO members_old;
+
+CREATE TABLE lists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    UNIQUE (household_id, name)
+);
+
+CREATE TABLE members (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    display_name VARCHAR(50) NOT NULL,
+    color VARCHAR(7) NOT NULL DEFAULT '#6c757d',
+    initials VARCHAR(3) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    UNIQUE (household_id, display_name)
+);
+
+CREATE TABLE todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_id INTEGER NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    assignee_id INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    due_date DATE,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    recurrence TEXT,
+    next_occurrence_id INTEGER REFERENCES todos(id) ON DELETE SET NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+INSERT INTO lists (id, household_id, name, position, created_at, updated_at)
+SELECT id, (SELECT MIN(id) FROM households), name, position, created_at, updated_at FROM lists_old;
+
+INSERT INTO members (id, household_id, display_name, color, initials, created_at, updated_at)
+SELECT id, (SELECT MIN(id) FROM households), display_name, color, initials, created_at, updated_at FROM members_old;
+
+INSERT INTO todos (id, list_id, text, assignee_id, due_date, completed, recurrence, next_occurrence_id, version, created_at, updated_at)
+SELECT id, list_id, text, assignee_id, due_date, completed, recurrence, next_occurrence_id, version, created_at, updated_at FROM todos_old;
+
+DROP TABLE todos_old;
+DROP TABLE lists_old;
+DROP TABLE members_old;
+
+CREATE INDEX idx_todos_list_id ON todos(list_id);
+CREATE INDEX idx_todos_assignee_id ON todos(assignee_id);
+CREATE INDEX idx_todos_due_date ON todos(due_date);
+CREATE INDEX idx_todos_completed ON todos(completed);
+
+-- Events are only replayed to connections of the same household
+ALTER TABLE events ADD COLUMN household_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    email VARCHAR(254) NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_users_household_id ON users(household_id);
+
+CREATE TABLE sessions (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
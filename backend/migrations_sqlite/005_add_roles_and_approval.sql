@@ -0,0 +1,13 @@
+-- See migrations/008_add_roles_and_approval.sql
+ALTER TABLE members
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'parent'
+        CHECK (role IN ('parent', 'child', 'guest'));
+
+ALTER TABLE users
+    ADD COLUMN member_id INTEGER REFERENCES members(id) ON DELETE CASCADE;
+
+ALTER TABLE todos
+    ADD COLUMN needs_approval BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE todos
+    ADD COLUMN approval_requested_by INTEGER REFERENCES members(id) ON DELETE SET NULL;
@@ -0,0 +1,28 @@
+-- See migrations/009_add_points_and_rewards.sql
+ALTER TABLE todos
+    ADD COLUMN points INTEGER NOT NULL DEFAULT 0 CHECK (points >= 0);
+
+CREATE TABLE rewards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    cost INTEGER NOT NULL CHECK (cost > 0),
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_rewards_household_id ON rewards(household_id);
+
+CREATE TABLE point_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    member_id INTEGER NOT NULL REFERENCES members(id) ON DELETE CASCADE,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL CHECK (reason IN ('todo_completed', 'todo_reopened', 'reward_redeemed')),
+    description TEXT NOT NULL,
+    todo_id INTEGER REFERENCES todos(id) ON DELETE SET NULL,
+    reward_id INTEGER REFERENCES rewards(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_point_entries_member_id ON point_entries(member_id);
+CREATE INDEX idx_point_entries_todo_id ON point_entries(todo_id);
@@ -0,0 +1,25 @@
+-- See migrations/010_add_chore_rotations.sql
+ALTER TABLE members
+    ADD COLUMN away BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE rotations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    skip_away BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_rotations_household_id ON rotations(household_id);
+
+CREATE TABLE rotation_members (
+    rotation_id INTEGER NOT NULL REFERENCES rotations(id) ON DELETE CASCADE,
+    member_id INTEGER NOT NULL REFERENCES members(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (rotation_id, member_id)
+);
+
+CREATE INDEX idx_rotation_members_member_id ON rotation_members(member_id);
+
+ALTER TABLE todos
+    ADD COLUMN rotation_id INTEGER REFERENCES rotations(id) ON DELETE SET NULL;
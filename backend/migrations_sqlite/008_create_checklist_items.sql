@@ -0,0 +1,15 @@
+-- See migrations/011_create_checklist_items.sql
+CREATE TABLE checklist_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    position INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_checklist_items_todo_id ON checklist_items(todo_id);
+
+ALTER TABLE todos
+    ADD COLUMN auto_complete BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- See migrations/012_create_todo_comments.sql
+CREATE TABLE todo_comments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    author_id INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    text TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_todo_comments_todo_id ON todo_comments(todo_id);
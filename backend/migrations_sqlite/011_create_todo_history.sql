@@ -0,0 +1,15 @@
+-- See migrations/014_create_todo_history.sql
+CREATE TABLE todo_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+    todo_id INTEGER NOT NULL,
+    todo_text TEXT NOT NULL,
+    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    member_id INTEGER REFERENCES members(id) ON DELETE SET NULL,
+    action VARCHAR(50) NOT NULL,
+    changes TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_todo_history_todo_id ON todo_history(todo_id);
+CREATE INDEX idx_todo_history_household_id ON todo_history(household_id, id);
@@ -0,0 +1,4 @@
+-- See migrations/015_add_deleted_at_to_todos.sql
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX idx_todos_deleted_at ON todos(deleted_at) WHERE deleted_at IS NOT NULL;
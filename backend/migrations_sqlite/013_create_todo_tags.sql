@@ -0,0 +1,8 @@
+-- See migrations/016_create_todo_tags.sql
+CREATE TABLE todo_tags (
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    tag VARCHAR(30) NOT NULL,
+    PRIMARY KEY (todo_id, tag)
+);
+
+CREATE INDEX idx_todo_tags_tag ON todo_tags(tag);
@@ -0,0 +1,7 @@
+-- See migrations/018_add_completed_at_to_todos.sql
+ALTER TABLE todos ADD COLUMN completed_at TIMESTAMP;
+
+UPDATE todos SET completed_at = updated_at WHERE completed;
+
+CREATE INDEX idx_todos_due_date_id ON todos(due_date, id);
+CREATE INDEX idx_todos_created_at_id ON todos(created_at, id);
@@ -0,0 +1,4 @@
+-- See migrations/019_add_due_time_and_household_timezone.sql
+ALTER TABLE todos ADD COLUMN due_time TIME;
+
+ALTER TABLE households ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
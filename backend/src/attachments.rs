@@ -0,0 +1,230 @@
+//! Files attached to todos, such as a photo of a permission slip.
+//!
+//! Files live in a local directory named after their SHA-256 hash, so the
+//! same photo attached twice is stored once. Images also get a small PNG
+//! thumbnail next to them. The database only records which todo a file
+//! belongs to; [`AttachmentStorage::remove_unused`] deletes files no
+//! attachment refers to any more.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::store::TodoStore;
+
+/// Where attachments went before they had to live outside the checkout;
+/// only looked at to warn that files may still be there.
+const OLD_ATTACHMENTS_DIR: &str = "attachments";
+
+/// 10 MiB, enough for a phone photo.
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Thumbnails fit in a square this many pixels wide.
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// What may be uploaded. Nothing a browser would run, such as HTML or SVG.
+pub const ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "text/plain",
+];
+
+/// Where attachments go without `ATTACHMENTS_DIR`: the user's data directory
+/// (`$XDG_DATA_HOME`, or else `~/.local/share`), so uploads never sit inside
+/// the checkout next to the files the server publishes.
+pub fn default_dir() -> PathBuf {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("/var/lib"));
+
+    data_home.join("family-todo").join("attachments")
+}
+
+const THUMBNAIL_SUFFIX: &str = ".thumb.png";
+
+pub struct AttachmentStorage {
+    dir: PathBuf,
+    max_bytes: usize,
+}
+
+impl AttachmentStorage {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: usize) -> Self {
+        Self { dir: dir.into(), max_bytes }
+    }
+
+    /// Reads `ATTACHMENTS_DIR` and `MAX_ATTACHMENT_BYTES`, falling back to
+    /// the defaults.
+    pub fn from_env() -> Self {
+        let dir = match std::env::var_os("ATTACHMENTS_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                if Path::new(OLD_ATTACHMENTS_DIR).is_dir() {
+                    tracing::warn!(
+                        "./{} is no longer used, set ATTACHMENTS_DIR to keep the files in it",
+                        OLD_ATTACHMENTS_DIR
+                    );
+                }
+                default_dir()
+            }
+        };
+        let max_bytes = std::env::var("MAX_ATTACHMENT_BYTES")
+            .ok()
+            .and_then(|bytes| bytes.parse().ok())
+            .filter(|&bytes| bytes > 0)
+            .unwrap_or(DEFAULT_MAX_ATTACHMENT_BYTES);
+
+        Self::new(dir, max_bytes)
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Stores a file and its thumbnail, if any, returning the file's hash.
+    /// Files are written under a temporary name and renamed into place, so a
+    /// half-written file is never served.
+    pub async fn save(&self, bytes: &[u8], thumbnail: Option<&[u8]>) -> Result<String> {
+        let sha256 = hex::encode(Sha256::digest(bytes));
+        tokio::fs::create_dir_all(&self.dir).await?;
+
+        self.write(&self.file_path(&sha256), bytes).await?;
+        if let Some(thumbnail) = thumbnail {
+            self.write(&self.thumbnail_path(&sha256), thumbnail).await?;
+        }
+
+        Ok(sha256)
+    }
+
+    pub async fn read(&self, sha256: &str) -> Result<Vec<u8>> {
+        Ok(tokio::fs::read(self.file_path(sha256)).await?)
+    }
+
+    pub async fn read_thumbnail(&self, sha256: &str) -> Result<Vec<u8>> {
+        Ok(tokio::fs::read(self.thumbnail_path(sha256)).await?)
+    }
+
+    /// Deletes the files behind `hashes` that no attachment uses any more.
+    pub async fn remove_unused(&self, db: &dyn TodoStore, hashes: impl IntoIterator<Item = String>) -> Result<()> {
+        for sha256 in hashes {
+            if db.attachment_in_use(&sha256).await? {
+                continue;
+            }
+            for path in [self.file_path(&sha256), self.thumbnail_path(&sha256)] {
+                match tokio::fs::remove_file(path).await {
+                    Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes every stored file no attachment uses, such as those of
+    /// deleted todos.
+    pub async fn remove_orphans(&self, db: &dyn TodoStore) -> Result<()> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut hashes = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.len() == 64 && name.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                hashes.push(name);
+            }
+        }
+
+        self.remove_unused(db, hashes).await
+    }
+
+    async fn write(&self, path: &PathBuf, bytes: &[u8]) -> Result<()> {
+        if tokio::fs::try_exists(path).await? {
+            return Ok(());
+        }
+
+        let partial = self.dir.join(format!(".{}.partial", Uuid::new_v4()));
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    fn file_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(sha256)
+    }
+
+    fn thumbnail_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(format!("{}{}", sha256, THUMBNAIL_SUFFIX))
+    }
+}
+
+pub fn is_allowed_content_type(content_type: &str) -> bool {
+    ALLOWED_CONTENT_TYPES.contains(&content_type)
+}
+
+/// Shrinks an image to fit [`THUMBNAIL_SIZE`], as PNG. `None` if the bytes
+/// are not an image we can read. This is CPU-bound, so call it off the
+/// async runtime.
+pub fn make_thumbnail(bytes: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(bytes).ok()?;
+    let mut png = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .ok()?;
+
+    Some(png)
+}
+
+/// Keeps only the last path segment of an uploaded file name and drops
+/// characters that could upset a `Content-Disposition` header.
+pub fn clean_file_name(file_name: Option<&str>) -> String {
+    let name: String = file_name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect();
+
+    match name.trim() {
+        "" => "file".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_lose_their_paths() {
+        assert_eq!(clean_file_name(Some("C:\\Users\\sam\\slip.jpg")), "slip.jpg");
+        assert_eq!(clean_file_name(Some("../../etc/passwd")), "passwd");
+        assert_eq!(clean_file_name(Some("say \"hi\".txt")), "say hi.txt");
+        assert_eq!(clean_file_name(None), "file");
+    }
+
+    #[test]
+    fn thumbnails_fit_the_box() {
+        let image = image::RgbImage::new(1024, 512);
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+
+        let thumbnail = image::load_from_memory(&make_thumbnail(&png).unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+        assert!(make_thumbnail(b"not an image").is_none());
+    }
+}
@@ -0,0 +1,200 @@
+//! Accounts and sessions: password hashing, the session cookie and the
+//! middleware that keeps signed-out clients away from the API.
+//!
+//! Passwords are stored as PBKDF2-HMAC-SHA256 hashes. A session is a random
+//! token in an `HttpOnly` cookie; the database only holds its SHA-256.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Request},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{Duration, Utc};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::error::AppError;
+use crate::models::{Role, User};
+use crate::store::TodoStore;
+
+pub const SESSION_COOKIE: &str = "session";
+
+/// How long a sign-in lasts.
+pub const SESSION_DAYS: i64 = 30;
+
+/// Default PBKDF2 rounds for new hashes, per the OWASP recommendation for
+/// HMAC-SHA256.
+const DEFAULT_PASSWORD_ROUNDS: u32 = 600_000;
+
+const PASSWORD_SCHEME: &str = "pbkdf2-sha256";
+
+lazy_static::lazy_static! {
+    /// Rounds for new hashes, from `PASSWORD_ROUNDS` if set. Stored hashes
+    /// record their own count, so this can be changed at any time.
+    static ref PASSWORD_ROUNDS: u32 = std::env::var("PASSWORD_ROUNDS")
+        .ok()
+        .and_then(|rounds| rounds.parse().ok())
+        .filter(|&rounds| rounds > 0)
+        .unwrap_or(DEFAULT_PASSWORD_ROUNDS);
+
+    /// Checked against when the email is unknown, so a failed sign-in takes
+    /// as long whether or not the account exists.
+    static ref DUMMY_HASH: String = hash_password("not a real password");
+}
+
+/// Hashes a password with a fresh salt, as `pbkdf2-sha256$<rounds>$<salt>$<hash>`.
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let rounds = *PASSWORD_ROUNDS;
+    let hash = pbkdf2_sha256(password.as_bytes(), &salt, rounds);
+
+    format!("{}${}${}${}", PASSWORD_SCHEME, rounds, hex::encode(salt), hex::encode(hash))
+}
+
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let mut parts = stored.split('$');
+    let (Some(PASSWORD_SCHEME), Some(rounds), Some(salt), Some(expected), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let (Ok(rounds), Ok(salt), Ok(expected)) = (rounds.parse(), hex::decode(salt), hex::decode(expected)) else {
+        return false;
+    };
+
+    pbkdf2_sha256(password.as_bytes(), &salt, rounds).ct_eq(&expected).into()
+}
+
+/// Checks a sign-in attempt, spending the same effort when there is no such user.
+pub fn check_credentials(user: Option<User>, password: &str) -> Option<User> {
+    match user {
+        Some(user) if verify_password(password, &user.password_hash) => Some(user),
+        Some(_) => None,
+        None => {
+            verify_password(password, &DUMMY_HASH);
+            None
+        }
+    }
+}
+
+/// PBKDF2 (RFC 8018) with HMAC-SHA256, producing a single 32-byte block.
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password, salt, rounds)
+}
+
+/// A new random session token for the cookie.
+pub fn new_session_token() -> String {
+    let mut token = [0u8; 32];
+    OsRng.fill_bytes(&mut token);
+    hex::encode(token)
+}
+
+/// What the database stores in place of a session token.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// The session token from the request's cookies, if any.
+pub fn session_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token.to_string())
+}
+
+/// Starts a session for `user` and returns the `Set-Cookie` value carrying it.
+pub async fn start_session(db: &Arc<dyn TodoStore>, user: &User) -> Result<HeaderValue, AppError> {
+    let token = new_session_token();
+    db.create_session(&hash_token(&token), user.id, Utc::now() + Duration::days(SESSION_DAYS))
+        .await?;
+
+    let cookie = format!(
+        "{}={}; HttpOnly; SameSite=Lax; Path=/; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        SESSION_DAYS * 24 * 60 * 60
+    );
+    Ok(HeaderValue::from_str(&cookie).expect("session cookies are ASCII"))
+}
+
+/// A `Set-Cookie` value that removes the session cookie.
+pub fn clear_session_cookie() -> HeaderValue {
+    HeaderValue::from_str(&format!("{}=; HttpOnly; SameSite=Lax; Path=/; Max-Age=0", SESSION_COOKIE))
+        .expect("session cookies are ASCII")
+}
+
+/// Rejects requests without a valid session cookie and makes the signed-in
+/// [`User`] available to handlers as an extension.
+pub async fn require_session(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let token = session_token(request.headers()).ok_or(AppError::Unauthorized("Not signed in"))?;
+    let user = db
+        .get_session_user(&hash_token(&token))
+        .await?
+        .ok_or(AppError::Unauthorized("Session expired, please sign in again"))?;
+
+    request.extensions_mut().insert(user);
+    Ok(next.run(request).await)
+}
+
+/// Fails with `403 forbidden` unless the user is a parent.
+pub fn require_parent(user: &User) -> Result<(), AppError> {
+    match user.role {
+        Role::Parent => Ok(()),
+        Role::Child | Role::Guest => Err(AppError::Forbidden("Only parents can do that")),
+    }
+}
+
+/// Fails with `403 forbidden` for guests, who can only look.
+pub fn require_editor(user: &User) -> Result<(), AppError> {
+    match user.role {
+        Role::Parent | Role::Child => Ok(()),
+        Role::Guest => Err(AppError::Forbidden("Guests cannot make changes")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pbkdf2_matches_known_vectors() {
+        // The RFC 6070 inputs, with SHA-256 in place of SHA-1
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+
+    #[test]
+    fn test_verify_password() {
+        let hash = hash_password("correct horse");
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "plaintext"));
+    }
+
+    #[test]
+    fn test_session_token_is_read_from_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark; session=abc123"));
+        assert_eq!(session_token(&headers).as_deref(), Some("abc123"));
+        assert_eq!(session_token(&HeaderMap::new()), None);
+    }
+}
@@ -1,42 +1,290 @@
+use std::future::Future;
+
 use axum::{
-    http::StatusCode,
+    async_trait,
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Multipart, Request,
+    },
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use family_todo_core::ValidationError;
 use serde_json::json;
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::Todo;
+use crate::store::{is_unique_violation, InsufficientPoints, StaleVersion};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
 
+/// Every error a handler can return. The JSON body always carries a
+/// human-readable `error`, a stable `code` clients can branch on and the
+/// `request_id` that also appears in the server logs:
+///
+/// ```json
+/// {"error": "Invalid request", "code": "validation_failed",
+///  "fields": [{"field": "text", "message": "Task text cannot be empty"}],
+///  "request_id": "4b0c..."}
+/// ```
 #[derive(Error, Debug)]
 pub enum AppError {
-    #[error("Database error: {0}")]
-    Database(#[from] sqlx::Error),
-    
-    #[error("Validation error: {0}")]
-    Validation(String),
-    
-    #[error("Not found: {0}")]
-    NotFound(String),
-    
+    #[error("Invalid request")]
+    Validation(Vec<ValidationError>),
+
+    #[error("{0}")]
+    BadRequest(String),
+
+    /// No valid session, or a failed sign-in.
+    #[error("{0}")]
+    Unauthorized(&'static str),
+
+    /// Signed in, but the member's role does not allow this.
+    #[error("{0}")]
+    Forbidden(&'static str),
+
+    #[error("{0} not found")]
+    NotFound(&'static str),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    /// An upload over the attachment size limit.
+    #[error("{0}")]
+    PayloadTooLarge(String),
+
+    /// The client's `If-Match` version is out of date. The response carries
+    /// the current todo as `current` so the client can merge.
+    #[error("Todo was changed by someone else")]
+    StaleVersion(Box<Todo>),
+
     #[error("Internal server error")]
-    Internal,
+    Internal(anyhow::Error),
+}
+
+impl AppError {
+    pub fn field(field: &'static str, message: impl Into<String>) -> Self {
+        AppError::Validation(vec![ValidationError::new(field, message)])
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) | AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) | AppError::StaleVersion(_) => StatusCode::CONFLICT,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable error code. These are part of the API; don't rename them.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_failed",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::StaleVersion(_) => "version_conflict",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+/// Store failures; unique-name clashes and stale versions become conflicts,
+/// anything else is internal.
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<StaleVersion>() {
+            Ok(stale) => return AppError::StaleVersion(Box::new(stale.current)),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<InsufficientPoints>() {
+            Ok(short) => return AppError::Conflict(format!("Not enough points ({} available)", short.balance)),
+            Err(error) => error,
+        };
+
+        if is_unique_violation(&error) {
+            AppError::Conflict("That name is already taken".to_string())
+        } else {
+            AppError::Internal(error)
+        }
+    }
+}
+
+impl From<ValidationError> for AppError {
+    fn from(error: ValidationError) -> Self {
+        AppError::Validation(vec![error])
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<MultipartRejection> for AppError {
+    fn from(rejection: MultipartRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+/// A broken multipart body, or one cut off by the body size limit.
+impl From<MultipartError> for AppError {
+    fn from(error: MultipartError) -> Self {
+        if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            AppError::PayloadTooLarge(error.body_text())
+        } else {
+            AppError::BadRequest(error.body_text())
+        }
+    }
+}
+
+impl AppError {
+    /// The JSON error body, for REST responses and WebSocket replies alike.
+    /// Internal errors are logged here along with the request id.
+    pub fn to_json(&self) -> serde_json::Value {
+        let request_id = current_request_id();
+
+        if let AppError::Internal(ref error) = self {
+            tracing::error!(request_id = request_id.as_deref(), "Internal error: {:#}", error);
+        }
+
+        let mut body = json!({
+            "error": self.to_string(),
+            "code": self.code(),
+            "request_id": request_id,
+        });
+        match self {
+            AppError::Validation(ref fields) => body["fields"] = json!(fields),
+            AppError::StaleVersion(ref current) => body["current"] = json!(current),
+            _ => {}
+        }
+
+        body
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Database(e) => {
-                tracing::error!("Database error: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+        let mut response = (self.status(), Json(self.to_json())).into_response();
+        if let AppError::StaleVersion(ref current) = self {
+            if let Ok(etag) = HeaderValue::from_str(&current.etag()) {
+                response.headers_mut().insert(header::ETAG, etag);
             }
-            AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.as_str()),
-            AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
-        };
+        }
+
+        response
+    }
+}
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+/// Collects field errors so a request reports all of its problems at once.
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<ValidationError>);
 
-        (status, body).into_response()
+impl FieldErrors {
+    pub fn check(&mut self, result: Result<(), ValidationError>) {
+        if let Err(error) = result {
+            self.0.push(error);
+        }
     }
-}
\ No newline at end of file
+
+    pub fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        self.0.push(ValidationError::new(field, message));
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.0))
+        }
+    }
+}
+
+/// `axum::Json` for request bodies, rejecting malformed input with an [`AppError`].
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct AppJson<T>(pub T);
+
+/// `axum::extract::Path` with [`AppError`] rejections.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct AppPath<T>(pub T);
+
+/// `axum::extract::Query` with [`AppError`] rejections.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct AppQuery<T>(pub T);
+
+/// `axum::extract::Multipart` with [`AppError`] rejections.
+pub struct AppMultipart(pub Multipart);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for AppMultipart {
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(AppMultipart(Multipart::from_request(request, state).await?))
+    }
+}
+
+/// Gives every request an id, reusing the client's `x-request-id` when it
+/// sends a sensible one, and echoes it on the response.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(accept_request_id)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = with_request_id(request_id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+/// A client-chosen request id, unless it is empty or unreasonably long.
+pub fn accept_request_id(value: &str) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty() && value.len() <= 64)
+}
+
+/// Runs `future` as the handling of request `request_id`, which then shows
+/// up in its error bodies and logs.
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// The id of the request being handled, if called from inside [`request_id`] or
+/// [`with_request_id`].
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
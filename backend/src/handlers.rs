@@ -1,89 +1,1568 @@
 use axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
-    response::Json,
+    async_trait,
+    extract::{Extension, FromRequestParts},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use std::sync::Arc;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::database::Database;
-use crate::models::{Todo, CreateTodoRequest, UpdateTodoRequest, TodosGroupedByDate};
+use crate::auth::{
+    self, check_credentials, clear_session_cookie, hash_password, require_editor, require_parent, start_session,
+};
+use crate::attachments::{clean_file_name, is_allowed_content_type, make_thumbnail, AttachmentStorage};
+use crate::error::{AppError, AppJson, AppMultipart, AppPath, AppQuery, FieldErrors};
+use crate::history;
+use crate::store::{group_todos_by_bucket, group_todos_by_date, TodoStore};
+use crate::models::{
+    Todo, CreateTodoRequest, UpdateTodoRequest, ReplaceTodoRequest, TodosGroupedByDate, TodosGroupedByBucket, TodoFilter, TodoCursor,
+    Member, CreateMemberRequest, UpdateMemberRequest,
+    TodoList, CreateListRequest, UpdateListRequest,
+    Role, User, Household, UpdateHouseholdRequest, Account, RegisterRequest, LoginRequest, CreateUserRequest,
+    Reward, CreateRewardRequest, UpdateRewardRequest, RedeemRewardRequest, PointEntry, PointBalance,
+    Rotation, CreateRotationRequest, UpdateRotationRequest,
+    CreateChecklistItemRequest, UpdateChecklistItemRequest, TodoComment, CreateCommentRequest,
+    Attachment, NewAttachment, HistoryAction, HistoryEntry, SearchHit,
+};
+use family_todo_core::{now_in, parse_timezone, search_terms, Tz};
+use family_todo_core::validation::{
+    validate_color, validate_comment_text, validate_display_name, validate_due_time, validate_email,
+    validate_household_name, validate_initials, validate_list_name, validate_password,
+    validate_points, validate_recurrence, validate_reward_cost, validate_reward_name,
+    validate_rotation_members, validate_timezone, validate_todo_text, normalize_tags, validate_tags,
+};
 use crate::websocket::broadcast_todo_update;
 
+/// How `GET /api/todos` lays out the todos it returns.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// The whiteboard's groups, one per due date.
+    #[default]
+    Date,
+    /// A single list in the filter's sort order.
+    None,
+    /// Overdue, Today, Tomorrow, This Week, Later and No Due Date.
+    Bucket,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TodosView {
+    #[serde(default)]
+    pub group_by: GroupBy,
+    /// The day buckets are relative to, from its start; defaults to now in
+    /// `timezone`.
+    pub today: Option<NaiveDate>,
+    /// An IANA timezone such as `Europe/London`; defaults to the household's.
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum TodoListing {
+    Grouped(Vec<TodosGroupedByDate>),
+    Flat(Vec<Todo>),
+    Bucketed(Vec<TodosGroupedByBucket>),
+    /// One page of either, when `limit` is given; `next_cursor` is `null`
+    /// on the last page.
+    Page { todos: Box<TodoListing>, next_cursor: Option<TodoCursor> },
+}
+
+/// Largest page of todos `limit` can ask for.
+pub const MAX_PAGE_SIZE: usize = 200;
+
+/// Days completed todos stay in listings unless `HIDE_COMPLETED_AFTER_DAYS`
+/// says otherwise.
+pub const DEFAULT_HIDE_COMPLETED_AFTER_DAYS: i64 = 30;
+
+lazy_static::lazy_static! {
+    /// How long completed todos stay in listings; `HIDE_COMPLETED_AFTER_DAYS=0`
+    /// keeps them for good.
+    static ref HIDE_COMPLETED_AFTER: Option<chrono::Duration> = {
+        let days = std::env::var("HIDE_COMPLETED_AFTER_DAYS")
+            .ok()
+            .and_then(|days| days.parse().ok())
+            .filter(|&days: &i64| days >= 0)
+            .unwrap_or(DEFAULT_HIDE_COMPLETED_AFTER_DAYS);
+        (days > 0).then(|| chrono::Duration::days(days))
+    };
+}
+
+/// Completed todos finished before this are left out of listings, unless
+/// they are asked for.
+pub(crate) fn completed_cutoff() -> Option<DateTime<Utc>> {
+    HIDE_COMPLETED_AFTER.map(|after| Utc::now() - after)
+}
+
 pub async fn get_todos(
-    Extension(db): Extension<Arc<Database>>,
-) -> Result<Json<Vec<TodosGroupedByDate>>, StatusCode> {
-    match db.get_todos_grouped_by_date().await {
-        Ok(todos) => Ok(Json(todos)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppQuery(filter): AppQuery<TodoFilter>,
+    AppQuery(view): AppQuery<TodosView>,
+) -> Result<Json<TodoListing>, AppError> {
+    let mut errors = FieldErrors::default();
+    if let Some(list_id) = filter.list_id {
+        resolve_list(&db, user.household_id, Some(list_id), &mut errors).await?;
+    }
+    check_due_range(&filter, &mut errors);
+    check_page(&filter, &mut errors);
+    let now = bucket_now(&db, user.household_id, &view, &mut errors).await?;
+    errors.finish()?;
+
+    list_todos(&db, user.household_id, filter, view.group_by, now).await.map(Json)
+}
+
+pub async fn get_list_todos(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(list_id): AppPath<i32>,
+    AppQuery(filter): AppQuery<TodoFilter>,
+    AppQuery(view): AppQuery<TodosView>,
+) -> Result<Json<TodoListing>, AppError> {
+    require_list(&db, user.household_id, list_id).await?;
+
+    let mut errors = FieldErrors::default();
+    check_due_range(&filter, &mut errors);
+    check_page(&filter, &mut errors);
+    let now = bucket_now(&db, user.household_id, &view, &mut errors).await?;
+    errors.finish()?;
+
+    let filter = TodoFilter { list_id: Some(list_id), ..filter };
+    list_todos(&db, user.household_id, filter, view.group_by, now).await.map(Json)
+}
+
+/// `group_by=bucket` buckets due dates relative to `now`.
+async fn list_todos(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    mut filter: TodoFilter,
+    group_by: GroupBy,
+    now: NaiveDateTime,
+) -> Result<TodoListing, AppError> {
+    if !filter.include_old_completed {
+        filter.completed_since = completed_cutoff();
+    }
+
+    // One more than asked for tells whether there is another page
+    let page_size = filter.limit;
+    filter.limit = page_size.map(|size| size + 1);
+    let mut todos = db.find_todos(household_id, &filter).await?;
+
+    let next_cursor = match page_size {
+        Some(size) if todos.len() > size => {
+            todos.truncate(size);
+            todos.last().map(|todo| TodoCursor::after(filter.sort.unwrap_or_default(), todo))
+        }
+        _ => None,
+    };
+    let listing = match group_by {
+        GroupBy::Date => TodoListing::Grouped(group_todos_by_date(todos, filter.sort)),
+        GroupBy::None => TodoListing::Flat(todos),
+        GroupBy::Bucket => TodoListing::Bucketed(group_todos_by_bucket(todos, now, filter.sort)),
+    };
+
+    Ok(match page_size {
+        Some(_) => TodoListing::Page { todos: Box::new(listing), next_cursor },
+        None => listing,
+    })
+}
+
+/// The wall-clock time a listing's buckets are relative to: the start of
+/// `today` if given, otherwise now in `timezone` or the household's.
+async fn bucket_now(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    view: &TodosView,
+    errors: &mut FieldErrors,
+) -> Result<NaiveDateTime, AppError> {
+    if let Some(today) = view.today {
+        return Ok(today.and_time(NaiveTime::MIN));
+    }
+    let timezone = match &view.timezone {
+        Some(timezone) => parse_timezone(timezone),
+        None => Ok(household_timezone(db, household_id).await?),
+    };
+    Ok(match timezone {
+        Ok(timezone) => now_in(timezone, Utc::now()),
+        Err(error) => {
+            errors.check(Err(error));
+            Utc::now().naive_utc()
+        }
+    })
+}
+
+/// The household's timezone; one that no longer parses reads as UTC.
+async fn household_timezone(db: &Arc<dyn TodoStore>, household_id: i32) -> Result<Tz, AppError> {
+    let household = db.get_household(household_id).await?.ok_or(AppError::NotFound("Household"))?;
+
+    Ok(parse_timezone(&household.timezone).unwrap_or(Tz::UTC))
+}
+
+fn check_page(filter: &TodoFilter, errors: &mut FieldErrors) {
+    let sort = filter.sort.unwrap_or_default();
+    if let Some(limit) = filter.limit {
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            errors.add("limit", format!("must be between 1 and {}", MAX_PAGE_SIZE));
+        }
+        if !sort.key.is_pageable() {
+            errors.add("sort", "must be created_at or due_date to page through todos");
+        }
+    }
+    if let Some(cursor) = &filter.cursor {
+        if filter.limit.is_none() {
+            errors.add("cursor", "needs a limit");
+        } else if cursor.sort != sort {
+            errors.add("cursor", format!("was handed out for sort={}, not sort={}", cursor.sort, sort));
+        }
+    }
+}
+
+fn check_due_range(filter: &TodoFilter, errors: &mut FieldErrors) {
+    if let (Some(from), Some(to)) = (filter.due_from, filter.due_to) {
+        if to < from {
+            errors.add("due_to", "must not be before due_from");
+        }
     }
 }
 
+pub async fn get_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<TodoResponse, AppError> {
+    let todo = db.get_todo_by_id(user.household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+
+    Ok(TodoResponse(todo))
+}
+
 pub async fn create_todo(
-    Extension(db): Extension<Arc<Database>>,
-    Json(request): Json<CreateTodoRequest>,
-) -> Result<Json<Todo>, StatusCode> {
-    // Basic validation
-    if request.text.trim().is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    
-    match db.create_todo(request).await {
-        Ok(todo) => {
-            // Broadcast the new todo to all connected clients
-            broadcast_todo_update("todo_created", json!(todo));
-            Ok(Json(todo))
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(request): AppJson<CreateTodoRequest>,
+) -> Result<TodoResponse, AppError> {
+    create_todo_in_list(&db, &user, request.list_id, request).await.map(TodoResponse)
+}
+
+pub async fn create_list_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(list_id): AppPath<i32>,
+    AppJson(request): AppJson<CreateTodoRequest>,
+) -> Result<TodoResponse, AppError> {
+    require_list(&db, user.household_id, list_id).await?;
+    create_todo_in_list(&db, &user, Some(list_id), request).await.map(TodoResponse)
+}
+
+/// Validates and creates a todo, then broadcasts it. Shared by the REST
+/// handlers and WebSocket commands.
+pub(crate) async fn create_todo_in_list(
+    db: &Arc<dyn TodoStore>,
+    user: &User,
+    list_id: Option<i32>,
+    mut request: CreateTodoRequest,
+) -> Result<Todo, AppError> {
+    require_editor(user)?;
+    if user.role == Role::Child {
+        if request.assignee_id.is_some_and(|id| Some(id) != user.member_id) {
+            return Err(AppError::Forbidden("Children can only assign todos to themselves"));
+        }
+        if request.points != 0 {
+            return Err(AppError::Forbidden("Only parents can set points"));
+        }
+        if request.rotation_id.is_some() {
+            return Err(AppError::Forbidden("Only parents can put todos on a rotation"));
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+    let household_id = user.household_id;
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_todo_text(&request.text));
+    errors.check(validate_points(request.points));
+    errors.check(validate_recurrence(request.recurrence.as_ref(), request.due_date));
+    errors.check(validate_due_time(request.due_time, request.due_date));
+    request.tags = normalize_tags(&request.tags);
+    errors.check(validate_tags(&request.tags));
+    check_member(db, household_id, "assignee_id", request.assignee_id, &mut errors).await?;
+    check_rotation(db, household_id, request.rotation_id, &mut errors).await?;
+    let list_id = resolve_list(db, household_id, list_id, &mut errors).await?;
+    errors.finish()?;
+    let list_id = list_id.expect("a missing list is reported as a field error");
+
+    let todo = db.create_todo(list_id, request).await?;
+    history::record(db, user, HistoryAction::Created, None, Some(&todo)).await;
+
+    // Broadcast the new todo to clients following its list
+    broadcast_todo_update(db, household_id, "todo_created", Some(todo.list_id), json!(todo)).await;
+    Ok(todo)
 }
 
+/// `PUT`: replaces the todo's contents, clearing optional fields left out.
+pub async fn replace_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    IfMatch(expected_version): IfMatch,
+    AppJson(request): AppJson<ReplaceTodoRequest>,
+) -> Result<TodoResponse, AppError> {
+    apply_todo_update(&db, &user, id, request.into(), expected_version).await.map(TodoResponse)
+}
+
+/// `PATCH`: changes only the fields present in the body; `null` clears one.
 pub async fn update_todo(
-    Extension(db): Extension<Arc<Database>>,
-    Path(id): Path<i32>,
-    Json(request): Json<UpdateTodoRequest>,
-) -> Result<Json<Todo>, StatusCode> {
-    // Validate text if provided
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    IfMatch(expected_version): IfMatch,
+    AppJson(request): AppJson<UpdateTodoRequest>,
+) -> Result<TodoResponse, AppError> {
+    apply_todo_update(&db, &user, id, request, expected_version).await.map(TodoResponse)
+}
+
+pub(crate) async fn apply_todo_update(
+    db: &Arc<dyn TodoStore>,
+    user: &User,
+    id: i32,
+    mut request: UpdateTodoRequest,
+    expected_version: Option<i32>,
+) -> Result<Todo, AppError> {
+    require_editor(user)?;
+    let household_id = user.household_id;
+    let current = db.get_todo_by_id(household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+    if user.role == Role::Child {
+        check_child_update(&current, &request)?;
+    }
+
+    // Validate the fields being set, then the todo they would produce
+    let mut errors = FieldErrors::default();
     if let Some(ref text) = request.text {
-        if text.trim().is_empty() {
-            return Err(StatusCode::BAD_REQUEST);
-        }
+        errors.check(validate_todo_text(text));
+    }
+    if let Some(points) = request.points {
+        errors.check(validate_points(points));
+    }
+    if let Some(ref mut tags) = request.tags {
+        *tags = normalize_tags(tags);
+        errors.check(validate_tags(tags));
     }
-    
-    match db.update_todo(id, request).await {
-        Ok(Some(todo)) => {
-            broadcast_todo_update("todo_updated", json!(todo));
-            Ok(Json(todo))
+    let recurrence = request.recurrence.clone().apply(current.recurrence.clone().map(|rule| rule.0));
+    let due_date = request.due_date.clone().apply(current.due_date);
+    errors.check(validate_recurrence(recurrence.as_ref(), due_date));
+    errors.check(validate_due_time(request.due_time.as_value().copied(), due_date));
+    check_member(db, household_id, "assignee_id", request.assignee_id.as_value().copied(), &mut errors).await?;
+    check_rotation(db, household_id, request.rotation_id.as_value().copied(), &mut errors).await?;
+    if let Some(list_id) = request.list_id {
+        resolve_list(db, household_id, Some(list_id), &mut errors).await?;
+    }
+    errors.finish()?;
+
+    // Completion goes through the toggle, so it is credited and recurs
+    // exactly as if the todo had been ticked
+    let completed = request.completed.take();
+    let mut todo = current.clone();
+    if request != UpdateTodoRequest::default() {
+        todo = db
+            .update_todo(household_id, id, request, expected_version)
+            .await?
+            .ok_or(AppError::NotFound("Todo"))?;
+        history::record(db, user, HistoryAction::Updated, Some(&current), Some(&todo)).await;
+
+        // Moving a todo to another list removes it from the old list's view
+        if current.list_id != todo.list_id {
+            broadcast_todo_update(db, household_id, "todo_deleted", Some(current.list_id), json!({"id": id})).await;
         }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        broadcast_todo_update(db, household_id, "todo_updated", Some(todo.list_id), json!(todo)).await;
+    } else if expected_version.is_some_and(|version| version != current.version) {
+        return Err(AppError::StaleVersion(Box::new(current)));
     }
+
+    if completed.is_some_and(|completed| completed != todo.completed) {
+        todo = apply_toggle(db, user, id, Some(todo.version)).await?;
+    }
+    Ok(todo)
+}
+
+/// Children may not reassign a todo, change its points, rotation or whether
+/// it needs approval, or complete one that does other than by toggling it.
+fn check_child_update(current: &Todo, request: &UpdateTodoRequest) -> Result<(), AppError> {
+    if request.assignee_id.clone().apply(current.assignee_id) != current.assignee_id {
+        return Err(AppError::Forbidden("Children cannot reassign todos"));
+    }
+    if request.points.is_some_and(|points| points != current.points) {
+        return Err(AppError::Forbidden("Only parents can set points"));
+    }
+    if request.rotation_id.clone().apply(current.rotation_id) != current.rotation_id {
+        return Err(AppError::Forbidden("Only parents can put todos on a rotation"));
+    }
+    if request.needs_approval.is_some_and(|needs_approval| needs_approval != current.needs_approval) {
+        return Err(AppError::Forbidden("Only parents can change whether a todo needs approval"));
+    }
+    if current.needs_approval && request.completed.is_some_and(|completed| completed != current.completed) {
+        return Err(AppError::Forbidden("This todo needs a parent's approval to be completed"));
+    }
+    Ok(())
 }
 
 pub async fn toggle_todo(
-    Extension(db): Extension<Arc<Database>>,
-    Path(id): Path<i32>,
-) -> Result<Json<Todo>, StatusCode> {
-    match db.toggle_todo(id).await {
-        Ok(Some(todo)) => {
-            broadcast_todo_update("todo_toggled", json!(todo));
-            Ok(Json(todo))
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    IfMatch(expected_version): IfMatch,
+) -> Result<TodoResponse, AppError> {
+    apply_toggle(&db, &user, id, expected_version).await.map(TodoResponse)
+}
+
+/// Toggles a todo. A child ticking off a todo that needs approval only asks
+/// for it (or, asked already, withdraws the request).
+pub(crate) async fn apply_toggle(
+    db: &Arc<dyn TodoStore>,
+    user: &User,
+    id: i32,
+    expected_version: Option<i32>,
+) -> Result<Todo, AppError> {
+    require_editor(user)?;
+    let household_id = user.household_id;
+
+    if user.role == Role::Child {
+        let current = db.get_todo_by_id(household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+        if current.needs_approval && !current.completed {
+            let requested_by = match current.approval_requested_by {
+                Some(_) => None,
+                None => user.member_id,
+            };
+            let todo = db
+                .set_approval_request(household_id, id, requested_by, expected_version)
+                .await?
+                .ok_or(AppError::NotFound("Todo"))?;
+            let action = match requested_by {
+                Some(_) => HistoryAction::ApprovalRequested,
+                None => HistoryAction::ApprovalWithdrawn,
+            };
+            history::record(db, user, action, Some(&current), Some(&todo)).await;
+
+            let event = if requested_by.is_some() { "todo_approval_requested" } else { "todo_updated" };
+            broadcast_todo_update(db, household_id, event, Some(todo.list_id), json!(todo)).await;
+            return Ok(todo);
         }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+
+    complete_toggle(db, user, id, expected_version, false).await
 }
 
-pub async fn delete_todo(
-    Extension(db): Extension<Arc<Database>>,
-    Path(id): Path<i32>,
-) -> Result<StatusCode, StatusCode> {
-    match db.delete_todo(id).await {
-        Ok(true) => {
-            broadcast_todo_update("todo_deleted", json!({"id": id}));
-            Ok(StatusCode::NO_CONTENT)
+/// Runs the toggle in the store, records it and broadcasts it (as
+/// `todo_approved` when a parent is `approving` it), along with any next
+/// occurrence it created and points it moved.
+async fn complete_toggle(
+    db: &Arc<dyn TodoStore>,
+    user: &User,
+    id: i32,
+    expected_version: Option<i32>,
+    approving: bool,
+) -> Result<Todo, AppError> {
+    let household_id = user.household_id;
+    let before = db.get_todo_by_id(household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+    let toggled = db
+        .toggle_todo(household_id, id, expected_version)
+        .await?
+        .ok_or(AppError::NotFound("Todo"))?;
+    let todo = toggled.todo;
+
+    let (action, event) = match (approving, todo.completed) {
+        (true, _) => (HistoryAction::Approved, "todo_approved"),
+        (false, true) => (HistoryAction::Completed, "todo_toggled"),
+        (false, false) => (HistoryAction::Reopened, "todo_toggled"),
+    };
+    history::record(db, user, action, Some(&before), Some(&todo)).await;
+
+    broadcast_todo_update(db, household_id, event, Some(todo.list_id), json!(todo)).await;
+    if let Some(next) = toggled.next_occurrence {
+        history::record(db, user, HistoryAction::Created, None, Some(&next)).await;
+        broadcast_todo_update(db, household_id, "todo_created", Some(next.list_id), json!(next)).await;
+    }
+    for entry in toggled.points {
+        broadcast_todo_update(db, household_id, "points_changed", None, json!(entry)).await;
+    }
+    Ok(todo)
+}
+
+pub async fn add_checklist_item(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(todo_id): AppPath<i32>,
+    AppJson(request): AppJson<CreateChecklistItemRequest>,
+) -> Result<TodoResponse, AppError> {
+    require_editor(&user)?;
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_todo_text(&request.text));
+    errors.finish()?;
+
+    let before = db.get_todo_by_id(user.household_id, todo_id).await?.ok_or(AppError::NotFound("Todo"))?;
+    let todo = db
+        .add_checklist_item(user.household_id, todo_id, request)
+        .await?
+        .ok_or(AppError::NotFound("Todo"))?;
+
+    checklist_changed(&db, &user, &before, todo).await.map(TodoResponse)
+}
+
+pub async fn update_checklist_item(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath((todo_id, id)): AppPath<(i32, i32)>,
+    AppJson(request): AppJson<UpdateChecklistItemRequest>,
+) -> Result<TodoResponse, AppError> {
+    require_editor(&user)?;
+
+    let mut errors = FieldErrors::default();
+    if let Some(ref text) = request.text {
+        errors.check(validate_todo_text(text));
+    }
+    errors.finish()?;
+
+    let before = db.get_todo_by_id(user.household_id, todo_id).await?.ok_or(AppError::NotFound("Todo"))?;
+    let todo = db
+        .update_checklist_item(user.household_id, todo_id, id, request)
+        .await?
+        .ok_or(AppError::NotFound("Checklist item"))?;
+
+    checklist_changed(&db, &user, &before, todo).await.map(TodoResponse)
+}
+
+pub async fn delete_checklist_item(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath((todo_id, id)): AppPath<(i32, i32)>,
+) -> Result<TodoResponse, AppError> {
+    // Children may tick and rename steps, but deleting is for parents
+    require_parent(&user)?;
+
+    let before = db.get_todo_by_id(user.household_id, todo_id).await?.ok_or(AppError::NotFound("Todo"))?;
+    let todo = db
+        .delete_checklist_item(user.household_id, todo_id, id)
+        .await?
+        .ok_or(AppError::NotFound("Checklist item"))?;
+
+    checklist_changed(&db, &user, &before, todo).await.map(TodoResponse)
+}
+
+/// Records and broadcasts a todo whose checklist changed. If that finished
+/// the last step of an `auto_complete` todo, toggles it as `user` would, so
+/// a child still only asks for approval.
+async fn checklist_changed(db: &Arc<dyn TodoStore>, user: &User, before: &Todo, todo: Todo) -> Result<Todo, AppError> {
+    history::record(db, user, HistoryAction::ChecklistChanged, Some(before), Some(&todo)).await;
+    broadcast_todo_update(db, user.household_id, "todo_updated", Some(todo.list_id), json!(todo)).await;
+
+    if todo.auto_complete && todo.progress.is_complete() && !todo.completed && todo.approval_requested_by.is_none() {
+        return apply_toggle(db, user, todo.id, Some(todo.version)).await;
+    }
+    Ok(todo)
+}
+
+/// A todo's comments, oldest first.
+pub async fn get_comments(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(todo_id): AppPath<i32>,
+) -> Result<Json<Vec<TodoComment>>, AppError> {
+    db.get_todo_by_id(user.household_id, todo_id).await?.ok_or(AppError::NotFound("Todo"))?;
+
+    Ok(Json(db.get_comments(user.household_id, todo_id).await?))
+}
+
+/// Leaves a comment, by default as the signed-in member.
+pub async fn add_comment(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(todo_id): AppPath<i32>,
+    AppJson(request): AppJson<CreateCommentRequest>,
+) -> Result<Json<TodoComment>, AppError> {
+    require_editor(&user)?;
+    let author_id = request.author_id.or(user.member_id);
+    if user.role == Role::Child && author_id != user.member_id {
+        return Err(AppError::Forbidden("Children can only comment as themselves"));
+    }
+
+    let todo = db.get_todo_by_id(user.household_id, todo_id).await?.ok_or(AppError::NotFound("Todo"))?;
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_comment_text(&request.text));
+    check_member(&db, user.household_id, "author_id", author_id, &mut errors).await?;
+    errors.finish()?;
+
+    let comment = db.add_comment(todo_id, author_id, &request.text).await?;
+
+    broadcast_todo_update(&db, user.household_id, "comment_added", Some(todo.list_id), json!(comment)).await;
+    Ok(Json(comment))
+}
+
+/// Every recorded change to a todo, newest first, even once it is deleted.
+pub async fn get_todo_history(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(todo_id): AppPath<i32>,
+) -> Result<Json<Vec<HistoryEntry>>, AppError> {
+    let entries = db.get_todo_history(user.household_id, todo_id).await?;
+    if entries.is_empty() && db.get_todo_by_id(user.household_id, todo_id).await?.is_none() {
+        return Err(AppError::NotFound("Todo"));
+    }
+
+    Ok(Json(entries))
+}
+
+pub const DEFAULT_ACTIVITY_LIMIT: i64 = 50;
+pub const MAX_ACTIVITY_LIMIT: i64 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    /// Only entries older than this entry id, to page further back.
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// The household's changes to any todo, newest first.
+pub async fn get_activity(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppQuery(query): AppQuery<ActivityQuery>,
+) -> Result<Json<Vec<HistoryEntry>>, AppError> {
+    let limit = query.limit.unwrap_or(DEFAULT_ACTIVITY_LIMIT).clamp(1, MAX_ACTIVITY_LIMIT);
+
+    Ok(Json(db.get_activity(user.household_id, query.before, limit).await?))
+}
+
+pub const DEFAULT_SEARCH_LIMIT: i64 = 20;
+pub const MAX_SEARCH_LIMIT: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+/// Searches the household's todos, completed ones included, best match first.
+pub async fn search_todos(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppQuery(query): AppQuery<SearchQuery>,
+) -> Result<Json<Vec<SearchHit>>, AppError> {
+    if search_terms(&query.q).is_empty() {
+        return Err(AppError::field("q", "must contain a word to search for"));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+
+    Ok(Json(db.search_todos(user.household_id, &query.q, limit).await?))
+}
+
+/// Attaches the multipart field `file` to a todo. Images also get a
+/// thumbnail; anything not in [`crate::attachments::ALLOWED_CONTENT_TYPES`]
+/// is refused.
+pub async fn upload_attachment(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(storage): Extension<Arc<AttachmentStorage>>,
+    Extension(user): Extension<User>,
+    AppPath(todo_id): AppPath<i32>,
+    AppMultipart(mut multipart): AppMultipart,
+) -> Result<Json<Attachment>, AppError> {
+    require_editor(&user)?;
+    let before = db.get_todo_by_id(user.household_id, todo_id).await?.ok_or(AppError::NotFound("Todo"))?;
+
+    let mut upload = None;
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let file_name = clean_file_name(field.file_name());
+        let content_type = field.content_type().unwrap_or("application/octet-stream").to_string();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.chunk().await? {
+            if bytes.len() + chunk.len() > storage.max_bytes() {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "Attachments can be at most {} bytes",
+                    storage.max_bytes()
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        upload = Some((file_name, content_type, bytes));
+        break;
+    }
+
+    let Some((file_name, content_type, bytes)) = upload else {
+        return Err(AppError::field("file", "A file is required"));
+    };
+    if !is_allowed_content_type(&content_type) {
+        return Err(AppError::field("file", format!("Files of type {} cannot be attached", content_type)));
+    }
+    if bytes.is_empty() {
+        return Err(AppError::field("file", "The file is empty"));
+    }
+
+    let thumbnail = if content_type.starts_with("image/") {
+        let image = bytes.clone();
+        let thumbnail = tokio::task::spawn_blocking(move || make_thumbnail(&image))
+            .await
+            .map_err(anyhow::Error::from)?;
+        Some(thumbnail.ok_or_else(|| AppError::field("file", "The image could not be read"))?)
+    } else {
+        None
+    };
+
+    let sha256 = storage.save(&bytes, thumbnail.as_deref()).await?;
+    let attachment = db
+        .add_attachment(todo_id, NewAttachment {
+            file_name,
+            content_type,
+            size: bytes.len() as i64,
+            sha256,
+            has_thumbnail: thumbnail.is_some(),
+            uploaded_by: user.member_id,
+        })
+        .await?;
+
+    if let Some(todo) = db.get_todo_by_id(user.household_id, todo_id).await? {
+        history::record(&db, &user, HistoryAction::AttachmentAdded, Some(&before), Some(&todo)).await;
+        broadcast_todo_update(&db, user.household_id, "todo_updated", Some(todo.list_id), json!(todo)).await;
+    }
+    Ok(Json(attachment))
+}
+
+/// The attached file itself. Images open in the browser, anything else
+/// downloads under its original name.
+pub async fn download_attachment(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(storage): Extension<Arc<AttachmentStorage>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Response, AppError> {
+    let attachment = db.get_attachment(user.household_id, id).await?.ok_or(AppError::NotFound("Attachment"))?;
+    let bytes = storage.read(&attachment.sha256).await?;
+
+    let disposition = if attachment.content_type.starts_with("image/") { "inline" } else { "attachment" };
+    Ok(file_response(&attachment.content_type, disposition, &attachment.file_name, bytes))
+}
+
+pub async fn get_attachment_thumbnail(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(storage): Extension<Arc<AttachmentStorage>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Response, AppError> {
+    let attachment = db
+        .get_attachment(user.household_id, id)
+        .await?
+        .filter(|attachment| attachment.has_thumbnail)
+        .ok_or(AppError::NotFound("Thumbnail"))?;
+    let bytes = storage.read_thumbnail(&attachment.sha256).await?;
+
+    Ok(file_response("image/png", "inline", &attachment.file_name, bytes))
+}
+
+pub async fn delete_attachment(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(storage): Extension<Arc<AttachmentStorage>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    // Deleting is for parents, as with the todos themselves
+    require_parent(&user)?;
+
+    let attachment = db.get_attachment(user.household_id, id).await?.ok_or(AppError::NotFound("Attachment"))?;
+    let before = db.get_todo_by_id(user.household_id, attachment.todo_id).await?;
+    let attachment = db.delete_attachment(user.household_id, id).await?.ok_or(AppError::NotFound("Attachment"))?;
+    storage.remove_unused(db.as_ref(), [attachment.sha256]).await?;
+
+    if let Some(todo) = db.get_todo_by_id(user.household_id, attachment.todo_id).await? {
+        history::record(&db, &user, HistoryAction::AttachmentRemoved, before.as_ref(), Some(&todo)).await;
+        broadcast_todo_update(&db, user.household_id, "todo_updated", Some(todo.list_id), json!(todo)).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Serves stored bytes. `nosniff` keeps browsers from second-guessing the
+/// content type we checked on upload.
+fn file_response(content_type: &str, disposition: &str, file_name: &str, bytes: Vec<u8>) -> Response {
+    let file_name: String = file_name.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect();
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("{}; filename=\"{}\"", disposition, file_name)),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        bytes,
+    )
+        .into_response()
+}
+
+/// Completes a todo a child asked approval for.
+pub async fn approve_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    IfMatch(expected_version): IfMatch,
+) -> Result<TodoResponse, AppError> {
+    require_parent(&user)?;
+    require_pending_approval(&db, user.household_id, id).await?;
+
+    complete_toggle(&db, &user, id, expected_version, true).await.map(TodoResponse)
+}
+
+/// Turns down a child's completion; the todo stays open.
+pub async fn reject_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    IfMatch(expected_version): IfMatch,
+) -> Result<TodoResponse, AppError> {
+    require_parent(&user)?;
+    let current = require_pending_approval(&db, user.household_id, id).await?;
+
+    let todo = db
+        .set_approval_request(user.household_id, id, None, expected_version)
+        .await?
+        .ok_or(AppError::NotFound("Todo"))?;
+    history::record(&db, &user, HistoryAction::Rejected, Some(&current), Some(&todo)).await;
+
+    broadcast_todo_update(&db, user.household_id, "todo_updated", Some(todo.list_id), json!(todo)).await;
+    Ok(TodoResponse(todo))
+}
+
+async fn require_pending_approval(db: &Arc<dyn TodoStore>, household_id: i32, id: i32) -> Result<Todo, AppError> {
+    let todo = db.get_todo_by_id(household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+    if todo.approval_requested_by.is_none() {
+        return Err(AppError::Conflict("Todo is not waiting for approval".to_string()));
+    }
+    Ok(todo)
+}
+
+/// A todo as JSON, with its version as the `ETag`.
+pub struct TodoResponse(pub Todo);
+
+impl IntoResponse for TodoResponse {
+    fn into_response(self) -> Response {
+        ([(header::ETAG, self.0.etag())], Json(self.0)).into_response()
+    }
+}
+
+/// The todo version from an `If-Match` header such as `"3"`. No header, or
+/// `*`, means the client is happy to overwrite whatever is there.
+pub struct IfMatch(pub Option<i32>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Ok(IfMatch(None));
+        };
+
+        let value = value.to_str().unwrap_or_default().trim();
+        if value == "*" {
+            return Ok(IfMatch(None));
         }
-        Ok(false) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+
+        value
+            .trim_start_matches("W/")
+            .trim_matches('"')
+            .parse()
+            .map(|version| IfMatch(Some(version)))
+            .map_err(|_| AppError::BadRequest("If-Match must be a todo version such as \"3\"".to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OccurrencesQuery {
+    pub count: Option<usize>,
+}
+
+/// Previews the upcoming due dates of a recurring todo.
+pub async fn get_todo_occurrences(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    AppQuery(query): AppQuery<OccurrencesQuery>,
+) -> Result<Json<Vec<NaiveDate>>, AppError> {
+    let count = query.count.unwrap_or(5).min(52);
+
+    let todo = db.get_todo_by_id(user.household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+
+    match (todo.recurrence, todo.due_date) {
+        (Some(rule), Some(due_date)) => Ok(Json(rule.occurrences_after(due_date, count))),
+        _ => Ok(Json(Vec::new())),
     }
-}
\ No newline at end of file
+}
+
+pub async fn delete_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    apply_delete(&db, &user, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(crate) async fn apply_delete(db: &Arc<dyn TodoStore>, user: &User, id: i32) -> Result<(), AppError> {
+    require_parent(user)?;
+    let household_id = user.household_id;
+
+    let before = db.get_todo_by_id(household_id, id).await?;
+    let list_id = db.delete_todo(household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+    history::record(db, user, HistoryAction::Deleted, before.as_ref(), None).await;
+
+    broadcast_todo_update(db, household_id, "todo_deleted", Some(list_id), json!({"id": id})).await;
+    Ok(())
+}
+
+/// Trashed todos, most recently deleted first.
+pub async fn get_trash(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<Todo>>, AppError> {
+    Ok(Json(db.get_trash(user.household_id).await?))
+}
+
+/// Takes a todo back out of the trash, to the list it was on.
+pub async fn restore_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<TodoResponse, AppError> {
+    require_parent(&user)?;
+
+    let trashed = require_trashed(&db, user.household_id, id).await?;
+    let todo = db.restore_todo(user.household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+    history::record(&db, &user, HistoryAction::Restored, Some(&trashed), Some(&todo)).await;
+
+    broadcast_todo_update(&db, user.household_id, "todo_restored", Some(todo.list_id), json!(todo)).await;
+    Ok(TodoResponse(todo))
+}
+
+/// Deletes a trashed todo for good, along with attachment files nothing
+/// else uses.
+pub async fn purge_todo(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(storage): Extension<Arc<AttachmentStorage>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    require_parent(&user)?;
+
+    let todo = require_trashed(&db, user.household_id, id).await?;
+    db.purge_todo(user.household_id, id).await?.ok_or(AppError::NotFound("Todo"))?;
+    history::record(&db, &user, HistoryAction::Purged, Some(&todo), None).await;
+    storage.remove_unused(db.as_ref(), todo.attachments.into_iter().map(|attachment| attachment.sha256)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn require_trashed(db: &Arc<dyn TodoStore>, household_id: i32, id: i32) -> Result<Todo, AppError> {
+    db.get_trash(household_id)
+        .await?
+        .into_iter()
+        .find(|todo| todo.id == id)
+        .ok_or(AppError::NotFound("Todo"))
+}
+
+pub async fn get_members(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<Member>>, AppError> {
+    Ok(Json(db.get_members(user.household_id).await?))
+}
+
+pub async fn get_member(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Json<Member>, AppError> {
+    let member = db.get_member_by_id(user.household_id, id).await?.ok_or(AppError::NotFound("Member"))?;
+
+    Ok(Json(member))
+}
+
+pub async fn create_member(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(mut request): AppJson<CreateMemberRequest>,
+) -> Result<Json<Member>, AppError> {
+    require_parent(&user)?;
+
+    request.display_name = request.display_name.trim().to_string();
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_display_name(&request.display_name));
+    if let Some(ref color) = request.color {
+        errors.check(validate_color(color));
+    }
+    if let Some(ref initials) = request.initials {
+        errors.check(validate_initials(initials));
+    }
+    errors.finish()?;
+
+    let member = db.create_member(user.household_id, request).await?;
+
+    broadcast_todo_update(&db, user.household_id, "member_created", None, json!(member)).await;
+    Ok(Json(member))
+}
+
+pub async fn update_member(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    AppJson(mut request): AppJson<UpdateMemberRequest>,
+) -> Result<Json<Member>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    if let Some(ref mut display_name) = request.display_name {
+        *display_name = display_name.trim().to_string();
+        errors.check(validate_display_name(display_name));
+    }
+    if let Some(ref color) = request.color {
+        errors.check(validate_color(color));
+    }
+    if let Some(ref initials) = request.initials {
+        errors.check(validate_initials(initials));
+    }
+    errors.finish()?;
+
+    let member = db.update_member(user.household_id, id, request).await?.ok_or(AppError::NotFound("Member"))?;
+
+    broadcast_todo_update(&db, user.household_id, "member_updated", None, json!(member)).await;
+    Ok(Json(member))
+}
+
+pub async fn delete_member(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    require_parent(&user)?;
+
+    if !db.delete_member(user.household_id, id).await? {
+        return Err(AppError::NotFound("Member"));
+    }
+
+    broadcast_todo_update(&db, user.household_id, "member_deleted", None, json!({"id": id})).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The member's current points.
+pub async fn get_member_balance(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Json<PointBalance>, AppError> {
+    require_member(&db, user.household_id, id).await?;
+
+    let balance = db.get_point_balance(user.household_id, id).await?;
+
+    Ok(Json(PointBalance { member_id: id, balance }))
+}
+
+/// Every change to the member's points, newest first.
+pub async fn get_member_ledger(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Json<Vec<PointEntry>>, AppError> {
+    require_member(&db, user.household_id, id).await?;
+
+    Ok(Json(db.get_point_entries(user.household_id, id).await?))
+}
+
+async fn require_member(db: &Arc<dyn TodoStore>, household_id: i32, member_id: i32) -> Result<(), AppError> {
+    match db.get_member_by_id(household_id, member_id).await? {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound("Member")),
+    }
+}
+
+pub async fn get_rotations(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<Rotation>>, AppError> {
+    Ok(Json(db.get_rotations(user.household_id).await?))
+}
+
+pub async fn get_rotation(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Json<Rotation>, AppError> {
+    match db.get_rotation_by_id(user.household_id, id).await? {
+        Some(rotation) => Ok(Json(rotation)),
+        None => Err(AppError::NotFound("Rotation")),
+    }
+}
+
+pub async fn create_rotation(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(request): AppJson<CreateRotationRequest>,
+) -> Result<Json<Rotation>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    check_rotation_members(&db, user.household_id, &request.member_ids, &mut errors).await?;
+    errors.finish()?;
+
+    let rotation = db.create_rotation(user.household_id, request).await?;
+
+    broadcast_todo_update(&db, user.household_id, "rotation_created", None, json!(rotation)).await;
+    Ok(Json(rotation))
+}
+
+pub async fn update_rotation(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    AppJson(request): AppJson<UpdateRotationRequest>,
+) -> Result<Json<Rotation>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    if let Some(ref member_ids) = request.member_ids {
+        check_rotation_members(&db, user.household_id, member_ids, &mut errors).await?;
+    }
+    errors.finish()?;
+
+    let rotation = db
+        .update_rotation(user.household_id, id, request)
+        .await?
+        .ok_or(AppError::NotFound("Rotation"))?;
+
+    broadcast_todo_update(&db, user.household_id, "rotation_updated", None, json!(rotation)).await;
+    Ok(Json(rotation))
+}
+
+pub async fn delete_rotation(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    require_parent(&user)?;
+
+    if !db.delete_rotation(user.household_id, id).await? {
+        return Err(AppError::NotFound("Rotation"));
+    }
+
+    broadcast_todo_update(&db, user.household_id, "rotation_deleted", None, json!({"id": id})).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn check_rotation_members(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    member_ids: &[i32],
+    errors: &mut FieldErrors,
+) -> Result<(), AppError> {
+    errors.check(validate_rotation_members(member_ids));
+
+    let members = db.get_members(household_id).await?;
+    if member_ids.iter().any(|id| !members.iter().any(|member| member.id == *id)) {
+        errors.add("member_ids", "no such family member");
+    }
+    Ok(())
+}
+
+/// Records a `rotation_id` that is not in the household.
+async fn check_rotation(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    rotation_id: Option<i32>,
+    errors: &mut FieldErrors,
+) -> Result<(), AppError> {
+    let Some(id) = rotation_id else {
+        return Ok(());
+    };
+
+    if db.get_rotation_by_id(household_id, id).await?.is_none() {
+        errors.add("rotation_id", "no such rotation");
+    }
+    Ok(())
+}
+
+pub async fn get_rewards(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<Reward>>, AppError> {
+    Ok(Json(db.get_rewards(user.household_id).await?))
+}
+
+pub async fn get_reward(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Json<Reward>, AppError> {
+    match db.get_reward_by_id(user.household_id, id).await? {
+        Some(reward) => Ok(Json(reward)),
+        None => Err(AppError::NotFound("Reward")),
+    }
+}
+
+pub async fn create_reward(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(mut request): AppJson<CreateRewardRequest>,
+) -> Result<Json<Reward>, AppError> {
+    require_parent(&user)?;
+
+    request.name = request.name.trim().to_string();
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_reward_name(&request.name));
+    errors.check(validate_reward_cost(request.cost));
+    errors.finish()?;
+
+    let reward = db.create_reward(user.household_id, request).await?;
+
+    broadcast_todo_update(&db, user.household_id, "reward_created", None, json!(reward)).await;
+    Ok(Json(reward))
+}
+
+pub async fn update_reward(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    AppJson(mut request): AppJson<UpdateRewardRequest>,
+) -> Result<Json<Reward>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    if let Some(ref mut name) = request.name {
+        *name = name.trim().to_string();
+        errors.check(validate_reward_name(name));
+    }
+    if let Some(cost) = request.cost {
+        errors.check(validate_reward_cost(cost));
+    }
+    errors.finish()?;
+
+    let reward = db.update_reward(user.household_id, id, request).await?.ok_or(AppError::NotFound("Reward"))?;
+
+    broadcast_todo_update(&db, user.household_id, "reward_updated", None, json!(reward)).await;
+    Ok(Json(reward))
+}
+
+pub async fn delete_reward(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    require_parent(&user)?;
+
+    if !db.delete_reward(user.household_id, id).await? {
+        return Err(AppError::NotFound("Reward"));
+    }
+
+    broadcast_todo_update(&db, user.household_id, "reward_deleted", None, json!({"id": id})).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Spends a member's points on a reward.
+pub async fn redeem_reward(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    AppJson(request): AppJson<RedeemRewardRequest>,
+) -> Result<Json<PointEntry>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    check_member(&db, user.household_id, "member_id", Some(request.member_id), &mut errors).await?;
+    errors.finish()?;
+
+    let entry = db
+        .redeem_reward(user.household_id, id, request.member_id)
+        .await?
+        .ok_or(AppError::NotFound("Reward"))?;
+
+    broadcast_todo_update(&db, user.household_id, "points_changed", None, json!(entry)).await;
+    Ok(Json(entry))
+}
+
+pub async fn get_lists(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<TodoList>>, AppError> {
+    Ok(Json(db.get_lists(user.household_id).await?))
+}
+
+pub async fn get_list(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<Json<TodoList>, AppError> {
+    let list = db.get_list_by_id(user.household_id, id).await?.ok_or(AppError::NotFound("List"))?;
+
+    Ok(Json(list))
+}
+
+pub async fn create_list(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(mut request): AppJson<CreateListRequest>,
+) -> Result<Json<TodoList>, AppError> {
+    require_parent(&user)?;
+
+    request.name = request.name.trim().to_string();
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_list_name(&request.name));
+    errors.finish()?;
+
+    let list = db.create_list(user.household_id, request).await?;
+
+    broadcast_todo_update(&db, user.household_id, "list_created", None, json!(list)).await;
+    Ok(Json(list))
+}
+
+pub async fn update_list(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+    AppJson(mut request): AppJson<UpdateListRequest>,
+) -> Result<Json<TodoList>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    if let Some(ref mut name) = request.name {
+        *name = name.trim().to_string();
+        errors.check(validate_list_name(name));
+    }
+    errors.finish()?;
+
+    let list = db.update_list(user.household_id, id, request).await?.ok_or(AppError::NotFound("List"))?;
+
+    broadcast_todo_update(&db, user.household_id, "list_updated", None, json!(list)).await;
+    Ok(Json(list))
+}
+
+pub async fn delete_list(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppPath(id): AppPath<i32>,
+) -> Result<StatusCode, AppError> {
+    require_parent(&user)?;
+    require_list(&db, user.household_id, id).await?;
+
+    // The list's todos would go with it, trash and attachments included, so
+    // it has to be emptied first
+    if !db.get_todos(user.household_id, Some(id)).await?.is_empty() {
+        return Err(AppError::Conflict("The list still has todos; move or delete them first".to_string()));
+    }
+    if db.get_trash(user.household_id).await?.iter().any(|todo| todo.list_id == id) {
+        return Err(AppError::Conflict("The list still has todos in the trash; restore or purge them first".to_string()));
+    }
+    if !db.delete_list(user.household_id, id).await? {
+        return Err(AppError::NotFound("List"));
+    }
+
+    broadcast_todo_update(&db, user.household_id, "list_deleted", None, json!({"id": id})).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(crate) async fn require_list(db: &Arc<dyn TodoStore>, household_id: i32, list_id: i32) -> Result<(), AppError> {
+    match db.get_list_by_id(household_id, list_id).await? {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound("List")),
+    }
+}
+
+/// Checks that a requested list exists, falling back to the default list.
+/// A missing list is recorded against `list_id`.
+async fn resolve_list(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    list_id: Option<i32>,
+    errors: &mut FieldErrors,
+) -> Result<Option<i32>, AppError> {
+    let list = match list_id {
+        Some(id) => db.get_list_by_id(household_id, id).await?,
+        None => db.get_default_list(household_id).await?,
+    };
+
+    if list.is_none() {
+        errors.add("list_id", "no such list");
+    }
+    Ok(list.map(|list| list.id))
+}
+
+/// Records a `member_id` that is not in the household against `field`.
+async fn check_member(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    field: &'static str,
+    member_id: Option<i32>,
+    errors: &mut FieldErrors,
+) -> Result<(), AppError> {
+    let Some(id) = member_id else {
+        return Ok(());
+    };
+
+    if db.get_member_by_id(household_id, id).await?.is_none() {
+        errors.add(field, "no such family member");
+    }
+    Ok(())
+}
+
+/// Creates an account (and its household) and signs it in.
+pub async fn register(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    AppJson(mut request): AppJson<RegisterRequest>,
+) -> Result<Response, AppError> {
+    let email = normalize_email(&request.email);
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_email(&email));
+    errors.check(validate_password(&request.password));
+    if let Some(ref mut household_name) = request.household_name {
+        *household_name = household_name.trim().to_string();
+        errors.check(validate_household_name(household_name));
+    }
+    check_email_free(&db, &email, &mut errors).await?;
+    errors.finish()?;
+
+    let password_hash = hash_password(&request.password);
+    let (user, household) = db.register_user(&email, &password_hash, request.household_name).await?;
+
+    signed_in(&db, StatusCode::CREATED, user, household).await
+}
+
+pub async fn login(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    AppJson(request): AppJson<LoginRequest>,
+) -> Result<Response, AppError> {
+    let user = db.get_user_by_email(&normalize_email(&request.email)).await?;
+    let user = check_credentials(user, &request.password)
+        .ok_or(AppError::Unauthorized("Invalid email or password"))?;
+    let household = db
+        .get_household(user.household_id)
+        .await?
+        .ok_or(AppError::NotFound("Household"))?;
+
+    signed_in(&db, StatusCode::OK, user, household).await
+}
+
+/// Ends the current session, if there is one, and clears the cookie.
+pub async fn logout(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if let Some(token) = auth::session_token(&headers) {
+        db.delete_session(&auth::hash_token(&token)).await?;
+    }
+
+    Ok((StatusCode::NO_CONTENT, [(header::SET_COOKIE, clear_session_cookie())]).into_response())
+}
+
+pub async fn get_account(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Account>, AppError> {
+    let household = db
+        .get_household(user.household_id)
+        .await?
+        .ok_or(AppError::NotFound("Household"))?;
+
+    Ok(Json(Account { user, household }))
+}
+
+pub async fn get_household(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Household>, AppError> {
+    let household = db
+        .get_household(user.household_id)
+        .await?
+        .ok_or(AppError::NotFound("Household"))?;
+
+    Ok(Json(household))
+}
+
+/// Renames the household or changes the timezone its due dates are read in.
+pub async fn update_household(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(mut request): AppJson<UpdateHouseholdRequest>,
+) -> Result<Json<Household>, AppError> {
+    require_parent(&user)?;
+
+    let mut errors = FieldErrors::default();
+    if let Some(ref mut name) = request.name {
+        *name = name.trim().to_string();
+        errors.check(validate_household_name(name));
+    }
+    if let Some(ref mut timezone) = request.timezone {
+        *timezone = timezone.trim().to_string();
+        errors.check(validate_timezone(timezone));
+    }
+    errors.finish()?;
+
+    let household = db
+        .update_household(user.household_id, request)
+        .await?
+        .ok_or(AppError::NotFound("Household"))?;
+
+    // Other clients need the new timezone to bucket todos the same way
+    broadcast_todo_update(&db, user.household_id, "household_updated", None, json!(household)).await;
+    Ok(Json(household))
+}
+
+/// Adds another account to the caller's household, optionally signing in
+/// as one of its members (and so with that member's role).
+pub async fn create_user(
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    AppJson(request): AppJson<CreateUserRequest>,
+) -> Result<(StatusCode, Json<User>), AppError> {
+    require_parent(&user)?;
+
+    let email = normalize_email(&request.email);
+
+    let mut errors = FieldErrors::default();
+    errors.check(validate_email(&email));
+    errors.check(validate_password(&request.password));
+    check_email_free(&db, &email, &mut errors).await?;
+    check_member(&db, user.household_id, "member_id", request.member_id, &mut errors).await?;
+    errors.finish()?;
+
+    let password_hash = hash_password(&request.password);
+    let new_user = db
+        .create_user(user.household_id, &email, &password_hash, request.member_id)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(new_user)))
+}
+
+/// Starts a session and answers with the account and the session cookie.
+async fn signed_in(
+    db: &Arc<dyn TodoStore>,
+    status: StatusCode,
+    user: User,
+    household: Household,
+) -> Result<Response, AppError> {
+    let cookie = start_session(db, &user).await?;
+
+    Ok((status, [(header::SET_COOKIE, cookie)], Json(Account { user, household })).into_response())
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+async fn check_email_free(
+    db: &Arc<dyn TodoStore>,
+    email: &str,
+    errors: &mut FieldErrors,
+) -> Result<(), AppError> {
+    if db.get_user_by_email(email).await?.is_some() {
+        errors.add("email", "is already registered");
+    }
+    Ok(())
+}
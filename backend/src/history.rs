@@ -0,0 +1,83 @@
+//! The audit trail of todo changes behind `GET /api/todos/:id/history` and
+//! `GET /api/activity`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use serde_json::{json, Map, Value};
+
+use crate::models::{FieldChange, HistoryAction, NewHistoryEntry, Todo, User};
+use crate::store::TodoStore;
+
+/// Bookkeeping that changes on every write, or along with a field that is
+/// reported already, so is not worth reporting.
+const UNTRACKED_FIELDS: &[&str] = &["id", "version", "created_at", "updated_at", "completed_at", "progress"];
+
+/// The fields that differ between two versions of a todo. Pass `None` for
+/// the side on which it did not exist, so a creation lists every field as
+/// new and a deletion every field as gone.
+pub fn diff(before: Option<&Todo>, after: Option<&Todo>) -> BTreeMap<String, FieldChange> {
+    let before = before.map(tracked_fields).unwrap_or_default();
+    let after = after.map(tracked_fields).unwrap_or_default();
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let old = before.get(name).cloned().unwrap_or(Value::Null);
+            let new = after.get(name).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| (name.clone(), FieldChange { old, new }))
+        })
+        .collect()
+}
+
+/// The todo as JSON, with its checklist and attachments cut down to what a
+/// person would recognise (no ids or timestamps).
+fn tracked_fields(todo: &Todo) -> Map<String, Value> {
+    let Value::Object(mut fields) = json!(todo) else {
+        return Map::new();
+    };
+    fields.retain(|name, _| !UNTRACKED_FIELDS.contains(&name.as_str()));
+    fields.insert(
+        "checklist".to_string(),
+        todo.checklist.iter().map(|item| json!({"text": item.text, "completed": item.completed})).collect(),
+    );
+    fields.insert(
+        "attachments".to_string(),
+        todo.attachments.iter().map(|attachment| json!(attachment.file_name)).collect(),
+    );
+
+    fields
+}
+
+/// Records that `user` changed a todo from `before` to `after`. Like
+/// broadcasting, this happens after the write has succeeded, so a failure
+/// is logged rather than failing the request. Updates that changed nothing
+/// are not recorded.
+pub(crate) async fn record(
+    db: &Arc<dyn TodoStore>,
+    user: &User,
+    action: HistoryAction,
+    before: Option<&Todo>,
+    after: Option<&Todo>,
+) {
+    let Some(todo) = after.or(before) else {
+        return;
+    };
+    let changes = diff(before, after);
+    if changes.is_empty() && action == HistoryAction::Updated {
+        return;
+    }
+
+    let entry = NewHistoryEntry {
+        todo_id: todo.id,
+        todo_text: todo.text.clone(),
+        user_id: Some(user.id),
+        member_id: user.member_id,
+        action,
+        changes,
+    };
+    if let Err(e) = db.add_history(user.household_id, entry).await {
+        tracing::error!("Failed to record {:?} of todo {}: {:#}", action, todo.id, e);
+    }
+}
@@ -0,0 +1,100 @@
+//! Family todo backend: a REST + WebSocket API over a pluggable [`TodoStore`].
+
+pub mod attachments;
+pub mod auth;
+pub mod error;
+pub mod history;
+pub mod models;
+pub mod store;
+pub mod trash;
+mod handlers;
+mod websocket;
+
+use axum::{
+    extract::{DefaultBodyLimit, Extension},
+    middleware,
+    routing::{get, post, put, patch, delete},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::attachments::AttachmentStorage;
+use crate::store::TodoStore;
+
+/// Builds the API and WebSocket routes on top of `store`. Everything but
+/// registering and signing in/out requires a session. Attachments are
+/// stored as configured by [`AttachmentStorage::from_env`].
+pub fn app(store: Arc<dyn TodoStore>) -> Router {
+    let attachments = Arc::new(AttachmentStorage::from_env());
+    // Room for the multipart framing around the largest allowed file
+    let upload_limit = DefaultBodyLimit::max(attachments.max_bytes() + 64 * 1024);
+
+    let signed_in = Router::new()
+        // API routes
+        .route("/api/auth/me", get(handlers::get_account))
+        .route("/api/users", post(handlers::create_user))
+        .route("/api/household", get(handlers::get_household))
+        .route("/api/household", put(handlers::update_household))
+        .route("/api/todos", get(handlers::get_todos))
+        .route("/api/todos", post(handlers::create_todo))
+        .route("/api/todos/:id", get(handlers::get_todo))
+        .route("/api/todos/:id", put(handlers::replace_todo))
+        .route("/api/todos/:id", patch(handlers::update_todo))
+        .route("/api/todos/:id/toggle", put(handlers::toggle_todo))
+        .route("/api/todos/:id/approve", post(handlers::approve_todo))
+        .route("/api/todos/:id/reject", post(handlers::reject_todo))
+        .route("/api/todos/:id/occurrences", get(handlers::get_todo_occurrences))
+        .route("/api/todos/:id/history", get(handlers::get_todo_history))
+        .route("/api/todos/:id/comments", get(handlers::get_comments))
+        .route("/api/todos/:id/comments", post(handlers::add_comment))
+        .route("/api/todos/:id/attachments", post(handlers::upload_attachment).layer(upload_limit))
+        .route("/api/attachments/:id", get(handlers::download_attachment))
+        .route("/api/attachments/:id", delete(handlers::delete_attachment))
+        .route("/api/attachments/:id/thumbnail", get(handlers::get_attachment_thumbnail))
+        .route("/api/todos/:id/checklist", post(handlers::add_checklist_item))
+        .route("/api/todos/:id/checklist/:item_id", patch(handlers::update_checklist_item))
+        .route("/api/todos/:id/checklist/:item_id", delete(handlers::delete_checklist_item))
+        .route("/api/todos/:id", delete(handlers::delete_todo))
+        .route("/api/activity", get(handlers::get_activity))
+        .route("/api/search", get(handlers::search_todos))
+        .route("/api/trash", get(handlers::get_trash))
+        .route("/api/trash/:id/restore", post(handlers::restore_todo))
+        .route("/api/trash/:id", delete(handlers::purge_todo))
+        .route("/api/lists", get(handlers::get_lists))
+        .route("/api/lists", post(handlers::create_list))
+        .route("/api/lists/:id", get(handlers::get_list))
+        .route("/api/lists/:id", put(handlers::update_list))
+        .route("/api/lists/:id", delete(handlers::delete_list))
+        .route("/api/lists/:id/todos", get(handlers::get_list_todos))
+        .route("/api/lists/:id/todos", post(handlers::create_list_todo))
+        .route("/api/members", get(handlers::get_members))
+        .route("/api/members", post(handlers::create_member))
+        .route("/api/members/:id", get(handlers::get_member))
+        .route("/api/members/:id", put(handlers::update_member))
+        .route("/api/members/:id", delete(handlers::delete_member))
+        .route("/api/members/:id/balance", get(handlers::get_member_balance))
+        .route("/api/members/:id/ledger", get(handlers::get_member_ledger))
+        .route("/api/rotations", get(handlers::get_rotations))
+        .route("/api/rotations", post(handlers::create_rotation))
+        .route("/api/rotations/:id", get(handlers::get_rotation))
+        .route("/api/rotations/:id", put(handlers::update_rotation))
+        .route("/api/rotations/:id", delete(handlers::delete_rotation))
+        .route("/api/rewards", get(handlers::get_rewards))
+        .route("/api/rewards", post(handlers::create_reward))
+        .route("/api/rewards/:id", get(handlers::get_reward))
+        .route("/api/rewards/:id", put(handlers::update_reward))
+        .route("/api/rewards/:id", delete(handlers::delete_reward))
+        .route("/api/rewards/:id/redeem", post(handlers::redeem_reward))
+        // WebSocket route
+        .route("/ws", get(websocket::websocket_handler))
+        .route_layer(middleware::from_fn(auth::require_session));
+
+    Router::new()
+        .route("/api/auth/register", post(handlers::register))
+        .route("/api/auth/login", post(handlers::login))
+        .route("/api/auth/logout", post(handlers::logout))
+        .merge(signed_in)
+        .layer(Extension(store))
+        .layer(Extension(attachments))
+        .layer(middleware::from_fn(error::request_id))
+}
@@ -1,20 +1,10 @@
-mod models;
-mod handlers;
-mod database;
-mod websocket;
-
-use axum::{
-    extract::Extension,
-    http::Method,
-    routing::{get, post, put, delete},
-    Router,
-};
-use tower_http::cors::{CorsLayer, Any};
-use tower_http::services::ServeDir;
+use axum::http::{header, HeaderName, HeaderValue, Method};
 use std::sync::Arc;
+use tower_http::cors::CorsLayer;
+use tower_http::services::ServeDir;
 use tracing::info;
 
-use crate::database::Database;
+use family_todo_backend::{app, attachments::AttachmentStorage, error::REQUEST_ID_HEADER, store, trash};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -24,31 +14,38 @@ async fn main() -> anyhow::Result<()> {
     // Load environment variables
     dotenv::dotenv().ok();
     
-    // Initialize database
-    let database = Arc::new(Database::new().await?);
-    
-    // Run migrations
-    database.migrate().await?;
-    
-    // Setup CORS
+    // Initialize the store and run its migrations. A `sqlite:` URL selects
+    // SQLite, anything else Postgres.
+    let database_url = std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| store::DEFAULT_DATABASE_URL.to_string());
+    let store = store::connect(&database_url).await?;
+    
+    // Purge old trash, and the attachment files only it was using, now and
+    // every hour from here on
+    trash::spawn_purge_job(store.clone(), Arc::new(AttachmentStorage::from_env()), trash::retention_from_env());
+    
+    // Setup CORS. The frontend is served from this server, so cross-origin
+    // requests are refused unless ALLOWED_ORIGINS lists the origins (e.g. a
+    // dev server) that may send the session cookie.
+    let allowed_origins: Vec<HeaderValue> = std::env::var("ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(HeaderValue::from_str)
+        .collect::<Result<_, _>>()?;
     let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers(Any)
-        .allow_origin(Any);
-    
-    // Build application routes
-    let app = Router::new()
-        // API routes
-        .route("/api/todos", get(handlers::get_todos))
-        .route("/api/todos", post(handlers::create_todo))
-        .route("/api/todos/:id", put(handlers::update_todo))
-        .route("/api/todos/:id/toggle", put(handlers::toggle_todo))
-        .route("/api/todos/:id", delete(handlers::delete_todo))
-        // WebSocket route
-        .route("/ws", get(websocket::websocket_handler))
-        // Static file serving for frontend
-        .nest_service("/", ServeDir::new("../"))
-        .layer(Extension(database))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE, header::IF_MATCH, HeaderName::from_static(REQUEST_ID_HEADER)])
+        .expose_headers([header::ETAG, HeaderName::from_static(REQUEST_ID_HEADER)])
+        .allow_credentials(true)
+        .allow_origin(allowed_origins);
+    
+    // Build application routes, serving the frontend for everything else.
+    // Only the frontend directory: the repository root would also publish
+    // backend/.env and any SQLite database next to it.
+    let app = app(store)
+        .fallback_service(ServeDir::new("../frontend"))
         .layer(cors);
     
     let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
@@ -60,4 +57,4 @@ async fn main() -> anyhow::Result<()> {
     axum::serve(listener, app).await?;
     
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,41 +1,941 @@
-use chrono::{DateTime, Utc, NaiveDate};
-use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc, NaiveDate, NaiveTime};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::{types::Json, FromRow};
+
+use family_todo_core::{BucketGroup, ChecklistProgress, DateGroup, Recurrence, SortableTodo};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Todo {
     pub id: i32,
+    pub list_id: i32,
     pub text: String,
-    pub assignee: String,
+    pub assignee_id: Option<i32>,
     pub due_date: Option<NaiveDate>,
+    /// When on the due date it is due, in the household's timezone.
+    pub due_time: Option<NaiveTime>,
     pub completed: bool,
+    pub recurrence: Option<Json<Recurrence>>,
+    pub next_occurrence_id: Option<i32>,
+    /// Credited to the assignee when the todo is completed.
+    pub points: i32,
+    /// Hands each new occurrence to the next member of this rotation.
+    pub rotation_id: Option<i32>,
+    /// Completions by children wait for a parent's approval.
+    pub needs_approval: bool,
+    /// The child whose completion is awaiting approval, if any.
+    pub approval_requested_by: Option<i32>,
+    /// Complete the todo once every checklist item is done.
+    pub auto_complete: bool,
+    /// Bumped on every write; served as the todo's ETag.
+    pub version: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// When the todo was moved to the trash; `None` for live todos.
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// When the todo was last completed; `None` while it is open.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Filled in by the store after the todo row is read.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub checklist: Vec<ChecklistItem>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub progress: ChecklistProgress,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Todo {
+    /// Attaches the todo's checklist items, in order, and counts them.
+    pub fn with_checklist(self, checklist: Vec<ChecklistItem>) -> Self {
+        let progress = ChecklistProgress::of(checklist.iter().map(|item| item.completed));
+        Self { checklist, progress, ..self }
+    }
+
+    /// Strong ETag for the current version, e.g. `"3"`.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+impl SortableTodo for Todo {
+    fn id(&self) -> i64 {
+        i64::from(self.id)
+    }
+    
+    fn completed(&self) -> bool {
+        self.completed
+    }
+    
+    fn due_date(&self) -> Option<NaiveDate> {
+        self.due_date
+    }
+
+    fn due_time(&self) -> Option<NaiveTime> {
+        self.due_time
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateTodoRequest {
+    /// Falls back to the first list when omitted.
+    pub list_id: Option<i32>,
     pub text: String,
-    pub assignee: String,
+    pub assignee_id: Option<i32>,
     pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub points: i32,
+    pub rotation_id: Option<i32>,
+    #[serde(default)]
+    pub needs_approval: bool,
+    #[serde(default)]
+    pub auto_complete: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// A nullable field in a partial update: left out (`Absent`), sent as
+/// `null` to clear it, or sent with a new value.
+///
+/// Fields of this type need `#[serde(default)]` so that a missing key
+/// becomes `Absent` rather than `Null`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Patch<T> {
+    #[default]
+    Absent,
+    Null,
+    Value(T),
+}
+
+impl<T> Patch<T> {
+    /// The field's value after the patch, given its value before.
+    pub fn apply(self, current: Option<T>) -> Option<T> {
+        match self {
+            Patch::Absent => current,
+            Patch::Null => None,
+            Patch::Value(value) => Some(value),
+        }
+    }
+
+    pub fn as_value(&self) -> Option<&T> {
+        match self {
+            Patch::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Patch<U> {
+        match self {
+            Patch::Absent => Patch::Absent,
+            Patch::Null => Patch::Null,
+            Patch::Value(value) => Patch::Value(f(value)),
+        }
+    }
+}
+
+impl<T> From<Option<T>> for Patch<T> {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Patch::Null, Patch::Value)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<T>::deserialize(deserializer).map(Patch::from)
+    }
+}
+
+/// Body of `PATCH /api/todos/:id`: only the fields present change.
+#[derive(Debug, Default, PartialEq, Deserialize)]
 pub struct UpdateTodoRequest {
+    pub list_id: Option<i32>,
     pub text: Option<String>,
-    pub assignee: Option<String>,
+    #[serde(default)]
+    pub assignee_id: Patch<i32>,
+    #[serde(default)]
+    pub due_date: Patch<NaiveDate>,
+    /// Cleared along with the due date.
+    #[serde(default)]
+    pub due_time: Patch<NaiveTime>,
+    /// Applied as a toggle, after the other fields, so completing this way
+    /// credits points and spawns the next occurrence like the toggle endpoint.
+    pub completed: Option<bool>,
+    #[serde(default)]
+    pub recurrence: Patch<Recurrence>,
+    pub points: Option<i32>,
+    #[serde(default)]
+    pub rotation_id: Patch<i32>,
+    pub needs_approval: Option<bool>,
+    pub auto_complete: Option<bool>,
+    /// Replaces all of the todo's tags.
+    pub tags: Option<Vec<String>>,
+}
+
+/// Body of `PUT /api/todos/:id`: the todo's new contents. Optional fields
+/// that are left out are cleared, but the todo stays (un)completed unless
+/// `completed` is given and only moves lists when `list_id` is.
+#[derive(Debug, Deserialize)]
+pub struct ReplaceTodoRequest {
+    pub list_id: Option<i32>,
+    pub text: String,
+    pub assignee_id: Option<i32>,
     pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
     pub completed: Option<bool>,
+    pub recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub points: i32,
+    pub rotation_id: Option<i32>,
+    #[serde(default)]
+    pub needs_approval: bool,
+    #[serde(default)]
+    pub auto_complete: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<ReplaceTodoRequest> for UpdateTodoRequest {
+    fn from(request: ReplaceTodoRequest) -> Self {
+        Self {
+            list_id: request.list_id,
+            text: Some(request.text),
+            assignee_id: request.assignee_id.into(),
+            due_date: request.due_date.into(),
+            due_time: request.due_time.into(),
+            completed: request.completed,
+            recurrence: request.recurrence.into(),
+            points: Some(request.points),
+            rotation_id: request.rotation_id.into(),
+            needs_approval: Some(request.needs_approval),
+            auto_complete: Some(request.auto_complete),
+            tags: Some(request.tags),
+        }
+    }
+}
+
+/// A note left on a todo by a family member.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TodoComment {
+    pub id: i32,
+    pub todo_id: i32,
+    /// `None` once the member who wrote it is removed.
+    pub author_id: Option<i32>,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentRequest {
+    pub text: String,
+    /// Defaults to the signed-in member. Parents may write on behalf of
+    /// someone else, e.g. from a shared kitchen tablet.
+    pub author_id: Option<i32>,
+}
+
+/// A file attached to a todo. The bytes are served by
+/// `GET /api/attachments/:id`, not included here.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Attachment {
+    pub id: i32,
+    pub todo_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    /// In bytes.
+    pub size: i64,
+    /// Hex SHA-256 of the content, which is also its name on disk.
+    pub sha256: String,
+    pub has_thumbnail: bool,
+    /// `None` once the member who uploaded it is removed.
+    pub uploaded_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An uploaded file, already saved to disk, to record against a todo.
+#[derive(Debug)]
+pub struct NewAttachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,
+    pub has_thumbnail: bool,
+    pub uploaded_by: Option<i32>,
+}
+
+/// One step of a todo's checklist.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ChecklistItem {
+    pub id: i32,
+    pub todo_id: i32,
+    pub text: String,
+    pub completed: bool,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateChecklistItemRequest {
+    pub text: String,
+    /// Appended after the existing items when omitted.
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateChecklistItemRequest {
+    pub text: Option<String>,
+    pub completed: Option<bool>,
+    pub position: Option<i32>,
+}
+
+/// What a family member's account is allowed to do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum Role {
+    /// Manages lists, members and accounts, and approves completions.
+    #[default]
+    Parent,
+    /// Adds, edits and completes todos, but cannot delete or reassign them.
+    Child,
+    /// Can only look.
+    Guest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Member {
+    pub id: i32,
+    pub household_id: i32,
+    pub display_name: String,
+    pub color: String,
+    pub initials: String,
+    pub role: Role,
+    /// Passed over by rotations that skip members who are away.
+    pub away: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMemberRequest {
+    pub display_name: String,
+    pub color: Option<String>,
+    pub initials: Option<String>,
+    /// Defaults to parent.
+    pub role: Option<Role>,
+    #[serde(default)]
+    pub away: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub display_name: Option<String>,
+    pub color: Option<String>,
+    pub initials: Option<String>,
+    pub role: Option<Role>,
+    pub away: Option<bool>,
+}
+
+/// The order in which a recurring chore passes between members, such as
+/// dishes going from one parent to the other and then to a child.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rotation {
+    pub id: i32,
+    pub household_id: i32,
+    /// Whose turn comes after whose; wraps around at the end.
+    pub member_ids: Vec<i32>,
+    /// Pass over members who are marked as away.
+    pub skip_away: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRotationRequest {
+    pub member_ids: Vec<i32>,
+    #[serde(default)]
+    pub skip_away: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRotationRequest {
+    pub member_ids: Option<Vec<i32>>,
+    pub skip_away: Option<bool>,
+}
+
+/// A named list of todos such as chores, groceries or school.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TodoList {
+    pub id: i32,
+    pub household_id: i32,
+    pub name: String,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateListRequest {
+    pub name: String,
+    /// Appended after the existing lists when omitted.
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateListRequest {
+    pub name: Option<String>,
+    pub position: Option<i32>,
+}
+
+pub type TodosGroupedByDate = DateGroup<Todo>;
+pub type TodosGroupedByBucket = BucketGroup<Todo>;
+
+/// A todo found by `GET /api/search`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub todo: Todo,
+    /// Higher is a better match; only comparable within one search.
+    pub rank: f64,
+    /// The matching part of the todo, its text or else a checklist step or
+    /// comment, with matched words wrapped in `<mark>`.
+    pub snippet: String,
+}
+
+/// Which todos `GET /api/todos` returns. Every filter is optional and they
+/// all have to match.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TodoFilter {
+    pub list_id: Option<i32>,
+    pub assignee: Option<AssigneeFilter>,
+    pub completed: Option<bool>,
+    /// First due date to include; todos without one are left out.
+    pub due_from: Option<NaiveDate>,
+    /// Last due date to include; todos without one are left out.
+    pub due_to: Option<NaiveDate>,
+    pub tag: Option<String>,
+    /// Matched case-insensitively anywhere in the todo's text.
+    pub q: Option<String>,
+    /// Defaults to the newest todos first.
+    pub sort: Option<TodoSort>,
+    /// Most todos to return, for paging with `cursor`.
+    pub limit: Option<usize>,
+    /// Leave out todos up to and including this one.
+    pub cursor: Option<TodoCursor>,
+    /// Keeps todos completed long ago, which listings otherwise leave out.
+    #[serde(default)]
+    pub include_old_completed: bool,
+    /// Leave out todos completed before this; set by the handlers from
+    /// `include_old_completed`.
+    #[serde(skip)]
+    pub completed_since: Option<DateTime<Utc>>,
+}
+
+impl TodoFilter {
+    pub fn tag(&self) -> Option<String> {
+        self.tag.as_deref().map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty())
+    }
+
+    /// `q` as a `LIKE` pattern, with the pattern characters escaped by `\`.
+    pub fn text_pattern(&self) -> Option<String> {
+        let text = self.q.as_deref().map(str::trim).filter(|text| !text.is_empty())?;
+        let escaped = text
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        Some(format!("%{}%", escaped))
+    }
+
+    /// The same test the SQL stores run, for stores that filter in memory.
+    pub fn matches(&self, todo: &Todo) -> bool {
+        let text = self.q.as_deref().map(str::trim).unwrap_or_default().to_lowercase();
+        self.list_id.is_none_or(|list_id| todo.list_id == list_id)
+            && self.assignee.is_none_or(|assignee| assignee.matches(todo.assignee_id))
+            && self.completed.is_none_or(|completed| todo.completed == completed)
+            && self.due_from.is_none_or(|from| todo.due_date.is_some_and(|due| due >= from))
+            && self.due_to.is_none_or(|to| todo.due_date.is_some_and(|due| due <= to))
+            && self.tag().is_none_or(|tag| todo.tags.contains(&tag))
+            && todo.text.to_lowercase().contains(&text)
+            && self.completed_since.is_none_or(|since| {
+                !todo.completed || todo.completed_at.is_some_and(|completed_at| completed_at >= since)
+            })
+            && self.cursor.as_ref().is_none_or(|cursor| cursor.is_before(todo))
+    }
+}
+
+/// `assignee=<member id>` or `assignee=unassigned`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum AssigneeFilter {
+    Unassigned,
+    Member(i32),
+}
+
+impl AssigneeFilter {
+    pub fn matches(self, assignee_id: Option<i32>) -> bool {
+        match self {
+            AssigneeFilter::Unassigned => assignee_id.is_none(),
+            AssigneeFilter::Member(id) => assignee_id == Some(id),
+        }
+    }
+}
+
+impl TryFrom<String> for AssigneeFilter {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "unassigned" => Ok(AssigneeFilter::Unassigned),
+            id => id
+                .parse()
+                .map(AssigneeFilter::Member)
+                .map_err(|_| "assignee must be a member id or \"unassigned\"".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    CreatedAt,
+    UpdatedAt,
+    DueDate,
+    Text,
+}
+
+impl SortKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortKey::CreatedAt => "created_at",
+            SortKey::UpdatedAt => "updated_at",
+            SortKey::DueDate => "due_date",
+            SortKey::Text => "text",
+        }
+    }
+
+    /// Whether listings in this order can be paged with a cursor.
+    pub fn is_pageable(self) -> bool {
+        matches!(self, SortKey::CreatedAt | SortKey::DueDate)
+    }
+}
+
+/// `sort=<key>` orders ascending, `sort=-<key>` descending. Todos without a
+/// due date come last either way when sorting by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TodoSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for TodoSort {
+    fn default() -> Self {
+        Self { key: SortKey::CreatedAt, descending: true }
+    }
+}
+
+impl TodoSort {
+    /// The `ORDER BY` clause for the SQL stores; ties are broken by id.
+    pub fn order_by(&self) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        match self.key {
+            SortKey::CreatedAt => format!("created_at {direction}, id {direction}"),
+            SortKey::UpdatedAt => format!("updated_at {direction}, id {direction}"),
+            SortKey::DueDate => format!("due_date IS NULL, due_date {direction}, id {direction}"),
+            SortKey::Text => format!("LOWER(text) {direction}, id {direction}"),
+        }
+    }
+
+    pub fn compare(&self, a: &Todo, b: &Todo) -> std::cmp::Ordering {
+        let ordering = match self.key {
+            SortKey::CreatedAt => a.created_at.cmp(&b.created_at),
+            SortKey::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            SortKey::DueDate => {
+                if a.due_date.is_none() != b.due_date.is_none() {
+                    return a.due_date.is_none().cmp(&b.due_date.is_none());
+                }
+                a.due_date.cmp(&b.due_date)
+            }
+            SortKey::Text => a.text.to_lowercase().cmp(&b.text.to_lowercase()),
+        }
+        .then(a.id.cmp(&b.id));
+
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
+
+impl TryFrom<String> for TodoSort {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (descending, key) = match value.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, value.as_str()),
+        };
+        let key = match key {
+            "created_at" => SortKey::CreatedAt,
+            "updated_at" => SortKey::UpdatedAt,
+            "due_date" => SortKey::DueDate,
+            "text" => SortKey::Text,
+            _ => return Err("sort must be created_at, updated_at, due_date or text, optionally prefixed with -".to_string()),
+        };
+        Ok(Self { key, descending })
+    }
+}
+
+impl fmt::Display for TodoSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = if self.descending { "-" } else { "" };
+        write!(f, "{}{}", direction, self.key.as_str())
+    }
+}
+
+/// Where a page of todos ended, handed out as an opaque `next_cursor` and
+/// passed back as `cursor` for the page after it. Only `created_at` and
+/// `due_date` orders can be paged, each keyed on that column and the id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TodoCursor {
+    pub sort: TodoSort,
+    pub created_at: DateTime<Utc>,
+    pub due_date: Option<NaiveDate>,
+    pub id: i32,
+}
+
+impl TodoCursor {
+    pub fn after(sort: TodoSort, todo: &Todo) -> Self {
+        Self { sort, created_at: todo.created_at, due_date: todo.due_date, id: todo.id }
+    }
+
+    /// The condition for the SQL stores, with the cursor's `created_at`,
+    /// `due_date` and `id` bound to `$11`, `$12` and `$13`.
+    pub fn keyset(&self) -> String {
+        let after = if self.sort.descending { "<" } else { ">" };
+        match (self.sort.key, self.due_date) {
+            // Undated todos come last whichever way dates are sorted
+            (SortKey::DueDate, Some(_)) => {
+                format!("(due_date IS NULL OR due_date {after} $12 OR (due_date = $12 AND id {after} $13))")
+            }
+            (SortKey::DueDate, None) => format!("(due_date IS NULL AND id {after} $13)"),
+            _ => format!("(created_at {after} $11 OR (created_at = $11 AND id {after} $13))"),
+        }
+    }
+
+    /// Whether `todo` comes after the cursor, the same test as [`keyset`](Self::keyset).
+    pub fn is_before(&self, todo: &Todo) -> bool {
+        let ordering = match self.sort.key {
+            SortKey::DueDate if self.due_date.is_none() != todo.due_date.is_none() => {
+                return todo.due_date.is_none();
+            }
+            SortKey::DueDate => (self.due_date, self.id).cmp(&(todo.due_date, todo.id)),
+            _ => (self.created_at, self.id).cmp(&(todo.created_at, todo.id)),
+        };
+
+        if self.sort.descending {
+            ordering.is_gt()
+        } else {
+            ordering.is_lt()
+        }
+    }
+}
+
+impl From<TodoCursor> for String {
+    fn from(cursor: TodoCursor) -> Self {
+        let position = (cursor.sort.to_string(), cursor.created_at, cursor.due_date, cursor.id);
+        hex::encode(serde_json::to_vec(&position).expect("cursor positions serialize"))
+    }
+}
+
+impl TryFrom<String> for TodoCursor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || "cursor must be a next_cursor from an earlier page".to_string();
+        let bytes = hex::decode(value).map_err(|_| invalid())?;
+        let (sort, created_at, due_date, id): (String, DateTime<Utc>, Option<NaiveDate>, i32) =
+            serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        let sort = TodoSort::try_from(sort).map_err(|_| invalid())?;
+
+        Ok(Self { sort, created_at, due_date, id })
+    }
+}
+
+/// Something members can spend their points on, such as screen time.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Reward {
+    pub id: i32,
+    pub household_id: i32,
+    pub name: String,
+    pub cost: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRewardRequest {
+    pub name: String,
+    pub cost: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRewardRequest {
+    pub name: Option<String>,
+    pub cost: Option<i32>,
+}
+
+/// Body of `POST /api/rewards/:id/redeem`: whose points pay for it.
+#[derive(Debug, Deserialize)]
+pub struct RedeemRewardRequest {
+    pub member_id: i32,
 }
 
+/// Why a member's points changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum PointReason {
+    TodoCompleted,
+    /// A completed todo was un-ticked, taking back its points.
+    TodoReopened,
+    RewardRedeemed,
+}
+
+/// One line of a member's points ledger.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PointEntry {
+    pub id: i32,
+    pub member_id: i32,
+    /// Positive for points earned, negative for points spent or taken back.
+    pub amount: i32,
+    pub reason: PointReason,
+    /// The todo's text or reward's name at the time.
+    pub description: String,
+    pub todo_id: Option<i32>,
+    pub reward_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response of `GET /api/members/:id/balance`.
 #[derive(Debug, Serialize)]
-pub struct TodosGroupedByDate {
-    pub date: String,
-    pub todos: Vec<Todo>,
+pub struct PointBalance {
+    pub member_id: i32,
+    pub balance: i64,
+}
+
+/// A family sharing lists, members and todos. Every account belongs to one.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Household {
+    pub id: i32,
+    pub name: String,
+    /// IANA timezone, such as `Europe/London`, that due dates and times are
+    /// read in.
+    pub timezone: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `PUT /api/household`; fields left out stay as they are.
+#[derive(Debug, Deserialize)]
+pub struct UpdateHouseholdRequest {
+    pub name: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// An account that can sign in to its household.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct User {
+    pub id: i32,
+    pub household_id: i32,
+    /// The family member this account signs in as.
+    pub member_id: Option<i32>,
+    pub email: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    /// The member's role; accounts without a member are parents.
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of `POST /api/auth/register`.
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub email: String,
+    pub password: String,
+    /// Name for the new household; defaults to "Family".
+    pub household_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+/// Body of `POST /api/users`: another account for the caller's household.
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub email: String,
+    pub password: String,
+    /// The family member the account signs in as, which gives it their role.
+    pub member_id: Option<i32>,
+}
+
+/// The signed-in account, as returned by register, login and `/api/auth/me`.
+#[derive(Debug, Serialize)]
+pub struct Account {
+    pub user: User,
+    pub household: Household,
+}
+
+/// What happened to a todo in a [`HistoryEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum HistoryAction {
+    Created,
+    Updated,
+    Completed,
+    /// A completed todo was un-ticked.
+    Reopened,
+    ApprovalRequested,
+    ApprovalWithdrawn,
+    Approved,
+    Rejected,
+    ChecklistChanged,
+    AttachmentAdded,
+    AttachmentRemoved,
+    /// Moved to the trash.
+    Deleted,
+    Restored,
+    /// Removed from the trash for good.
+    Purged,
+}
+
+/// The value of one field before and after a change; `null` on the side
+/// where the todo did not exist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// One change to a todo, for its history and the household's activity feed.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub household_id: i32,
+    pub todo_id: i32,
+    /// The todo's text at the time.
+    pub todo_text: String,
+    /// The account that made the change, and its member if it has one.
+    pub user_id: Option<i32>,
+    pub member_id: Option<i32>,
+    pub action: HistoryAction,
+    /// Only the fields that changed, by name.
+    pub changes: Json<BTreeMap<String, FieldChange>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A change to record with [`crate::store::TodoStore::add_history`].
+#[derive(Debug)]
+pub struct NewHistoryEntry {
+    pub todo_id: i32,
+    pub todo_text: String,
+    pub user_id: Option<i32>,
+    pub member_id: Option<i32>,
+    pub action: HistoryAction,
+    pub changes: BTreeMap<String, FieldChange>,
+}
+
+/// A change that was broadcast to WebSocket clients, kept so reconnecting
+/// clients can catch up.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Event {
+    /// Increases by one per event, in broadcast order.
+    pub seq: i64,
+    pub household_id: i32,
+    pub event_type: String,
+    pub list_id: Option<i32>,
+    pub data: Json<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketMessage {
     pub message_type: String,
+    /// The event's sequence number; absent on messages that are not stored
+    /// events, such as `connected`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<i64>,
+    /// The list an event belongs to; `None` for household-wide events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_id: Option<i32>,
     pub data: serde_json::Value,
-}
\ No newline at end of file
+}
+
+impl From<Event> for WebSocketMessage {
+    fn from(event: Event) -> Self {
+        Self {
+            message_type: event.event_type,
+            seq: Some(event.seq),
+            list_id: event.list_id,
+            data: event.data.0,
+        }
+    }
+}
+
+/// A command sent by a client over the WebSocket, e.g.
+/// `{"type": "toggle", "request_id": "7", "id": 12, "version": 3}`.
+/// The reply echoes `request_id` so the client can match the two up.
+#[derive(Debug, Deserialize)]
+pub struct ClientMessage {
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub command: ClientCommand,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// Same as `POST /api/todos`.
+    Create { todo: CreateTodoRequest },
+    /// Same as `PATCH /api/todos/:id`; `version` plays the part of `If-Match`.
+    Update {
+        id: i32,
+        version: Option<i32>,
+        changes: UpdateTodoRequest,
+    },
+    /// Same as `PUT /api/todos/:id/toggle`.
+    Toggle { id: i32, version: Option<i32> },
+    /// Same as `DELETE /api/todos/:id`.
+    Delete { id: i32 },
+    /// Switches the connection to the events of one list, or of all lists
+    /// when `list_id` is null, like reconnecting with `?list_id=`.
+    Subscribe { list_id: Option<i32> },
+    Ping,
+}
+
+/// The server's answer to a [`ClientMessage`]. Tagged with `message_type`
+/// like broadcast events, so clients dispatch on one field.
+#[derive(Debug, Serialize)]
+#[serde(tag = "message_type", rename_all = "snake_case")]
+pub enum ServerReply {
+    /// The command succeeded; `data` is what the REST endpoint would return.
+    Ack {
+        request_id: Option<String>,
+        data: serde_json::Value,
+    },
+    /// The command failed; `error` is the same body a REST request gets.
+    Error {
+        request_id: Option<String>,
+        error: serde_json::Value,
+    },
+    Pong { request_id: Option<String> },
+}
@@ -0,0 +1,1368 @@
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use family_todo_core::{initials_for, next_in_rotation, DEFAULT_MEMBER_COLOR};
+use sqlx::types::Json;
+
+use super::{
+    rank_matches, InsufficientPoints, StaleVersion, TodoStore, Toggled, UniqueViolation, DEFAULT_HOUSEHOLD_NAME,
+    DEFAULT_LIST_NAME, DEFAULT_TIMEZONE, EVENT_RETENTION,
+};
+use crate::models::{
+    Todo, CreateTodoRequest, UpdateTodoRequest, TodoFilter, SearchHit,
+    Member, CreateMemberRequest, UpdateMemberRequest,
+    TodoList, CreateListRequest, UpdateListRequest,
+    Event, Household, UpdateHouseholdRequest, Role, User,
+    Reward, CreateRewardRequest, UpdateRewardRequest, PointEntry, PointReason,
+    Rotation, CreateRotationRequest, UpdateRotationRequest,
+    ChecklistItem, CreateChecklistItemRequest, UpdateChecklistItemRequest, TodoComment,
+    Attachment, NewAttachment, HistoryEntry, NewHistoryEntry,
+};
+
+/// Keeps everything in process memory. Nothing survives a restart, which
+/// makes it the store of choice for tests and quick demos.
+///
+/// Starts out like a freshly migrated database: one household, not yet
+/// claimed by an account, with a "General" list and no members or todos.
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    todos: Vec<Todo>,
+    members: Vec<Member>,
+    lists: Vec<TodoList>,
+    events: Vec<Event>,
+    households: Vec<Household>,
+    users: Vec<User>,
+    sessions: Vec<Session>,
+    rewards: Vec<Reward>,
+    point_entries: Vec<PointEntry>,
+    rotations: Vec<Rotation>,
+    comments: Vec<TodoComment>,
+    history: Vec<HistoryEntry>,
+    last_todo_id: i32,
+    last_member_id: i32,
+    last_list_id: i32,
+    last_event_seq: i64,
+    last_household_id: i32,
+    last_user_id: i32,
+    last_reward_id: i32,
+    last_point_entry_id: i32,
+    last_rotation_id: i32,
+    last_checklist_item_id: i32,
+    last_comment_id: i32,
+    last_attachment_id: i32,
+    last_history_id: i64,
+}
+
+struct Session {
+    token_hash: String,
+    user_id: i32,
+    expires_at: DateTime<Utc>,
+}
+
+impl State {
+    fn list_ids(&self, household_id: i32) -> Vec<i32> {
+        self.lists
+            .iter()
+            .filter(|list| list.household_id == household_id)
+            .map(|list| list.id)
+            .collect()
+    }
+
+    fn todo_mut(&mut self, household_id: i32, id: i32) -> Option<&mut Todo> {
+        let list_ids = self.list_ids(household_id);
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id == id && list_ids.contains(&todo.list_id) && todo.deleted_at.is_none())
+    }
+
+    fn trashed_todo_mut(&mut self, household_id: i32, id: i32) -> Option<&mut Todo> {
+        let list_ids = self.list_ids(household_id);
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id == id && list_ids.contains(&todo.list_id) && todo.deleted_at.is_some())
+    }
+
+    /// Like [`State::todo_mut`], but refuses a todo that is not at `expected_version`.
+    fn todo_at_version(&mut self, household_id: i32, id: i32, expected_version: Option<i32>) -> Result<Option<&mut Todo>> {
+        match self.todo_mut(household_id, id) {
+            Some(todo) if expected_version.is_some_and(|version| version != todo.version) => {
+                Err(StaleVersion { current: todo.clone() }.into())
+            }
+            todo => Ok(todo),
+        }
+    }
+
+    /// Copies a checklist onto the todo `todo_id` under new ids, as the SQL
+    /// stores do for a next occurrence: same steps, nothing ticked.
+    fn copy_checklist(&mut self, checklist: &[ChecklistItem], todo_id: i32) -> Vec<ChecklistItem> {
+        let now = Utc::now();
+        checklist
+            .iter()
+            .map(|item| {
+                self.last_checklist_item_id += 1;
+                ChecklistItem {
+                    id: self.last_checklist_item_id,
+                    todo_id,
+                    completed: false,
+                    created_at: now,
+                    updated_at: now,
+                    ..item.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Applies a change to a todo's checklist, then re-sorts and re-counts
+    /// it and bumps the todo's version like any other write.
+    fn change_checklist(
+        &mut self,
+        household_id: i32,
+        todo_id: i32,
+        change: impl FnOnce(&mut Vec<ChecklistItem>) -> bool,
+    ) -> Option<Todo> {
+        let todo = self.todo_mut(household_id, todo_id)?;
+        let mut checklist = std::mem::take(&mut todo.checklist);
+        let changed = change(&mut checklist);
+        checklist.sort_by_key(|item| (item.position, item.id));
+        *todo = todo.clone().with_checklist(checklist);
+        if !changed {
+            return None;
+        }
+
+        todo.version += 1;
+        todo.updated_at = Utc::now();
+        Some(todo.clone())
+    }
+
+    fn insert_todo(&mut self, todo: Todo) -> Todo {
+        self.last_todo_id += 1;
+        let todo = Todo { id: self.last_todo_id, ..todo };
+        self.todos.push(todo.clone());
+        todo
+    }
+
+    fn insert_list(&mut self, household_id: i32, name: String, position: i32) -> TodoList {
+        let now = Utc::now();
+        self.last_list_id += 1;
+        let list = TodoList {
+            id: self.last_list_id,
+            household_id,
+            name,
+            position,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lists.push(list.clone());
+        list
+    }
+
+    fn insert_household(&mut self, name: String) -> Household {
+        let now = Utc::now();
+        self.last_household_id += 1;
+        let household = Household {
+            id: self.last_household_id,
+            name,
+            timezone: DEFAULT_TIMEZONE.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.households.push(household.clone());
+        self.insert_list(household.id, DEFAULT_LIST_NAME.to_string(), 0);
+        household
+    }
+
+    fn insert_user(
+        &mut self,
+        household_id: i32,
+        email: &str,
+        password_hash: &str,
+        member_id: Option<i32>,
+    ) -> Result<User> {
+        if self.users.iter().any(|user| user.email == email) {
+            return Err(UniqueViolation(email.to_string()).into());
+        }
+
+        self.last_user_id += 1;
+        let user = User {
+            id: self.last_user_id,
+            household_id,
+            member_id,
+            email: email.to_string(),
+            password_hash: password_hash.to_string(),
+            role: Role::default(),
+            created_at: Utc::now(),
+        };
+        self.users.push(user.clone());
+        Ok(self.with_role(user))
+    }
+
+    /// Fills in the user's role from their member, as the SQL stores' join does.
+    fn with_role(&self, user: User) -> User {
+        let role = user
+            .member_id
+            .and_then(|id| self.members.iter().find(|member| member.id == id))
+            .map_or_else(Role::default, |member| member.role);
+
+        User { role, ..user }
+    }
+
+    fn member_ids(&self, household_id: i32) -> Vec<i32> {
+        self.members
+            .iter()
+            .filter(|member| member.household_id == household_id)
+            .map(|member| member.id)
+            .collect()
+    }
+
+    fn rotation_mut(&mut self, household_id: i32, id: i32) -> Option<&mut Rotation> {
+        self.rotations
+            .iter_mut()
+            .find(|rotation| rotation.id == id && rotation.household_id == household_id)
+    }
+
+    /// Who the next occurrence of `todo` goes to, like the SQL stores' `toggle_todo`.
+    fn next_assignee(&self, todo: &Todo) -> Option<i32> {
+        let Some(rotation) = todo
+            .rotation_id
+            .and_then(|id| self.rotations.iter().find(|rotation| rotation.id == id))
+        else {
+            return todo.assignee_id;
+        };
+
+        let is_away = |id: i32| {
+            rotation.skip_away && self.members.iter().any(|member| member.id == id && member.away)
+        };
+        next_in_rotation(&rotation.member_ids, todo.assignee_id, is_away).or(todo.assignee_id)
+    }
+
+    fn reward_mut(&mut self, household_id: i32, id: i32) -> Option<&mut Reward> {
+        self.rewards
+            .iter_mut()
+            .find(|reward| reward.id == id && reward.household_id == household_id)
+    }
+
+    fn point_balance(&self, member_id: i32) -> i64 {
+        self.point_entries
+            .iter()
+            .filter(|entry| entry.member_id == member_id)
+            .map(|entry| i64::from(entry.amount))
+            .sum()
+    }
+
+    fn insert_point_entry(
+        &mut self,
+        member_id: i32,
+        amount: i32,
+        reason: PointReason,
+        description: String,
+        todo_id: Option<i32>,
+        reward_id: Option<i32>,
+    ) -> PointEntry {
+        self.last_point_entry_id += 1;
+        let entry = PointEntry {
+            id: self.last_point_entry_id,
+            member_id,
+            amount,
+            reason,
+            description,
+            todo_id,
+            reward_id,
+            created_at: Utc::now(),
+        };
+        self.point_entries.push(entry.clone());
+        entry
+    }
+
+    /// Credits a completed todo's assignee once, or takes back whatever an
+    /// un-ticked todo had credited, like the SQL stores' `toggle_todo`.
+    fn settle_points(&mut self, todo: &Todo) -> Vec<PointEntry> {
+        let mut outstanding: Vec<(i32, i64)> = Vec::new();
+        for entry in self.point_entries.iter().filter(|entry| entry.todo_id == Some(todo.id)) {
+            match outstanding.iter_mut().find(|(member_id, _)| *member_id == entry.member_id) {
+                Some((_, amount)) => *amount += i64::from(entry.amount),
+                None => outstanding.push((entry.member_id, i64::from(entry.amount))),
+            }
+        }
+        outstanding.retain(|(_, amount)| *amount != 0);
+
+        let changes: Vec<(i32, i32, PointReason)> = match (todo.completed, todo.assignee_id) {
+            (true, Some(assignee_id)) if todo.points > 0 && outstanding.is_empty() => {
+                vec![(assignee_id, todo.points, PointReason::TodoCompleted)]
+            }
+            (false, _) => outstanding
+                .into_iter()
+                .map(|(member_id, amount)| (member_id, -(amount as i32), PointReason::TodoReopened))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        changes
+            .into_iter()
+            .map(|(member_id, amount, reason)| {
+                self.insert_point_entry(member_id, amount, reason, todo.text.clone(), Some(todo.id), None)
+            })
+            .collect()
+    }
+
+    fn member_name_taken(&self, household_id: i32, display_name: &str, except: Option<i32>) -> bool {
+        self.members.iter().any(|member| {
+            member.household_id == household_id && member.display_name == display_name && Some(member.id) != except
+        })
+    }
+
+    fn list_name_taken(&self, household_id: i32, name: &str, except: Option<i32>) -> bool {
+        self.lists
+            .iter()
+            .any(|list| list.household_id == household_id && list.name == name && Some(list.id) != except)
+    }
+
+    /// Mirrors the foreign keys on deleted todos: `ON DELETE SET NULL` on
+    /// `next_occurrence_id` and the ledger's `todo_id`, and `ON DELETE
+    /// CASCADE` on comments.
+    fn forget_removed_todos(&mut self) {
+        let ids: Vec<i32> = self.todos.iter().map(|todo| todo.id).collect();
+        for todo in &mut self.todos {
+            if todo.next_occurrence_id.is_some_and(|id| !ids.contains(&id)) {
+                todo.next_occurrence_id = None;
+            }
+        }
+        for entry in &mut self.point_entries {
+            if entry.todo_id.is_some_and(|id| !ids.contains(&id)) {
+                entry.todo_id = None;
+            }
+        }
+        self.comments.retain(|comment| ids.contains(&comment.todo_id));
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        let mut state = State::default();
+        state.insert_household(DEFAULT_HOUSEHOLD_NAME.to_string());
+
+        Self { state: Mutex::new(state) }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("memory store lock poisoned")
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl TodoStore for MemoryStore {
+    async fn create_todo(&self, list_id: i32, request: CreateTodoRequest) -> Result<Todo> {
+        let now = Utc::now();
+        let todo = Todo {
+            id: 0,
+            list_id,
+            text: request.text,
+            assignee_id: request.assignee_id,
+            due_date: request.due_date,
+            due_time: request.due_time,
+            completed: false,
+            recurrence: request.recurrence.map(Json),
+            next_occurrence_id: None,
+            points: request.points,
+            rotation_id: request.rotation_id,
+            needs_approval: request.needs_approval,
+            approval_requested_by: None,
+            auto_complete: request.auto_complete,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            completed_at: None,
+            checklist: Vec::new(),
+            progress: Default::default(),
+            attachments: Vec::new(),
+            tags: request.tags,
+        };
+
+        Ok(self.state().insert_todo(todo))
+    }
+
+    async fn find_todos(&self, household_id: i32, filter: &TodoFilter) -> Result<Vec<Todo>> {
+        let state = self.state();
+        let list_ids = state.list_ids(household_id);
+        let mut todos: Vec<Todo> = state
+            .todos
+            .iter()
+            .filter(|todo| list_ids.contains(&todo.list_id) && todo.deleted_at.is_none())
+            .filter(|todo| filter.matches(todo))
+            .cloned()
+            .collect();
+        let sort = filter.sort.unwrap_or_default();
+        todos.sort_by(|a, b| sort.compare(a, b));
+        if let Some(limit) = filter.limit {
+            todos.truncate(limit);
+        }
+
+        Ok(todos)
+    }
+
+    async fn get_todo_by_id(&self, household_id: i32, id: i32) -> Result<Option<Todo>> {
+        Ok(self.state().todo_mut(household_id, id).map(|todo| todo.clone()))
+    }
+
+    async fn update_todo(
+        &self,
+        household_id: i32,
+        id: i32,
+        request: UpdateTodoRequest,
+        expected_version: Option<i32>,
+    ) -> Result<Option<Todo>> {
+        let mut state = self.state();
+        let Some(todo) = state.todo_at_version(household_id, id, expected_version)? else {
+            return Ok(None);
+        };
+
+        if let Some(list_id) = request.list_id {
+            todo.list_id = list_id;
+        }
+        if let Some(text) = request.text {
+            todo.text = text;
+        }
+        todo.assignee_id = request.assignee_id.apply(todo.assignee_id);
+        todo.due_date = request.due_date.apply(todo.due_date);
+        todo.due_time = todo.due_date.and(request.due_time.apply(todo.due_time));
+        todo.recurrence = request.recurrence.map(Json).apply(todo.recurrence.take());
+        if let Some(points) = request.points {
+            todo.points = points;
+        }
+        todo.rotation_id = request.rotation_id.apply(todo.rotation_id);
+        if let Some(needs_approval) = request.needs_approval {
+            todo.needs_approval = needs_approval;
+        }
+        if let Some(auto_complete) = request.auto_complete {
+            todo.auto_complete = auto_complete;
+        }
+        if let Some(tags) = request.tags {
+            todo.tags = tags;
+        }
+        todo.version += 1;
+        todo.updated_at = Utc::now();
+
+        Ok(Some(todo.clone()))
+    }
+
+    async fn toggle_todo(
+        &self,
+        household_id: i32,
+        id: i32,
+        expected_version: Option<i32>,
+    ) -> Result<Option<Toggled>> {
+        let mut state = self.state();
+        let Some(todo) = state.todo_at_version(household_id, id, expected_version)? else {
+            return Ok(None);
+        };
+
+        todo.completed = !todo.completed;
+        todo.approval_requested_by = None;
+        todo.version += 1;
+        todo.updated_at = Utc::now();
+        todo.completed_at = todo.completed.then_some(todo.updated_at);
+        let toggled = todo.clone();
+        let points = state.settle_points(&toggled);
+
+        let next_due_date = match (&toggled.recurrence, toggled.due_date) {
+            (Some(rule), Some(due_date)) if toggled.completed && toggled.next_occurrence_id.is_none() => {
+                rule.next_after(due_date)
+            }
+            _ => None,
+        };
+
+        let Some(next_due_date) = next_due_date else {
+            return Ok(Some(Toggled { todo: toggled, next_occurrence: None, points }));
+        };
+
+        let assignee_id = state.next_assignee(&toggled);
+        let mut next = state.insert_todo(Todo {
+            id: 0,
+            assignee_id,
+            due_date: Some(next_due_date),
+            completed: false,
+            completed_at: None,
+            next_occurrence_id: None,
+            approval_requested_by: None,
+            version: 1,
+            created_at: toggled.updated_at,
+            attachments: Vec::new(),
+            ..toggled.clone()
+        });
+        let checklist = state.copy_checklist(&toggled.checklist, next.id);
+        next = next.with_checklist(checklist);
+        if let Some(stored) = state.todos.iter_mut().find(|todo| todo.id == next.id) {
+            *stored = next.clone();
+        }
+        let todo = state.todo_mut(household_id, id).expect("toggled todo still exists");
+        todo.next_occurrence_id = Some(next.id);
+
+        Ok(Some(Toggled {
+            todo: todo.clone(),
+            next_occurrence: Some(next),
+            points,
+        }))
+    }
+
+    async fn set_approval_request(
+        &self,
+        household_id: i32,
+        id: i32,
+        member_id: Option<i32>,
+        expected_version: Option<i32>,
+    ) -> Result<Option<Todo>> {
+        let mut state = self.state();
+        let Some(todo) = state.todo_at_version(household_id, id, expected_version)? else {
+            return Ok(None);
+        };
+
+        todo.approval_requested_by = member_id;
+        todo.version += 1;
+        todo.updated_at = Utc::now();
+
+        Ok(Some(todo.clone()))
+    }
+
+    async fn add_checklist_item(
+        &self,
+        household_id: i32,
+        todo_id: i32,
+        request: CreateChecklistItemRequest,
+    ) -> Result<Option<Todo>> {
+        let mut state = self.state();
+        state.last_checklist_item_id += 1;
+        let id = state.last_checklist_item_id;
+
+        Ok(state.change_checklist(household_id, todo_id, |checklist| {
+            let now = Utc::now();
+            let position = request
+                .position
+                .unwrap_or_else(|| checklist.iter().map(|item| item.position + 1).max().unwrap_or(0));
+            checklist.push(ChecklistItem {
+                id,
+                todo_id,
+                text: request.text,
+                completed: false,
+                position,
+                created_at: now,
+                updated_at: now,
+            });
+            true
+        }))
+    }
+
+    async fn update_checklist_item(
+        &self,
+        household_id: i32,
+        todo_id: i32,
+        id: i32,
+        request: UpdateChecklistItemRequest,
+    ) -> Result<Option<Todo>> {
+        Ok(self.state().change_checklist(household_id, todo_id, |checklist| {
+            let Some(item) = checklist.iter_mut().find(|item| item.id == id) else {
+                return false;
+            };
+
+            if let Some(text) = request.text {
+                item.text = text;
+            }
+            if let Some(completed) = request.completed {
+                item.completed = completed;
+            }
+            if let Some(position) = request.position {
+                item.position = position;
+            }
+            item.updated_at = Utc::now();
+            true
+        }))
+    }
+
+    async fn delete_checklist_item(&self, household_id: i32, todo_id: i32, id: i32) -> Result<Option<Todo>> {
+        Ok(self.state().change_checklist(household_id, todo_id, |checklist| {
+            let before = checklist.len();
+            checklist.retain(|item| item.id != id);
+            checklist.len() != before
+        }))
+    }
+
+    async fn add_comment(&self, todo_id: i32, author_id: Option<i32>, text: &str) -> Result<TodoComment> {
+        let mut state = self.state();
+        state.last_comment_id += 1;
+        let comment = TodoComment {
+            id: state.last_comment_id,
+            todo_id,
+            author_id,
+            text: text.to_string(),
+            created_at: Utc::now(),
+        };
+        state.comments.push(comment.clone());
+
+        Ok(comment)
+    }
+
+    async fn get_comments(&self, household_id: i32, todo_id: i32) -> Result<Vec<TodoComment>> {
+        let mut state = self.state();
+        if state.todo_mut(household_id, todo_id).is_none() {
+            return Ok(Vec::new());
+        }
+
+        Ok(state
+            .comments
+            .iter()
+            .filter(|comment| comment.todo_id == todo_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn search_todos(&self, household_id: i32, query: &str, limit: i64) -> Result<Vec<SearchHit>> {
+        let state = self.state();
+        let list_ids = state.list_ids(household_id);
+        let documents = state
+            .todos
+            .iter()
+            .filter(|todo| list_ids.contains(&todo.list_id) && todo.deleted_at.is_none())
+            .map(|todo| {
+                let comments = state
+                    .comments
+                    .iter()
+                    .filter(|comment| comment.todo_id == todo.id)
+                    .map(|comment| comment.text.clone())
+                    .collect();
+                (todo.clone(), comments)
+            })
+            .collect();
+
+        Ok(rank_matches(query, documents, limit))
+    }
+
+    async fn add_attachment(&self, todo_id: i32, attachment: NewAttachment) -> Result<Attachment> {
+        let mut state = self.state();
+        state.last_attachment_id += 1;
+        let now = Utc::now();
+        let attachment = Attachment {
+            id: state.last_attachment_id,
+            todo_id,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size: attachment.size,
+            sha256: attachment.sha256,
+            has_thumbnail: attachment.has_thumbnail,
+            uploaded_by: attachment.uploaded_by,
+            created_at: now,
+        };
+        if let Some(todo) = state.todos.iter_mut().find(|todo| todo.id == todo_id) {
+            todo.attachments.push(attachment.clone());
+            todo.version += 1;
+            todo.updated_at = now;
+        }
+
+        Ok(attachment)
+    }
+
+    async fn get_attachment(&self, household_id: i32, id: i32) -> Result<Option<Attachment>> {
+        let state = self.state();
+        let list_ids = state.list_ids(household_id);
+
+        Ok(state
+            .todos
+            .iter()
+            .filter(|todo| list_ids.contains(&todo.list_id) && todo.deleted_at.is_none())
+            .flat_map(|todo| &todo.attachments)
+            .find(|attachment| attachment.id == id)
+            .cloned())
+    }
+
+    async fn delete_attachment(&self, household_id: i32, id: i32) -> Result<Option<Attachment>> {
+        let mut state = self.state();
+        let list_ids = state.list_ids(household_id);
+        let Some(todo) = state
+            .todos
+            .iter_mut()
+            .find(|todo| list_ids.contains(&todo.list_id) && todo.deleted_at.is_none() && todo.attachments.iter().any(|attachment| attachment.id == id))
+        else {
+            return Ok(None);
+        };
+
+        let index = todo.attachments.iter().position(|attachment| attachment.id == id).expect("found above");
+        let attachment = todo.attachments.remove(index);
+        todo.version += 1;
+        todo.updated_at = Utc::now();
+
+        Ok(Some(attachment))
+    }
+
+    async fn attachment_in_use(&self, sha256: &str) -> Result<bool> {
+        Ok(self
+            .state()
+            .todos
+            .iter()
+            .flat_map(|todo| &todo.attachments)
+            .any(|attachment| attachment.sha256 == sha256))
+    }
+
+    async fn delete_todo(&self, household_id: i32, id: i32) -> Result<Option<i32>> {
+        let mut state = self.state();
+        let Some(todo) = state.todo_mut(household_id, id) else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        todo.deleted_at = Some(now);
+        todo.updated_at = now;
+        todo.version += 1;
+
+        Ok(Some(todo.list_id))
+    }
+
+    async fn get_trash(&self, household_id: i32) -> Result<Vec<Todo>> {
+        let state = self.state();
+        let list_ids = state.list_ids(household_id);
+        let mut trash: Vec<Todo> = state
+            .todos
+            .iter()
+            .filter(|todo| list_ids.contains(&todo.list_id) && todo.deleted_at.is_some())
+            .cloned()
+            .collect();
+        trash.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then(b.id.cmp(&a.id)));
+
+        Ok(trash)
+    }
+
+    async fn restore_todo(&self, household_id: i32, id: i32) -> Result<Option<Todo>> {
+        let mut state = self.state();
+        let Some(todo) = state.trashed_todo_mut(household_id, id) else {
+            return Ok(None);
+        };
+
+        todo.deleted_at = None;
+        todo.updated_at = Utc::now();
+        todo.version += 1;
+
+        Ok(Some(todo.clone()))
+    }
+
+    async fn purge_todo(&self, household_id: i32, id: i32) -> Result<Option<i32>> {
+        let mut state = self.state();
+        let Some(list_id) = state.trashed_todo_mut(household_id, id).map(|todo| todo.list_id) else {
+            return Ok(None);
+        };
+
+        state.todos.retain(|todo| todo.id != id);
+        state.forget_removed_todos();
+
+        Ok(Some(list_id))
+    }
+
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<u64> {
+        let mut state = self.state();
+        let before = state.todos.len();
+        state.todos.retain(|todo| todo.deleted_at.is_none_or(|deleted_at| deleted_at >= deleted_before));
+        let purged = before - state.todos.len();
+        state.forget_removed_todos();
+
+        Ok(purged as u64)
+    }
+
+    async fn create_member(&self, household_id: i32, request: CreateMemberRequest) -> Result<Member> {
+        let mut state = self.state();
+        if state.member_name_taken(household_id, &request.display_name, None) {
+            return Err(UniqueViolation(request.display_name).into());
+        }
+
+        let now = Utc::now();
+        state.last_member_id += 1;
+        let member = Member {
+            id: state.last_member_id,
+            household_id,
+            initials: request.initials.unwrap_or_else(|| initials_for(&request.display_name)),
+            color: request.color.unwrap_or_else(|| DEFAULT_MEMBER_COLOR.to_string()),
+            display_name: request.display_name,
+            role: request.role.unwrap_or_default(),
+            away: request.away,
+            created_at: now,
+            updated_at: now,
+        };
+        state.members.push(member.clone());
+
+        Ok(member)
+    }
+
+    async fn get_members(&self, household_id: i32) -> Result<Vec<Member>> {
+        let mut members: Vec<Member> = self
+            .state()
+            .members
+            .iter()
+            .filter(|member| member.household_id == household_id)
+            .cloned()
+            .collect();
+        members.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+
+        Ok(members)
+    }
+
+    async fn get_member_by_id(&self, household_id: i32, id: i32) -> Result<Option<Member>> {
+        Ok(self
+            .state()
+            .members
+            .iter()
+            .find(|member| member.id == id && member.household_id == household_id)
+            .cloned())
+    }
+
+    async fn update_member(&self, household_id: i32, id: i32, request: UpdateMemberRequest) -> Result<Option<Member>> {
+        let mut state = self.state();
+        if let Some(display_name) = &request.display_name {
+            if state.member_name_taken(household_id, display_name, Some(id)) {
+                return Err(UniqueViolation(display_name.clone()).into());
+            }
+        }
+
+        let Some(member) = state
+            .members
+            .iter_mut()
+            .find(|member| member.id == id && member.household_id == household_id)
+        else {
+            return Ok(None);
+        };
+
+        if let Some(display_name) = request.display_name {
+            member.display_name = display_name;
+        }
+        if let Some(color) = request.color {
+            member.color = color;
+        }
+        if let Some(initials) = request.initials {
+            member.initials = initials;
+        }
+        if let Some(role) = request.role {
+            member.role = role;
+        }
+        if let Some(away) = request.away {
+            member.away = away;
+        }
+        member.updated_at = Utc::now();
+
+        Ok(Some(member.clone()))
+    }
+
+    async fn delete_member(&self, household_id: i32, id: i32) -> Result<bool> {
+        let mut state = self.state();
+        let before = state.members.len();
+        state.members.retain(|member| member.id != id || member.household_id != household_id);
+        if state.members.len() == before {
+            return Ok(false);
+        }
+
+        for todo in &mut state.todos {
+            if todo.assignee_id == Some(id) {
+                todo.assignee_id = None;
+            }
+            if todo.approval_requested_by == Some(id) {
+                todo.approval_requested_by = None;
+            }
+        }
+
+        state.point_entries.retain(|entry| entry.member_id != id);
+        for comment in &mut state.comments {
+            if comment.author_id == Some(id) {
+                comment.author_id = None;
+            }
+        }
+        for attachment in state.todos.iter_mut().flat_map(|todo| &mut todo.attachments) {
+            if attachment.uploaded_by == Some(id) {
+                attachment.uploaded_by = None;
+            }
+        }
+        for rotation in &mut state.rotations {
+            rotation.member_ids.retain(|member_id| *member_id != id);
+        }
+
+        // Mirrors `ON DELETE CASCADE` on `users.member_id`, sessions included
+        let removed: Vec<i32> = state
+            .users
+            .iter()
+            .filter(|user| user.member_id == Some(id))
+            .map(|user| user.id)
+            .collect();
+        state.users.retain(|user| !removed.contains(&user.id));
+        state.sessions.retain(|session| !removed.contains(&session.user_id));
+        for entry in &mut state.history {
+            if entry.member_id == Some(id) {
+                entry.member_id = None;
+            }
+            if entry.user_id.is_some_and(|user_id| removed.contains(&user_id)) {
+                entry.user_id = None;
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn create_list(&self, household_id: i32, request: CreateListRequest) -> Result<TodoList> {
+        let mut state = self.state();
+        if state.list_name_taken(household_id, &request.name, None) {
+            return Err(UniqueViolation(request.name).into());
+        }
+
+        let position = request.position.unwrap_or_else(|| {
+            state
+                .lists
+                .iter()
+                .filter(|list| list.household_id == household_id)
+                .map(|list| list.position + 1)
+                .max()
+                .unwrap_or(0)
+        });
+
+        Ok(state.insert_list(household_id, request.name, position))
+    }
+
+    async fn get_lists(&self, household_id: i32) -> Result<Vec<TodoList>> {
+        let mut lists: Vec<TodoList> = self
+            .state()
+            .lists
+            .iter()
+            .filter(|list| list.household_id == household_id)
+            .cloned()
+            .collect();
+        lists.sort_by_key(|list| (list.position, list.id));
+
+        Ok(lists)
+    }
+
+    async fn get_list_by_id(&self, household_id: i32, id: i32) -> Result<Option<TodoList>> {
+        Ok(self
+            .state()
+            .lists
+            .iter()
+            .find(|list| list.id == id && list.household_id == household_id)
+            .cloned())
+    }
+
+    async fn get_default_list(&self, household_id: i32) -> Result<Option<TodoList>> {
+        Ok(self.get_lists(household_id).await?.into_iter().next())
+    }
+
+    async fn update_list(&self, household_id: i32, id: i32, request: UpdateListRequest) -> Result<Option<TodoList>> {
+        let mut state = self.state();
+        if let Some(name) = &request.name {
+            if state.list_name_taken(household_id, name, Some(id)) {
+                return Err(UniqueViolation(name.clone()).into());
+            }
+        }
+
+        let Some(list) = state
+            .lists
+            .iter_mut()
+            .find(|list| list.id == id && list.household_id == household_id)
+        else {
+            return Ok(None);
+        };
+
+        if let Some(name) = request.name {
+            list.name = name;
+        }
+        if let Some(position) = request.position {
+            list.position = position;
+        }
+        list.updated_at = Utc::now();
+
+        Ok(Some(list.clone()))
+    }
+
+    async fn delete_list(&self, household_id: i32, id: i32) -> Result<bool> {
+        let mut state = self.state();
+        let before = state.lists.len();
+        state.lists.retain(|list| list.id != id || list.household_id != household_id);
+        if state.lists.len() == before {
+            return Ok(false);
+        }
+
+        state.todos.retain(|todo| todo.list_id != id);
+        state.forget_removed_todos();
+
+        Ok(true)
+    }
+
+    async fn create_rotation(&self, household_id: i32, request: CreateRotationRequest) -> Result<Rotation> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.last_rotation_id += 1;
+        let rotation = Rotation {
+            id: state.last_rotation_id,
+            household_id,
+            member_ids: request.member_ids,
+            skip_away: request.skip_away,
+            created_at: now,
+            updated_at: now,
+        };
+        state.rotations.push(rotation.clone());
+
+        Ok(rotation)
+    }
+
+    async fn get_rotations(&self, household_id: i32) -> Result<Vec<Rotation>> {
+        Ok(self
+            .state()
+            .rotations
+            .iter()
+            .filter(|rotation| rotation.household_id == household_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_rotation_by_id(&self, household_id: i32, id: i32) -> Result<Option<Rotation>> {
+        Ok(self.state().rotation_mut(household_id, id).map(|rotation| rotation.clone()))
+    }
+
+    async fn update_rotation(&self, household_id: i32, id: i32, request: UpdateRotationRequest) -> Result<Option<Rotation>> {
+        let mut state = self.state();
+        let Some(rotation) = state.rotation_mut(household_id, id) else {
+            return Ok(None);
+        };
+
+        if let Some(member_ids) = request.member_ids {
+            rotation.member_ids = member_ids;
+        }
+        if let Some(skip_away) = request.skip_away {
+            rotation.skip_away = skip_away;
+        }
+        rotation.updated_at = Utc::now();
+
+        Ok(Some(rotation.clone()))
+    }
+
+    async fn delete_rotation(&self, household_id: i32, id: i32) -> Result<bool> {
+        let mut state = self.state();
+        let before = state.rotations.len();
+        state.rotations.retain(|rotation| rotation.id != id || rotation.household_id != household_id);
+        if state.rotations.len() == before {
+            return Ok(false);
+        }
+
+        // Mirrors `ON DELETE SET NULL` on `todos.rotation_id`
+        for todo in &mut state.todos {
+            if todo.rotation_id == Some(id) {
+                todo.rotation_id = None;
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn create_reward(&self, household_id: i32, request: CreateRewardRequest) -> Result<Reward> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.last_reward_id += 1;
+        let reward = Reward {
+            id: state.last_reward_id,
+            household_id,
+            name: request.name,
+            cost: request.cost,
+            created_at: now,
+            updated_at: now,
+        };
+        state.rewards.push(reward.clone());
+
+        Ok(reward)
+    }
+
+    async fn get_rewards(&self, household_id: i32) -> Result<Vec<Reward>> {
+        let mut rewards: Vec<Reward> = self
+            .state()
+            .rewards
+            .iter()
+            .filter(|reward| reward.household_id == household_id)
+            .cloned()
+            .collect();
+        rewards.sort_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(rewards)
+    }
+
+    async fn get_reward_by_id(&self, household_id: i32, id: i32) -> Result<Option<Reward>> {
+        Ok(self.state().reward_mut(household_id, id).map(|reward| reward.clone()))
+    }
+
+    async fn update_reward(&self, household_id: i32, id: i32, request: UpdateRewardRequest) -> Result<Option<Reward>> {
+        let mut state = self.state();
+        let Some(reward) = state.reward_mut(household_id, id) else {
+            return Ok(None);
+        };
+
+        if let Some(name) = request.name {
+            reward.name = name;
+        }
+        if let Some(cost) = request.cost {
+            reward.cost = cost;
+        }
+        reward.updated_at = Utc::now();
+
+        Ok(Some(reward.clone()))
+    }
+
+    async fn delete_reward(&self, household_id: i32, id: i32) -> Result<bool> {
+        let mut state = self.state();
+        let before = state.rewards.len();
+        state.rewards.retain(|reward| reward.id != id || reward.household_id != household_id);
+        if state.rewards.len() == before {
+            return Ok(false);
+        }
+
+        for entry in &mut state.point_entries {
+            if entry.reward_id == Some(id) {
+                entry.reward_id = None;
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn redeem_reward(&self, household_id: i32, reward_id: i32, member_id: i32) -> Result<Option<PointEntry>> {
+        let mut state = self.state();
+        let Some(reward) = state.reward_mut(household_id, reward_id).map(|reward| reward.clone()) else {
+            return Ok(None);
+        };
+
+        let balance = state.point_balance(member_id);
+        if balance < i64::from(reward.cost) {
+            return Err(InsufficientPoints { balance }.into());
+        }
+
+        let entry = state.insert_point_entry(
+            member_id,
+            -reward.cost,
+            PointReason::RewardRedeemed,
+            reward.name,
+            None,
+            Some(reward.id),
+        );
+        Ok(Some(entry))
+    }
+
+    async fn get_point_balance(&self, household_id: i32, member_id: i32) -> Result<i64> {
+        let state = self.state();
+        if !state.member_ids(household_id).contains(&member_id) {
+            return Ok(0);
+        }
+
+        Ok(state.point_balance(member_id))
+    }
+
+    async fn get_point_entries(&self, household_id: i32, member_id: i32) -> Result<Vec<PointEntry>> {
+        let state = self.state();
+        if !state.member_ids(household_id).contains(&member_id) {
+            return Ok(Vec::new());
+        }
+
+        Ok(state
+            .point_entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.member_id == member_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn add_history(&self, household_id: i32, entry: NewHistoryEntry) -> Result<HistoryEntry> {
+        let mut state = self.state();
+        state.last_history_id += 1;
+        let entry = HistoryEntry {
+            id: state.last_history_id,
+            household_id,
+            todo_id: entry.todo_id,
+            todo_text: entry.todo_text,
+            user_id: entry.user_id,
+            member_id: entry.member_id,
+            action: entry.action,
+            changes: Json(entry.changes),
+            created_at: Utc::now(),
+        };
+        state.history.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn get_todo_history(&self, household_id: i32, todo_id: i32) -> Result<Vec<HistoryEntry>> {
+        Ok(self
+            .state()
+            .history
+            .iter()
+            .rev()
+            .filter(|entry| entry.household_id == household_id && entry.todo_id == todo_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_activity(&self, household_id: i32, before: Option<i64>, limit: i64) -> Result<Vec<HistoryEntry>> {
+        let limit = usize::try_from(limit).unwrap_or(0);
+
+        Ok(self
+            .state()
+            .history
+            .iter()
+            .rev()
+            .filter(|entry| entry.household_id == household_id && before.is_none_or(|before| entry.id < before))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn append_event(
+        &self,
+        household_id: i32,
+        event_type: &str,
+        list_id: Option<i32>,
+        data: serde_json::Value,
+    ) -> Result<Event> {
+        let mut state = self.state();
+        state.last_event_seq += 1;
+        let event = Event {
+            seq: state.last_event_seq,
+            household_id,
+            event_type: event_type.to_string(),
+            list_id,
+            data: Json(data),
+            created_at: Utc::now(),
+        };
+        state.events.push(event.clone());
+        let household_events = state.events.iter().filter(|kept| kept.household_id == household_id).count();
+        let mut excess = household_events.saturating_sub(EVENT_RETENTION as usize);
+        state.events.retain(|kept| {
+            let pruned = excess > 0 && kept.household_id == household_id;
+            if pruned {
+                excess -= 1;
+            }
+            !pruned
+        });
+
+        Ok(event)
+    }
+
+    async fn get_events_since(&self, household_id: i32, since: i64, limit: i64) -> Result<Vec<Event>> {
+        let limit = usize::try_from(limit).unwrap_or(0);
+
+        Ok(self
+            .state()
+            .events
+            .iter()
+            .filter(|event| event.household_id == household_id && event.seq > since)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn latest_event_seq(&self, household_id: i32) -> Result<i64> {
+        let state = self.state();
+        let latest = state.events.iter().rev().find(|event| event.household_id == household_id);
+        Ok(latest.map_or(0, |event| event.seq))
+    }
+
+    async fn register_user(
+        &self,
+        email: &str,
+        password_hash: &str,
+        household_name: Option<String>,
+    ) -> Result<(User, Household)> {
+        let mut state = self.state();
+        if state.users.iter().any(|user| user.email == email) {
+            return Err(UniqueViolation(email.to_string()).into());
+        }
+
+        // Before the first account there is only the seeded household
+        let household = match (state.users.is_empty(), household_name) {
+            (true, None) => state.households[0].clone(),
+            (true, Some(name)) => {
+                let household = &mut state.households[0];
+                household.name = name;
+                household.updated_at = Utc::now();
+                household.clone()
+            }
+            (false, name) => state.insert_household(name.unwrap_or_else(|| DEFAULT_HOUSEHOLD_NAME.to_string())),
+        };
+        let user = state.insert_user(household.id, email, password_hash, None)?;
+
+        Ok((user, household))
+    }
+
+    async fn create_user(
+        &self,
+        household_id: i32,
+        email: &str,
+        password_hash: &str,
+        member_id: Option<i32>,
+    ) -> Result<User> {
+        self.state().insert_user(household_id, email, password_hash, member_id)
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        let state = self.state();
+        let user = state.users.iter().find(|user| user.email == email).cloned();
+
+        Ok(user.map(|user| state.with_role(user)))
+    }
+
+    async fn get_household(&self, id: i32) -> Result<Option<Household>> {
+        Ok(self.state().households.iter().find(|household| household.id == id).cloned())
+    }
+
+    async fn update_household(&self, id: i32, request: UpdateHouseholdRequest) -> Result<Option<Household>> {
+        let mut state = self.state();
+        let Some(household) = state.households.iter_mut().find(|household| household.id == id) else {
+            return Ok(None);
+        };
+        if let Some(name) = request.name {
+            household.name = name;
+        }
+        if let Some(timezone) = request.timezone {
+            household.timezone = timezone;
+        }
+        household.updated_at = Utc::now();
+
+        Ok(Some(household.clone()))
+    }
+
+    async fn create_session(&self, token_hash: &str, user_id: i32, expires_at: DateTime<Utc>) -> Result<()> {
+        let mut state = self.state();
+        let now = Utc::now();
+        state.sessions.retain(|session| session.expires_at > now);
+        state.sessions.push(Session {
+            token_hash: token_hash.to_string(),
+            user_id,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    async fn get_session_user(&self, token_hash: &str) -> Result<Option<User>> {
+        let state = self.state();
+        let now = Utc::now();
+        let user = state
+            .sessions
+            .iter()
+            .find(|session| session.token_hash == token_hash && session.expires_at > now)
+            .and_then(|session| state.users.iter().find(|user| user.id == session.user_id))
+            .cloned();
+
+        Ok(user.map(|user| state.with_role(user)))
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> Result<()> {
+        self.state().sessions.retain(|session| session.token_hash != token_hash);
+
+        Ok(())
+    }
+}
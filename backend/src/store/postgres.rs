@@ -0,0 +1,87 @@
+use anyhow::Result;
+use family_todo_core::search::{search_terms, HIGHLIGHT_END, HIGHLIGHT_START};
+use sqlx::PgPool;
+
+use super::sql::impl_todo_store;
+use super::TodoStore;
+use crate::models::SearchHit;
+
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    /// Holds a member's row until the transaction ends.
+    const LOCK_MEMBER: &'static str = "SELECT id FROM members WHERE id = $1 FOR UPDATE";
+
+    /// Holds the oldest household's row, the one the first account claims.
+    const LOCK_FIRST_HOUSEHOLD: &'static str =
+        "SELECT id FROM households WHERE id = (SELECT MIN(id) FROM households) FOR UPDATE";
+
+    pub async fn new(database_url: &str) -> Result<Self> {
+        let pool = PgPool::connect(database_url).await?;
+
+        Ok(Self { pool })
+    }
+
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!("./migrations").run(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Full-text search over the `search_vector` the triggers keep up to
+    /// date. Every word has to match, the last letters of each may be left off.
+    async fn search(&self, household_id: i32, query: &str, limit: i64) -> Result<Vec<SearchHit>> {
+        // Only letters and digits reach the tsquery, so the user cannot inject operators
+        let tsquery = search_terms(query)
+            .iter()
+            .map(|term| format!("{}:*", term))
+            .collect::<Vec<_>>()
+            .join(" & ");
+        if tsquery.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let matches = sqlx::query_as::<_, (i32, f32, String)>(
+            r#"
+            SELECT t.id,
+                   ts_rank(t.search_vector, q.query),
+                   ts_headline(
+                       'english',
+                       concat_ws(' … ', t.text, tags.text, items.text, comments.text),
+                       q.query,
+                       $4
+                   )
+            FROM todos t
+            CROSS JOIN to_tsquery('english', $2) AS q(query)
+            LEFT JOIN LATERAL (SELECT string_agg(tag, ' ') AS text FROM todo_tags WHERE todo_id = t.id) tags ON TRUE
+            LEFT JOIN LATERAL (SELECT string_agg(text, ' … ') AS text FROM checklist_items WHERE todo_id = t.id) items ON TRUE
+            LEFT JOIN LATERAL (SELECT string_agg(text, ' … ') AS text FROM todo_comments WHERE todo_id = t.id) comments ON TRUE
+            WHERE t.list_id IN (SELECT id FROM lists WHERE household_id = $1)
+              AND t.deleted_at IS NULL
+              AND t.search_vector @@ q.query
+            ORDER BY 2 DESC, t.id DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(household_id)
+        .bind(&tsquery)
+        .bind(limit)
+        .bind(format!(
+            "StartSel={}, StopSel={}, MinWords=4, MaxWords=12, MaxFragments=2, FragmentDelimiter=\" … \"",
+            HIGHLIGHT_START, HIGHLIGHT_END
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut hits = Vec::with_capacity(matches.len());
+        for (id, rank, snippet) in matches {
+            if let Some(todo) = self.get_todo_by_id(household_id, id).await? {
+                hits.push(SearchHit { todo, rank: f64::from(rank), snippet });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+impl_todo_store!(PostgresStore, sqlx::Postgres);
@@ -0,0 +1,1735 @@
+//! The [`TodoStore`](super::TodoStore) implementation shared by Postgres and
+//! SQLite.
+//!
+//! Both stores keep a `pool` field and the queries below stick to SQL that
+//! both databases accept: `$n` placeholders, `RETURNING`, and timestamps bound
+//! from Rust rather than `NOW()`, so one body serves both pool types.
+//! Search is the exception: each store brings its own inherent `search`, and
+//! its own `LOCK_MEMBER` and `LOCK_FIRST_HOUSEHOLD` statements since only
+//! Postgres has `FOR UPDATE`. The macro is also given the store's database
+//! type, for helpers that take a transaction.
+
+/// Most todos whose details are read with one query, well below the number
+/// of bind parameters either database allows.
+pub(crate) const DETAIL_BATCH: usize = 500;
+
+/// `$1, $2, ..., $n`, for an `IN` list of `n` bound values.
+pub(crate) fn placeholders(n: usize) -> String {
+    (1..=n).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ")
+}
+
+macro_rules! impl_todo_store {
+    ($store:ty, $db:ty) => {
+        #[async_trait::async_trait]
+        impl $crate::store::TodoStore for $store {
+            async fn create_todo(
+                &self,
+                list_id: i32,
+                request: $crate::models::CreateTodoRequest,
+            ) -> anyhow::Result<$crate::models::Todo> {
+                let now = chrono::Utc::now();
+
+                let mut tx = self.pool.begin().await?;
+                let todo = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    INSERT INTO todos (list_id, text, assignee_id, due_date, recurrence, points, rotation_id, needs_approval, auto_complete, created_at, updated_at, due_time)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10, $11)
+                    RETURNING id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    "#
+                )
+                .bind(list_id)
+                .bind(&request.text)
+                .bind(request.assignee_id)
+                .bind(request.due_date)
+                .bind(request.recurrence.map(sqlx::types::Json))
+                .bind(request.points)
+                .bind(request.rotation_id)
+                .bind(request.needs_approval)
+                .bind(request.auto_complete)
+                .bind(now)
+                .bind(request.due_time)
+                .fetch_one(&mut *tx)
+                .await?;
+                Self::set_tags(&mut tx, todo.id, &request.tags).await?;
+                tx.commit().await?;
+
+                Ok($crate::models::Todo { tags: request.tags, ..todo })
+            }
+
+            async fn find_todos(
+                &self,
+                household_id: i32,
+                filter: &$crate::models::TodoFilter,
+            ) -> anyhow::Result<Vec<$crate::models::Todo>> {
+                let assignee_id = match filter.assignee {
+                    Some($crate::models::AssigneeFilter::Member(id)) => Some(id),
+                    _ => None,
+                };
+                let unassigned = filter.assignee == Some($crate::models::AssigneeFilter::Unassigned);
+                let after = filter.cursor.as_ref().map(|cursor| format!("AND {}", cursor.keyset()));
+                let limit = filter.limit.map(|limit| format!("LIMIT {}", limit));
+
+                let sql = format!(
+                    r#"
+                    SELECT id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    FROM todos
+                    WHERE list_id IN (SELECT id FROM lists WHERE household_id = $1)
+                      AND deleted_at IS NULL
+                      AND ($2 IS NULL OR list_id = $2)
+                      AND ($3 IS NULL OR assignee_id = $3)
+                      AND ($4 = FALSE OR assignee_id IS NULL)
+                      AND ($5 IS NULL OR completed = $5)
+                      AND ($6 IS NULL OR due_date >= $6)
+                      AND ($7 IS NULL OR due_date <= $7)
+                      AND ($8 IS NULL OR id IN (SELECT todo_id FROM todo_tags WHERE tag = $8))
+                      AND ($9 IS NULL OR LOWER(text) LIKE $9 ESCAPE '\')
+                      AND ($10 IS NULL OR completed = FALSE OR completed_at >= $10)
+                      {}
+                    ORDER BY {}
+                    {}
+                    "#,
+                    after.unwrap_or_default(),
+                    filter.sort.unwrap_or_default().order_by(),
+                    limit.unwrap_or_default()
+                );
+                let mut query = sqlx::query_as::<_, $crate::models::Todo>(&sql)
+                    .bind(household_id)
+                    .bind(filter.list_id)
+                    .bind(assignee_id)
+                    .bind(unassigned)
+                    .bind(filter.completed)
+                    .bind(filter.due_from)
+                    .bind(filter.due_to)
+                    .bind(filter.tag())
+                    .bind(filter.text_pattern())
+                    .bind(filter.completed_since);
+                if let Some(cursor) = &filter.cursor {
+                    query = query.bind(cursor.created_at).bind(cursor.due_date).bind(cursor.id);
+                }
+                let todos = query.fetch_all(&self.pool).await?;
+
+                self.load_details_of(todos).await
+            }
+
+            async fn get_todo_by_id(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Todo>> {
+                let todo = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    SELECT id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    FROM todos
+                    WHERE id = $1 AND list_id IN (SELECT id FROM lists WHERE household_id = $2)
+                      AND deleted_at IS NULL
+                    "#
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                match todo {
+                    Some(todo) => Ok(Some(self.load_details(todo).await?)),
+                    None => Ok(None),
+                }
+            }
+
+            async fn update_todo(
+                &self,
+                household_id: i32,
+                id: i32,
+                request: $crate::models::UpdateTodoRequest,
+                expected_version: Option<i32>,
+            ) -> anyhow::Result<Option<$crate::models::Todo>> {
+                // First get the current todo
+                let current_todo = match self.get_todo_by_id(household_id, id).await? {
+                    Some(todo) => todo,
+                    None => return Ok(None),
+                };
+                if expected_version.is_some_and(|version| version != current_todo.version) {
+                    return Err($crate::store::StaleVersion { current: current_todo }.into());
+                }
+
+                // Use current values as defaults
+                let list_id = request.list_id.unwrap_or(current_todo.list_id);
+                let text = request.text.unwrap_or(current_todo.text);
+                let assignee_id = request.assignee_id.apply(current_todo.assignee_id);
+                let due_date = request.due_date.apply(current_todo.due_date);
+                let due_time = due_date.and(request.due_time.apply(current_todo.due_time));
+                let recurrence = request.recurrence.map(sqlx::types::Json).apply(current_todo.recurrence);
+                let points = request.points.unwrap_or(current_todo.points);
+                let rotation_id = request.rotation_id.apply(current_todo.rotation_id);
+                let needs_approval = request.needs_approval.unwrap_or(current_todo.needs_approval);
+                let auto_complete = request.auto_complete.unwrap_or(current_todo.auto_complete);
+
+                let mut tx = self.pool.begin().await?;
+                let todo = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    UPDATE todos
+                    SET list_id = $1, text = $2, assignee_id = $3, due_date = $4, due_time = $5,
+                        recurrence = $6, points = $7, rotation_id = $8, needs_approval = $9, auto_complete = $10,
+                        updated_at = $11, version = version + 1
+                    WHERE id = $12 AND ($13 IS NULL OR version = $13) AND deleted_at IS NULL
+                    RETURNING id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    "#
+                )
+                .bind(list_id)
+                .bind(&text)
+                .bind(assignee_id)
+                .bind(due_date)
+                .bind(due_time)
+                .bind(recurrence)
+                .bind(points)
+                .bind(rotation_id)
+                .bind(needs_approval)
+                .bind(auto_complete)
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .bind(expected_version)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                match todo {
+                    Some(todo) => {
+                        if let Some(tags) = request.tags {
+                            Self::set_tags(&mut tx, id, &tags).await?;
+                        }
+                        tx.commit().await?;
+                        Ok(Some(self.load_details(todo).await?))
+                    }
+                    // Changed or deleted between the read above and this write
+                    None => {
+                        tx.rollback().await?;
+                        match self.get_todo_by_id(household_id, id).await? {
+                            Some(current) => Err($crate::store::StaleVersion { current }.into()),
+                            None => Ok(None),
+                        }
+                    }
+                }
+            }
+
+            async fn toggle_todo(
+                &self,
+                household_id: i32,
+                id: i32,
+                expected_version: Option<i32>,
+            ) -> anyhow::Result<Option<$crate::store::Toggled>> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+
+                let todo = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    UPDATE todos
+                    SET completed = NOT completed, completed_at = CASE WHEN completed THEN NULL ELSE $2 END,
+                        approval_requested_by = NULL, updated_at = $2, version = version + 1
+                    WHERE id = $1 AND ($3 IS NULL OR version = $3)
+                      AND list_id IN (SELECT id FROM lists WHERE household_id = $4)
+                      AND deleted_at IS NULL
+                    RETURNING id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    "#
+                )
+                .bind(id)
+                .bind(now)
+                .bind(expected_version)
+                .bind(household_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                let Some(mut todo) = todo else {
+                    drop(tx);
+                    return match self.get_todo_by_id(household_id, id).await? {
+                        Some(current) => Err($crate::store::StaleVersion { current }.into()),
+                        None => Ok(None),
+                    };
+                };
+
+                let next_due_date = match (&todo.recurrence, todo.due_date) {
+                    (Some(rule), Some(due_date)) if todo.completed && todo.next_occurrence_id.is_none() => {
+                        rule.next_after(due_date)
+                    }
+                    _ => None,
+                };
+
+                let next_occurrence = match next_due_date {
+                    Some(next_due_date) => {
+                        let assignee_id = match todo.rotation_id {
+                            Some(rotation_id) => {
+                                let turns = sqlx::query_as::<_, (i32, bool)>(
+                                    r#"
+                                    SELECT rm.member_id, r.skip_away AND m.away
+                                    FROM rotation_members rm
+                                    JOIN rotations r ON r.id = rm.rotation_id
+                                    JOIN members m ON m.id = rm.member_id
+                                    WHERE rm.rotation_id = $1
+                                    ORDER BY rm.position
+                                    "#
+                                )
+                                .bind(rotation_id)
+                                .fetch_all(&mut *tx)
+                                .await?;
+
+                                let order: Vec<i32> = turns.iter().map(|(member_id, _)| *member_id).collect();
+                                let is_away = |id: i32| turns.iter().any(|(member_id, skip)| *member_id == id && *skip);
+                                family_todo_core::next_in_rotation(&order, todo.assignee_id, is_away).or(todo.assignee_id)
+                            }
+                            None => todo.assignee_id,
+                        };
+
+                        let next = sqlx::query_as::<_, $crate::models::Todo>(
+                            r#"
+                            INSERT INTO todos (list_id, text, assignee_id, due_date, recurrence, points, rotation_id, needs_approval, auto_complete, created_at, updated_at, due_time)
+                            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10, $11)
+                            RETURNING id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                            "#
+                        )
+                        .bind(todo.list_id)
+                        .bind(&todo.text)
+                        .bind(assignee_id)
+                        .bind(next_due_date)
+                        .bind(&todo.recurrence)
+                        .bind(todo.points)
+                        .bind(todo.rotation_id)
+                        .bind(todo.needs_approval)
+                        .bind(todo.auto_complete)
+                        .bind(now)
+                        .bind(todo.due_time)
+                        .fetch_one(&mut *tx)
+                        .await?;
+
+                        // The next occurrence starts with the same checklist, nothing ticked
+                        sqlx::query(
+                            r#"
+                            INSERT INTO checklist_items (todo_id, text, completed, position, created_at, updated_at)
+                            SELECT $1, text, FALSE, position, $2, $2 FROM checklist_items WHERE todo_id = $3
+                            "#
+                        )
+                        .bind(next.id)
+                        .bind(now)
+                        .bind(todo.id)
+                        .execute(&mut *tx)
+                        .await?;
+
+                        sqlx::query("INSERT INTO todo_tags (todo_id, tag) SELECT $1, tag FROM todo_tags WHERE todo_id = $2")
+                            .bind(next.id)
+                            .bind(todo.id)
+                            .execute(&mut *tx)
+                            .await?;
+
+                        sqlx::query("UPDATE todos SET next_occurrence_id = $1 WHERE id = $2")
+                            .bind(next.id)
+                            .bind(todo.id)
+                            .execute(&mut *tx)
+                            .await?;
+                        todo.next_occurrence_id = Some(next.id);
+
+                        Some(next)
+                    }
+                    None => None,
+                };
+
+                // Credit the assignee once; un-ticking takes back whatever is outstanding
+                let outstanding = sqlx::query_as::<_, (i32, i64)>(
+                    r#"
+                    SELECT member_id, SUM(amount)
+                    FROM point_entries
+                    WHERE todo_id = $1
+                    GROUP BY member_id
+                    HAVING SUM(amount) <> 0
+                    "#
+                )
+                .bind(todo.id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+                let changes: Vec<(i32, i32, $crate::models::PointReason)> = match (todo.completed, todo.assignee_id) {
+                    (true, Some(assignee_id)) if todo.points > 0 && outstanding.is_empty() => {
+                        vec![(assignee_id, todo.points, $crate::models::PointReason::TodoCompleted)]
+                    }
+                    (false, _) => outstanding
+                        .into_iter()
+                        .map(|(member_id, amount)| (member_id, -(amount as i32), $crate::models::PointReason::TodoReopened))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                let mut points = Vec::new();
+                for (member_id, amount, reason) in changes {
+                    let entry = sqlx::query_as::<_, $crate::models::PointEntry>(
+                        r#"
+                        INSERT INTO point_entries (member_id, amount, reason, description, todo_id, created_at)
+                        VALUES ($1, $2, $3, $4, $5, $6)
+                        RETURNING id, member_id, amount, reason, description, todo_id, reward_id, created_at
+                        "#
+                    )
+                    .bind(member_id)
+                    .bind(amount)
+                    .bind(reason)
+                    .bind(&todo.text)
+                    .bind(todo.id)
+                    .bind(now)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    points.push(entry);
+                }
+
+                tx.commit().await?;
+
+                let todo = self.load_details(todo).await?;
+                let next_occurrence = match next_occurrence {
+                    Some(next) => Some(self.load_details(next).await?),
+                    None => None,
+                };
+
+                Ok(Some($crate::store::Toggled { todo, next_occurrence, points }))
+            }
+
+            async fn set_approval_request(
+                &self,
+                household_id: i32,
+                id: i32,
+                member_id: Option<i32>,
+                expected_version: Option<i32>,
+            ) -> anyhow::Result<Option<$crate::models::Todo>> {
+                let todo = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    UPDATE todos
+                    SET approval_requested_by = $1, updated_at = $2, version = version + 1
+                    WHERE id = $3 AND ($4 IS NULL OR version = $4)
+                      AND list_id IN (SELECT id FROM lists WHERE household_id = $5)
+                      AND deleted_at IS NULL
+                    RETURNING id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    "#
+                )
+                .bind(member_id)
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .bind(expected_version)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                match todo {
+                    Some(todo) => Ok(Some(self.load_details(todo).await?)),
+                    None => match self.get_todo_by_id(household_id, id).await? {
+                        Some(current) => Err($crate::store::StaleVersion { current }.into()),
+                        None => Ok(None),
+                    },
+                }
+            }
+
+            async fn add_checklist_item(
+                &self,
+                household_id: i32,
+                todo_id: i32,
+                request: $crate::models::CreateChecklistItemRequest,
+            ) -> anyhow::Result<Option<$crate::models::Todo>> {
+                if self.get_todo_by_id(household_id, todo_id).await?.is_none() {
+                    return Ok(None);
+                }
+
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO checklist_items (todo_id, text, position, created_at, updated_at)
+                    VALUES (
+                        $1, $2,
+                        COALESCE($3, (SELECT COALESCE(MAX(position) + 1, 0) FROM checklist_items WHERE todo_id = $1)),
+                        $4, $4
+                    )
+                    "#
+                )
+                .bind(todo_id)
+                .bind(&request.text)
+                .bind(request.position)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+
+                sqlx::query("UPDATE todos SET updated_at = $1, version = version + 1 WHERE id = $2")
+                    .bind(now)
+                    .bind(todo_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                self.get_todo_by_id(household_id, todo_id).await
+            }
+
+            async fn update_checklist_item(
+                &self,
+                household_id: i32,
+                todo_id: i32,
+                id: i32,
+                request: $crate::models::UpdateChecklistItemRequest,
+            ) -> anyhow::Result<Option<$crate::models::Todo>> {
+                let Some(todo) = self.get_todo_by_id(household_id, todo_id).await? else {
+                    return Ok(None);
+                };
+                let Some(current_item) = todo.checklist.into_iter().find(|item| item.id == id) else {
+                    return Ok(None);
+                };
+
+                let text = request.text.unwrap_or(current_item.text);
+                let completed = request.completed.unwrap_or(current_item.completed);
+                let position = request.position.unwrap_or(current_item.position);
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query(
+                    r#"
+                    UPDATE checklist_items
+                    SET text = $1, completed = $2, position = $3, updated_at = $4
+                    WHERE id = $5
+                    "#
+                )
+                .bind(&text)
+                .bind(completed)
+                .bind(position)
+                .bind(now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+                sqlx::query("UPDATE todos SET updated_at = $1, version = version + 1 WHERE id = $2")
+                    .bind(now)
+                    .bind(todo_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                self.get_todo_by_id(household_id, todo_id).await
+            }
+
+            async fn delete_checklist_item(
+                &self,
+                household_id: i32,
+                todo_id: i32,
+                id: i32,
+            ) -> anyhow::Result<Option<$crate::models::Todo>> {
+                let Some(todo) = self.get_todo_by_id(household_id, todo_id).await? else {
+                    return Ok(None);
+                };
+                if !todo.checklist.iter().any(|item| item.id == id) {
+                    return Ok(None);
+                }
+
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query("DELETE FROM checklist_items WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query("UPDATE todos SET updated_at = $1, version = version + 1 WHERE id = $2")
+                    .bind(chrono::Utc::now())
+                    .bind(todo_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                self.get_todo_by_id(household_id, todo_id).await
+            }
+
+            async fn add_comment(
+                &self,
+                todo_id: i32,
+                author_id: Option<i32>,
+                text: &str,
+            ) -> anyhow::Result<$crate::models::TodoComment> {
+                let comment = sqlx::query_as::<_, $crate::models::TodoComment>(
+                    r#"
+                    INSERT INTO todo_comments (todo_id, author_id, text, created_at)
+                    VALUES ($1, $2, $3, $4)
+                    RETURNING id, todo_id, author_id, text, created_at
+                    "#
+                )
+                .bind(todo_id)
+                .bind(author_id)
+                .bind(text)
+                .bind(chrono::Utc::now())
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(comment)
+            }
+
+            async fn get_comments(&self, household_id: i32, todo_id: i32) -> anyhow::Result<Vec<$crate::models::TodoComment>> {
+                let comments = sqlx::query_as::<_, $crate::models::TodoComment>(
+                    r#"
+                    SELECT id, todo_id, author_id, text, created_at
+                    FROM todo_comments
+                    WHERE todo_id = $1
+                      AND todo_id IN (
+                          SELECT id FROM todos
+                          WHERE list_id IN (SELECT id FROM lists WHERE household_id = $2) AND deleted_at IS NULL
+                      )
+                    ORDER BY created_at, id
+                    "#
+                )
+                .bind(todo_id)
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(comments)
+            }
+
+            async fn search_todos(
+                &self,
+                household_id: i32,
+                query: &str,
+                limit: i64,
+            ) -> anyhow::Result<Vec<$crate::models::SearchHit>> {
+                self.search(household_id, query, limit).await
+            }
+
+            async fn add_attachment(
+                &self,
+                todo_id: i32,
+                attachment: $crate::models::NewAttachment,
+            ) -> anyhow::Result<$crate::models::Attachment> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+
+                let attachment = sqlx::query_as::<_, $crate::models::Attachment>(
+                    r#"
+                    INSERT INTO attachments (todo_id, file_name, content_type, size, sha256, has_thumbnail, uploaded_by, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    RETURNING id, todo_id, file_name, content_type, size, sha256, has_thumbnail, uploaded_by, created_at
+                    "#
+                )
+                .bind(todo_id)
+                .bind(&attachment.file_name)
+                .bind(&attachment.content_type)
+                .bind(attachment.size)
+                .bind(&attachment.sha256)
+                .bind(attachment.has_thumbnail)
+                .bind(attachment.uploaded_by)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                sqlx::query("UPDATE todos SET updated_at = $1, version = version + 1 WHERE id = $2")
+                    .bind(now)
+                    .bind(todo_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                Ok(attachment)
+            }
+
+            async fn get_attachment(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Attachment>> {
+                let attachment = sqlx::query_as::<_, $crate::models::Attachment>(
+                    r#"
+                    SELECT id, todo_id, file_name, content_type, size, sha256, has_thumbnail, uploaded_by, created_at
+                    FROM attachments
+                    WHERE id = $1
+                      AND todo_id IN (
+                          SELECT id FROM todos
+                          WHERE list_id IN (SELECT id FROM lists WHERE household_id = $2) AND deleted_at IS NULL
+                      )
+                    "#
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(attachment)
+            }
+
+            async fn delete_attachment(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Attachment>> {
+                let Some(attachment) = self.get_attachment(household_id, id).await? else {
+                    return Ok(None);
+                };
+
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query("DELETE FROM attachments WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query("UPDATE todos SET updated_at = $1, version = version + 1 WHERE id = $2")
+                    .bind(chrono::Utc::now())
+                    .bind(attachment.todo_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+
+                Ok(Some(attachment))
+            }
+
+            async fn attachment_in_use(&self, sha256: &str) -> anyhow::Result<bool> {
+                let in_use = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM attachments WHERE sha256 = $1)")
+                    .bind(sha256)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+                Ok(in_use)
+            }
+
+            async fn delete_todo(&self, household_id: i32, id: i32) -> anyhow::Result<Option<i32>> {
+                let now = chrono::Utc::now();
+                let list_id = sqlx::query_scalar::<_, i32>(
+                    r#"
+                    UPDATE todos
+                    SET deleted_at = $3, updated_at = $3, version = version + 1
+                    WHERE id = $1 AND list_id IN (SELECT id FROM lists WHERE household_id = $2)
+                      AND deleted_at IS NULL
+                    RETURNING list_id
+                    "#
+                )
+                .bind(id)
+                .bind(household_id)
+                .bind(now)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(list_id)
+            }
+
+            async fn get_trash(&self, household_id: i32) -> anyhow::Result<Vec<$crate::models::Todo>> {
+                let todos = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    SELECT id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    FROM todos
+                    WHERE list_id IN (SELECT id FROM lists WHERE household_id = $1)
+                      AND deleted_at IS NOT NULL
+                    ORDER BY deleted_at DESC, id DESC
+                    "#
+                )
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                self.load_details_of(todos).await
+            }
+
+            async fn restore_todo(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Todo>> {
+                let todo = sqlx::query_as::<_, $crate::models::Todo>(
+                    r#"
+                    UPDATE todos
+                    SET deleted_at = NULL, updated_at = $3, version = version + 1
+                    WHERE id = $1 AND list_id IN (SELECT id FROM lists WHERE household_id = $2)
+                      AND deleted_at IS NOT NULL
+                    RETURNING id, list_id, text, assignee_id, due_date, due_time, completed, recurrence, next_occurrence_id, points, rotation_id, needs_approval, approval_requested_by, auto_complete, version, created_at, updated_at, deleted_at, completed_at
+                    "#
+                )
+                .bind(id)
+                .bind(household_id)
+                .bind(chrono::Utc::now())
+                .fetch_optional(&self.pool)
+                .await?;
+
+                match todo {
+                    Some(todo) => Ok(Some(self.load_details(todo).await?)),
+                    None => Ok(None),
+                }
+            }
+
+            async fn purge_todo(&self, household_id: i32, id: i32) -> anyhow::Result<Option<i32>> {
+                let list_id = sqlx::query_scalar::<_, i32>(
+                    r#"
+                    DELETE FROM todos
+                    WHERE id = $1 AND list_id IN (SELECT id FROM lists WHERE household_id = $2)
+                      AND deleted_at IS NOT NULL
+                    RETURNING list_id
+                    "#
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(list_id)
+            }
+
+            async fn purge_trash(&self, deleted_before: chrono::DateTime<chrono::Utc>) -> anyhow::Result<u64> {
+                let purged = sqlx::query("DELETE FROM todos WHERE deleted_at < $1")
+                    .bind(deleted_before)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(purged.rows_affected())
+            }
+
+            async fn create_member(
+                &self,
+                household_id: i32,
+                request: $crate::models::CreateMemberRequest,
+            ) -> anyhow::Result<$crate::models::Member> {
+                let color = request.color.unwrap_or_else(|| family_todo_core::DEFAULT_MEMBER_COLOR.to_string());
+                let initials = request.initials.unwrap_or_else(|| family_todo_core::initials_for(&request.display_name));
+                let now = chrono::Utc::now();
+
+                let member = sqlx::query_as::<_, $crate::models::Member>(
+                    r#"
+                    INSERT INTO members (household_id, display_name, color, initials, role, away, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+                    RETURNING id, household_id, display_name, color, initials, role, away, created_at, updated_at
+                    "#
+                )
+                .bind(household_id)
+                .bind(&request.display_name)
+                .bind(&color)
+                .bind(&initials)
+                .bind(request.role.unwrap_or_default())
+                .bind(request.away)
+                .bind(now)
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(member)
+            }
+
+            async fn get_members(&self, household_id: i32) -> anyhow::Result<Vec<$crate::models::Member>> {
+                let members = sqlx::query_as::<_, $crate::models::Member>(
+                    r#"
+                    SELECT id, household_id, display_name, color, initials, role, away, created_at, updated_at
+                    FROM members
+                    WHERE household_id = $1
+                    ORDER BY display_name
+                    "#
+                )
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(members)
+            }
+
+            async fn get_member_by_id(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Member>> {
+                let member = sqlx::query_as::<_, $crate::models::Member>(
+                    "SELECT id, household_id, display_name, color, initials, role, away, created_at, updated_at FROM members WHERE id = $1 AND household_id = $2"
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(member)
+            }
+
+            async fn update_member(
+                &self,
+                household_id: i32,
+                id: i32,
+                request: $crate::models::UpdateMemberRequest,
+            ) -> anyhow::Result<Option<$crate::models::Member>> {
+                let current_member = match self.get_member_by_id(household_id, id).await? {
+                    Some(member) => member,
+                    None => return Ok(None),
+                };
+
+                let display_name = request.display_name.unwrap_or(current_member.display_name);
+                let color = request.color.unwrap_or(current_member.color);
+                let initials = request.initials.unwrap_or(current_member.initials);
+                let role = request.role.unwrap_or(current_member.role);
+                let away = request.away.unwrap_or(current_member.away);
+
+                let member = sqlx::query_as::<_, $crate::models::Member>(
+                    r#"
+                    UPDATE members
+                    SET display_name = $1, color = $2, initials = $3, role = $4, away = $5, updated_at = $6
+                    WHERE id = $7
+                    RETURNING id, household_id, display_name, color, initials, role, away, created_at, updated_at
+                    "#
+                )
+                .bind(&display_name)
+                .bind(&color)
+                .bind(&initials)
+                .bind(role)
+                .bind(away)
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(Some(member))
+            }
+
+            async fn delete_member(&self, household_id: i32, id: i32) -> anyhow::Result<bool> {
+                let result = sqlx::query("DELETE FROM members WHERE id = $1 AND household_id = $2")
+                    .bind(id)
+                    .bind(household_id)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn create_list(
+                &self,
+                household_id: i32,
+                request: $crate::models::CreateListRequest,
+            ) -> anyhow::Result<$crate::models::TodoList> {
+                let now = chrono::Utc::now();
+
+                let list = sqlx::query_as::<_, $crate::models::TodoList>(
+                    r#"
+                    INSERT INTO lists (household_id, name, position, created_at, updated_at)
+                    VALUES (
+                        $1, $2,
+                        COALESCE($3, (SELECT COALESCE(MAX(position) + 1, 0) FROM lists WHERE household_id = $1)),
+                        $4, $4
+                    )
+                    RETURNING id, household_id, name, position, created_at, updated_at
+                    "#
+                )
+                .bind(household_id)
+                .bind(&request.name)
+                .bind(request.position)
+                .bind(now)
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(list)
+            }
+
+            async fn get_lists(&self, household_id: i32) -> anyhow::Result<Vec<$crate::models::TodoList>> {
+                let lists = sqlx::query_as::<_, $crate::models::TodoList>(
+                    "SELECT id, household_id, name, position, created_at, updated_at FROM lists WHERE household_id = $1 ORDER BY position, id"
+                )
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(lists)
+            }
+
+            async fn get_list_by_id(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::TodoList>> {
+                let list = sqlx::query_as::<_, $crate::models::TodoList>(
+                    "SELECT id, household_id, name, position, created_at, updated_at FROM lists WHERE id = $1 AND household_id = $2"
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(list)
+            }
+
+            async fn get_default_list(&self, household_id: i32) -> anyhow::Result<Option<$crate::models::TodoList>> {
+                let list = sqlx::query_as::<_, $crate::models::TodoList>(
+                    "SELECT id, household_id, name, position, created_at, updated_at FROM lists WHERE household_id = $1 ORDER BY position, id LIMIT 1"
+                )
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(list)
+            }
+
+            async fn update_list(
+                &self,
+                household_id: i32,
+                id: i32,
+                request: $crate::models::UpdateListRequest,
+            ) -> anyhow::Result<Option<$crate::models::TodoList>> {
+                let current_list = match self.get_list_by_id(household_id, id).await? {
+                    Some(list) => list,
+                    None => return Ok(None),
+                };
+
+                let name = request.name.unwrap_or(current_list.name);
+                let position = request.position.unwrap_or(current_list.position);
+
+                let list = sqlx::query_as::<_, $crate::models::TodoList>(
+                    r#"
+                    UPDATE lists
+                    SET name = $1, position = $2, updated_at = $3
+                    WHERE id = $4
+                    RETURNING id, household_id, name, position, created_at, updated_at
+                    "#
+                )
+                .bind(&name)
+                .bind(position)
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(Some(list))
+            }
+
+            async fn delete_list(&self, household_id: i32, id: i32) -> anyhow::Result<bool> {
+                let result = sqlx::query("DELETE FROM lists WHERE id = $1 AND household_id = $2")
+                    .bind(id)
+                    .bind(household_id)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn create_rotation(
+                &self,
+                household_id: i32,
+                request: $crate::models::CreateRotationRequest,
+            ) -> anyhow::Result<$crate::models::Rotation> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+
+                let id = sqlx::query_scalar::<_, i32>(
+                    r#"
+                    INSERT INTO rotations (household_id, skip_away, created_at, updated_at)
+                    VALUES ($1, $2, $3, $3)
+                    RETURNING id
+                    "#
+                )
+                .bind(household_id)
+                .bind(request.skip_away)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                for (position, member_id) in request.member_ids.iter().enumerate() {
+                    sqlx::query("INSERT INTO rotation_members (rotation_id, member_id, position) VALUES ($1, $2, $3)")
+                        .bind(id)
+                        .bind(member_id)
+                        .bind(position as i32)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                tx.commit().await?;
+
+                Ok($crate::models::Rotation {
+                    id,
+                    household_id,
+                    member_ids: request.member_ids,
+                    skip_away: request.skip_away,
+                    created_at: now,
+                    updated_at: now,
+                })
+            }
+
+            async fn get_rotations(&self, household_id: i32) -> anyhow::Result<Vec<$crate::models::Rotation>> {
+                let rows = sqlx::query_as::<_, (i32, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
+                    "SELECT id, skip_away, created_at, updated_at FROM rotations WHERE household_id = $1 ORDER BY id"
+                )
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                let turns = sqlx::query_as::<_, (i32, i32)>(
+                    r#"
+                    SELECT rotation_id, member_id
+                    FROM rotation_members
+                    WHERE rotation_id IN (SELECT id FROM rotations WHERE household_id = $1)
+                    ORDER BY rotation_id, position
+                    "#
+                )
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .map(|(id, skip_away, created_at, updated_at)| $crate::models::Rotation {
+                        id,
+                        household_id,
+                        member_ids: turns
+                            .iter()
+                            .filter(|(rotation_id, _)| *rotation_id == id)
+                            .map(|(_, member_id)| *member_id)
+                            .collect(),
+                        skip_away,
+                        created_at,
+                        updated_at,
+                    })
+                    .collect())
+            }
+
+            async fn get_rotation_by_id(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Rotation>> {
+                let row = sqlx::query_as::<_, (bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
+                    "SELECT skip_away, created_at, updated_at FROM rotations WHERE id = $1 AND household_id = $2"
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                let Some((skip_away, created_at, updated_at)) = row else {
+                    return Ok(None);
+                };
+
+                let member_ids = sqlx::query_scalar::<_, i32>(
+                    "SELECT member_id FROM rotation_members WHERE rotation_id = $1 ORDER BY position"
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(Some($crate::models::Rotation { id, household_id, member_ids, skip_away, created_at, updated_at }))
+            }
+
+            async fn update_rotation(
+                &self,
+                household_id: i32,
+                id: i32,
+                request: $crate::models::UpdateRotationRequest,
+            ) -> anyhow::Result<Option<$crate::models::Rotation>> {
+                let Some(current_rotation) = self.get_rotation_by_id(household_id, id).await? else {
+                    return Ok(None);
+                };
+
+                let skip_away = request.skip_away.unwrap_or(current_rotation.skip_away);
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query("UPDATE rotations SET skip_away = $1, updated_at = $2 WHERE id = $3")
+                    .bind(skip_away)
+                    .bind(chrono::Utc::now())
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                if let Some(member_ids) = &request.member_ids {
+                    sqlx::query("DELETE FROM rotation_members WHERE rotation_id = $1")
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+
+                    for (position, member_id) in member_ids.iter().enumerate() {
+                        sqlx::query("INSERT INTO rotation_members (rotation_id, member_id, position) VALUES ($1, $2, $3)")
+                            .bind(id)
+                            .bind(member_id)
+                            .bind(position as i32)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+
+                tx.commit().await?;
+
+                self.get_rotation_by_id(household_id, id).await
+            }
+
+            async fn delete_rotation(&self, household_id: i32, id: i32) -> anyhow::Result<bool> {
+                let result = sqlx::query("DELETE FROM rotations WHERE id = $1 AND household_id = $2")
+                    .bind(id)
+                    .bind(household_id)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn create_reward(
+                &self,
+                household_id: i32,
+                request: $crate::models::CreateRewardRequest,
+            ) -> anyhow::Result<$crate::models::Reward> {
+                let reward = sqlx::query_as::<_, $crate::models::Reward>(
+                    r#"
+                    INSERT INTO rewards (household_id, name, cost, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $4)
+                    RETURNING id, household_id, name, cost, created_at, updated_at
+                    "#
+                )
+                .bind(household_id)
+                .bind(&request.name)
+                .bind(request.cost)
+                .bind(chrono::Utc::now())
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(reward)
+            }
+
+            async fn get_rewards(&self, household_id: i32) -> anyhow::Result<Vec<$crate::models::Reward>> {
+                let rewards = sqlx::query_as::<_, $crate::models::Reward>(
+                    r#"
+                    SELECT id, household_id, name, cost, created_at, updated_at
+                    FROM rewards
+                    WHERE household_id = $1
+                    ORDER BY cost, name
+                    "#
+                )
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(rewards)
+            }
+
+            async fn get_reward_by_id(&self, household_id: i32, id: i32) -> anyhow::Result<Option<$crate::models::Reward>> {
+                let reward = sqlx::query_as::<_, $crate::models::Reward>(
+                    "SELECT id, household_id, name, cost, created_at, updated_at FROM rewards WHERE id = $1 AND household_id = $2"
+                )
+                .bind(id)
+                .bind(household_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(reward)
+            }
+
+            async fn update_reward(
+                &self,
+                household_id: i32,
+                id: i32,
+                request: $crate::models::UpdateRewardRequest,
+            ) -> anyhow::Result<Option<$crate::models::Reward>> {
+                let current_reward = match self.get_reward_by_id(household_id, id).await? {
+                    Some(reward) => reward,
+                    None => return Ok(None),
+                };
+
+                let name = request.name.unwrap_or(current_reward.name);
+                let cost = request.cost.unwrap_or(current_reward.cost);
+
+                let reward = sqlx::query_as::<_, $crate::models::Reward>(
+                    r#"
+                    UPDATE rewards
+                    SET name = $1, cost = $2, updated_at = $3
+                    WHERE id = $4
+                    RETURNING id, household_id, name, cost, created_at, updated_at
+                    "#
+                )
+                .bind(&name)
+                .bind(cost)
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(Some(reward))
+            }
+
+            async fn delete_reward(&self, household_id: i32, id: i32) -> anyhow::Result<bool> {
+                let result = sqlx::query("DELETE FROM rewards WHERE id = $1 AND household_id = $2")
+                    .bind(id)
+                    .bind(household_id)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn redeem_reward(
+                &self,
+                household_id: i32,
+                reward_id: i32,
+                member_id: i32,
+            ) -> anyhow::Result<Option<$crate::models::PointEntry>> {
+                let Some(reward) = self.get_reward_by_id(household_id, reward_id).await? else {
+                    return Ok(None);
+                };
+
+                // Redemptions for the same member queue up on the lock, so
+                // each one sees the balance the one before it left behind
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(Self::LOCK_MEMBER).bind(member_id).execute(&mut *tx).await?;
+
+                let balance = sqlx::query_scalar::<_, i64>(
+                    "SELECT COALESCE(SUM(amount), 0) FROM point_entries WHERE member_id = $1"
+                )
+                .bind(member_id)
+                .fetch_one(&mut *tx)
+                .await?;
+                if balance < i64::from(reward.cost) {
+                    return Err($crate::store::InsufficientPoints { balance }.into());
+                }
+
+                let entry = sqlx::query_as::<_, $crate::models::PointEntry>(
+                    r#"
+                    INSERT INTO point_entries (member_id, amount, reason, description, reward_id, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    RETURNING id, member_id, amount, reason, description, todo_id, reward_id, created_at
+                    "#
+                )
+                .bind(member_id)
+                .bind(-reward.cost)
+                .bind($crate::models::PointReason::RewardRedeemed)
+                .bind(&reward.name)
+                .bind(reward.id)
+                .bind(chrono::Utc::now())
+                .fetch_one(&mut *tx)
+                .await?;
+                tx.commit().await?;
+
+                Ok(Some(entry))
+            }
+
+            async fn get_point_balance(&self, household_id: i32, member_id: i32) -> anyhow::Result<i64> {
+                let balance = sqlx::query_scalar::<_, i64>(
+                    r#"
+                    SELECT COALESCE(SUM(amount), 0)
+                    FROM point_entries
+                    WHERE member_id = $1 AND member_id IN (SELECT id FROM members WHERE household_id = $2)
+                    "#
+                )
+                .bind(member_id)
+                .bind(household_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(balance)
+            }
+
+            async fn get_point_entries(
+                &self,
+                household_id: i32,
+                member_id: i32,
+            ) -> anyhow::Result<Vec<$crate::models::PointEntry>> {
+                let entries = sqlx::query_as::<_, $crate::models::PointEntry>(
+                    r#"
+                    SELECT id, member_id, amount, reason, description, todo_id, reward_id, created_at
+                    FROM point_entries
+                    WHERE member_id = $1 AND member_id IN (SELECT id FROM members WHERE household_id = $2)
+                    ORDER BY id DESC
+                    "#
+                )
+                .bind(member_id)
+                .bind(household_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(entries)
+            }
+
+            async fn add_history(
+                &self,
+                household_id: i32,
+                entry: $crate::models::NewHistoryEntry,
+            ) -> anyhow::Result<$crate::models::HistoryEntry> {
+                let entry = sqlx::query_as::<_, $crate::models::HistoryEntry>(
+                    r#"
+                    INSERT INTO todo_history (household_id, todo_id, todo_text, user_id, member_id, action, changes, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    RETURNING id, household_id, todo_id, todo_text, user_id, member_id, action, changes, created_at
+                    "#
+                )
+                .bind(household_id)
+                .bind(entry.todo_id)
+                .bind(&entry.todo_text)
+                .bind(entry.user_id)
+                .bind(entry.member_id)
+                .bind(entry.action)
+                .bind(sqlx::types::Json(&entry.changes))
+                .bind(chrono::Utc::now())
+                .fetch_one(&self.pool)
+                .await?;
+
+                Ok(entry)
+            }
+
+            async fn get_todo_history(
+                &self,
+                household_id: i32,
+                todo_id: i32,
+            ) -> anyhow::Result<Vec<$crate::models::HistoryEntry>> {
+                let entries = sqlx::query_as::<_, $crate::models::HistoryEntry>(
+                    r#"
+                    SELECT id, household_id, todo_id, todo_text, user_id, member_id, action, changes, created_at
+                    FROM todo_history
+                    WHERE household_id = $1 AND todo_id = $2
+                    ORDER BY id DESC
+                    "#
+                )
+                .bind(household_id)
+                .bind(todo_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(entries)
+            }
+
+            async fn get_activity(
+                &self,
+                household_id: i32,
+                before: Option<i64>,
+                limit: i64,
+            ) -> anyhow::Result<Vec<$crate::models::HistoryEntry>> {
+                let entries = sqlx::query_as::<_, $crate::models::HistoryEntry>(
+                    r#"
+                    SELECT id, household_id, todo_id, todo_text, user_id, member_id, action, changes, created_at
+                    FROM todo_history
+                    WHERE household_id = $1 AND ($2 IS NULL OR id < $2)
+                    ORDER BY id DESC
+                    LIMIT $3
+                    "#
+                )
+                .bind(household_id)
+                .bind(before)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(entries)
+            }
+
+            async fn append_event(
+                &self,
+                household_id: i32,
+                event_type: &str,
+                list_id: Option<i32>,
+                data: serde_json::Value,
+            ) -> anyhow::Result<$crate::models::Event> {
+                let event = sqlx::query_as::<_, $crate::models::Event>(
+                    r#"
+                    INSERT INTO events (household_id, event_type, list_id, data, created_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING seq, household_id, event_type, list_id, data, created_at
+                    "#
+                )
+                .bind(household_id)
+                .bind(event_type)
+                .bind(list_id)
+                .bind(sqlx::types::Json(data))
+                .bind(chrono::Utc::now())
+                .fetch_one(&self.pool)
+                .await?;
+
+                // Keep the household's newest events; a busy household must
+                // not push a quiet one's history out
+                sqlx::query(
+                    r#"
+                    DELETE FROM events
+                    WHERE household_id = $1 AND seq <= (
+                        SELECT seq FROM events WHERE household_id = $1 ORDER BY seq DESC LIMIT 1 OFFSET $2
+                    )
+                    "#
+                )
+                .bind(household_id)
+                .bind($crate::store::EVENT_RETENTION)
+                .execute(&self.pool)
+                .await?;
+
+                Ok(event)
+            }
+
+            async fn get_events_since(
+                &self,
+                household_id: i32,
+                since: i64,
+                limit: i64,
+            ) -> anyhow::Result<Vec<$crate::models::Event>> {
+                let events = sqlx::query_as::<_, $crate::models::Event>(
+                    r#"
+                    SELECT seq, household_id, event_type, list_id, data, created_at
+                    FROM events
+                    WHERE household_id = $1 AND seq > $2
+                    ORDER BY seq
+                    LIMIT $3
+                    "#
+                )
+                .bind(household_id)
+                .bind(since)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok(events)
+            }
+
+            async fn latest_event_seq(&self, household_id: i32) -> anyhow::Result<i64> {
+                let seq = sqlx::query_scalar::<_, i64>("SELECT COALESCE(MAX(seq), 0) FROM events WHERE household_id = $1")
+                    .bind(household_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+                Ok(seq)
+            }
+
+            async fn register_user(
+                &self,
+                email: &str,
+                password_hash: &str,
+                household_name: Option<String>,
+            ) -> anyhow::Result<($crate::models::User, $crate::models::Household)> {
+                let now = chrono::Utc::now();
+                let mut tx = self.pool.begin().await?;
+                // Two first registrations at once would both find the migrated
+                // household unclaimed, so the second waits for the first here
+                sqlx::query(Self::LOCK_FIRST_HOUSEHOLD).execute(&mut *tx).await?;
+
+                // Before the first account there is only the migrated household
+                let unclaimed = sqlx::query_as::<_, $crate::models::Household>(
+                    r#"
+                    SELECT id, name, timezone, created_at, updated_at
+                    FROM households
+                    WHERE NOT EXISTS (SELECT 1 FROM users)
+                    ORDER BY id
+                    LIMIT 1
+                    "#
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                let household = match (unclaimed, household_name) {
+                    (Some(household), None) => household,
+                    (Some(household), Some(name)) => {
+                        sqlx::query_as::<_, $crate::models::Household>(
+                            "UPDATE households SET name = $1, updated_at = $2 WHERE id = $3 RETURNING id, name, timezone, created_at, updated_at"
+                        )
+                        .bind(&name)
+                        .bind(now)
+                        .bind(household.id)
+                        .fetch_one(&mut *tx)
+                        .await?
+                    }
+                    (None, name) => {
+                        let household = sqlx::query_as::<_, $crate::models::Household>(
+                            "INSERT INTO households (name, created_at, updated_at) VALUES ($1, $2, $2) RETURNING id, name, timezone, created_at, updated_at"
+                        )
+                        .bind(name.as_deref().unwrap_or($crate::store::DEFAULT_HOUSEHOLD_NAME))
+                        .bind(now)
+                        .fetch_one(&mut *tx)
+                        .await?;
+
+                        sqlx::query("INSERT INTO lists (household_id, name, position, created_at, updated_at) VALUES ($1, $2, 0, $3, $3)")
+                            .bind(household.id)
+                            .bind($crate::store::DEFAULT_LIST_NAME)
+                            .bind(now)
+                            .execute(&mut *tx)
+                            .await?;
+
+                        household
+                    }
+                };
+
+                let user = sqlx::query_as::<_, $crate::models::User>(
+                    r#"
+                    INSERT INTO users (household_id, email, password_hash, created_at)
+                    VALUES ($1, $2, $3, $4)
+                    RETURNING id, household_id, member_id, email, password_hash, 'parent' AS role, created_at
+                    "#
+                )
+                .bind(household.id)
+                .bind(email)
+                .bind(password_hash)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                tx.commit().await?;
+
+                Ok((user, household))
+            }
+
+            async fn create_user(
+                &self,
+                household_id: i32,
+                email: &str,
+                password_hash: &str,
+                member_id: Option<i32>,
+            ) -> anyhow::Result<$crate::models::User> {
+                sqlx::query(
+                    r#"
+                    INSERT INTO users (household_id, member_id, email, password_hash, created_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    "#
+                )
+                .bind(household_id)
+                .bind(member_id)
+                .bind(email)
+                .bind(password_hash)
+                .bind(chrono::Utc::now())
+                .execute(&self.pool)
+                .await?;
+
+                // Read it back to pick up the member's role
+                let user = self.get_user_by_email(email).await?.expect("user was just inserted");
+
+                Ok(user)
+            }
+
+            async fn get_user_by_email(&self, email: &str) -> anyhow::Result<Option<$crate::models::User>> {
+                let user = sqlx::query_as::<_, $crate::models::User>(
+                    r#"
+                    SELECT u.id, u.household_id, u.member_id, u.email, u.password_hash,
+                           COALESCE(m.role, 'parent') AS role, u.created_at
+                    FROM users u
+                    LEFT JOIN members m ON m.id = u.member_id
+                    WHERE u.email = $1
+                    "#
+                )
+                .bind(email)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(user)
+            }
+
+            async fn get_household(&self, id: i32) -> anyhow::Result<Option<$crate::models::Household>> {
+                let household = sqlx::query_as::<_, $crate::models::Household>(
+                    "SELECT id, name, timezone, created_at, updated_at FROM households WHERE id = $1"
+                )
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(household)
+            }
+
+            async fn update_household(
+                &self,
+                id: i32,
+                request: $crate::models::UpdateHouseholdRequest,
+            ) -> anyhow::Result<Option<$crate::models::Household>> {
+                let household = sqlx::query_as::<_, $crate::models::Household>(
+                    r#"
+                    UPDATE households
+                    SET name = COALESCE($1, name), timezone = COALESCE($2, timezone), updated_at = $3
+                    WHERE id = $4
+                    RETURNING id, name, timezone, created_at, updated_at
+                    "#
+                )
+                .bind(request.name)
+                .bind(request.timezone)
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(household)
+            }
+
+            async fn create_session(
+                &self,
+                token_hash: &str,
+                user_id: i32,
+                expires_at: chrono::DateTime<chrono::Utc>,
+            ) -> anyhow::Result<()> {
+                let now = chrono::Utc::now();
+
+                sqlx::query("DELETE FROM sessions WHERE expires_at <= $1")
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await?;
+
+                sqlx::query("INSERT INTO sessions (token_hash, user_id, created_at, expires_at) VALUES ($1, $2, $3, $4)")
+                    .bind(token_hash)
+                    .bind(user_id)
+                    .bind(now)
+                    .bind(expires_at)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(())
+            }
+
+            async fn get_session_user(&self, token_hash: &str) -> anyhow::Result<Option<$crate::models::User>> {
+                let user = sqlx::query_as::<_, $crate::models::User>(
+                    r#"
+                    SELECT u.id, u.household_id, u.member_id, u.email, u.password_hash,
+                           COALESCE(m.role, 'parent') AS role, u.created_at
+                    FROM sessions s
+                    JOIN users u ON u.id = s.user_id
+                    LEFT JOIN members m ON m.id = u.member_id
+                    WHERE s.token_hash = $1 AND s.expires_at > $2
+                    "#
+                )
+                .bind(token_hash)
+                .bind(chrono::Utc::now())
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(user)
+            }
+
+            async fn delete_session(&self, token_hash: &str) -> anyhow::Result<()> {
+                sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+                    .bind(token_hash)
+                    .execute(&self.pool)
+                    .await?;
+
+                Ok(())
+            }
+        }
+
+        impl $store {
+            /// Reads the todo's checklist items, attachments and tags and adds them to it.
+            async fn load_details(&self, todo: $crate::models::Todo) -> anyhow::Result<$crate::models::Todo> {
+                let mut todos = self.load_details_of(vec![todo]).await?;
+                Ok(todos.remove(0))
+            }
+
+            /// Like `load_details` for many todos at once: one query per kind
+            /// of detail (per `DETAIL_BATCH` todos), reading only their rows.
+            async fn load_details_of(&self, todos: Vec<$crate::models::Todo>) -> anyhow::Result<Vec<$crate::models::Todo>> {
+                let mut items = Vec::new();
+                let mut attachments = Vec::new();
+                let mut tags = Vec::new();
+                for batch in todos.chunks($crate::store::sql::DETAIL_BATCH) {
+                    // `= ANY($1)` would be Postgres only, so the ids get a placeholder each
+                    let ids = $crate::store::sql::placeholders(batch.len());
+
+                    let sql = format!(
+                        r#"
+                        SELECT id, todo_id, text, completed, position, created_at, updated_at
+                        FROM checklist_items
+                        WHERE todo_id IN ({})
+                        ORDER BY position, id
+                        "#,
+                        ids
+                    );
+                    let mut query = sqlx::query_as::<_, $crate::models::ChecklistItem>(&sql);
+                    for todo in batch {
+                        query = query.bind(todo.id);
+                    }
+                    items.extend(query.fetch_all(&self.pool).await?);
+
+                    let sql = format!(
+                        r#"
+                        SELECT id, todo_id, file_name, content_type, size, sha256, has_thumbnail, uploaded_by, created_at
+                        FROM attachments
+                        WHERE todo_id IN ({})
+                        ORDER BY created_at, id
+                        "#,
+                        ids
+                    );
+                    let mut query = sqlx::query_as::<_, $crate::models::Attachment>(&sql);
+                    for todo in batch {
+                        query = query.bind(todo.id);
+                    }
+                    attachments.extend(query.fetch_all(&self.pool).await?);
+
+                    let sql = format!("SELECT todo_id, tag FROM todo_tags WHERE todo_id IN ({}) ORDER BY tag", ids);
+                    let mut query = sqlx::query_as::<_, (i32, String)>(&sql);
+                    for todo in batch {
+                        query = query.bind(todo.id);
+                    }
+                    tags.extend(query.fetch_all(&self.pool).await?);
+                }
+
+                Ok(todos
+                    .into_iter()
+                    .map(|todo| {
+                        let checklist = items.iter().filter(|item| item.todo_id == todo.id).cloned().collect();
+                        let attachments = attachments.iter().filter(|attachment| attachment.todo_id == todo.id).cloned().collect();
+                        let tags = tags.iter().filter(|(todo_id, _)| *todo_id == todo.id).map(|(_, tag)| tag.clone()).collect();
+                        $crate::models::Todo { attachments, tags, ..todo.with_checklist(checklist) }
+                    })
+                    .collect())
+            }
+
+            /// Replaces a todo's tags, which the caller has normalized, as
+            /// part of the transaction that writes the todo.
+            async fn set_tags(tx: &mut sqlx::Transaction<'_, $db>, todo_id: i32, tags: &[String]) -> anyhow::Result<()> {
+                sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1")
+                    .bind(todo_id)
+                    .execute(&mut **tx)
+                    .await?;
+                for tag in tags {
+                    sqlx::query("INSERT INTO todo_tags (todo_id, tag) VALUES ($1, $2)")
+                        .bind(todo_id)
+                        .bind(tag)
+                        .execute(&mut **tx)
+                        .await?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+pub(crate) use impl_todo_store;
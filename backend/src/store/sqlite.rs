@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+
+use super::sql::impl_todo_store;
+use super::{rank_matches, TodoStore};
+use crate::models::SearchHit;
+
+/// A single-file store for small deployments such as a Raspberry Pi.
+///
+/// `sqlite::memory:` gives a throwaway database, which the pool then keeps on
+/// one connection that is never recycled.
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// SQLite has no `FOR UPDATE`, but any write takes the database's write
+    /// lock until the transaction ends, which serves the same purpose.
+    const LOCK_MEMBER: &'static str = "UPDATE members SET id = id WHERE id = $1";
+
+    /// Takes the write lock the same way, on the household the first account claims.
+    const LOCK_FIRST_HOUSEHOLD: &'static str =
+        "UPDATE households SET id = id WHERE id = (SELECT MIN(id) FROM households)";
+
+    pub async fn new(database_url: &str) -> Result<Self> {
+        let in_memory = database_url.contains(":memory:");
+
+        let mut options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        if !in_memory {
+            options = options.journal_mode(SqliteJournalMode::Wal);
+        }
+
+        let pool_options = if in_memory {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new()
+        };
+        let pool = pool_options.connect_with(options).await?;
+
+        Ok(Self { pool })
+    }
+
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!("./migrations_sqlite").run(&self.pool).await?;
+        Ok(())
+    }
+
+    /// SQLite has no stemming full-text search built in, so this uses the
+    /// same fuzzy matching as the offline app.
+    async fn search(&self, household_id: i32, query: &str, limit: i64) -> Result<Vec<SearchHit>> {
+        let todos = self.get_todos(household_id, None).await?;
+        let comments = sqlx::query_as::<_, (i32, String)>(
+            r#"
+            SELECT todo_id, text
+            FROM todo_comments
+            WHERE todo_id IN (
+                SELECT id FROM todos
+                WHERE list_id IN (SELECT id FROM lists WHERE household_id = $1) AND deleted_at IS NULL
+            )
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(household_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let documents = todos
+            .into_iter()
+            .map(|todo| {
+                let texts = comments
+                    .iter()
+                    .filter(|(todo_id, _)| *todo_id == todo.id)
+                    .map(|(_, text)| text.clone())
+                    .collect();
+                (todo, texts)
+            })
+            .collect();
+        Ok(rank_matches(query, documents, limit))
+    }
+}
+
+impl_todo_store!(SqliteStore, sqlx::Sqlite);
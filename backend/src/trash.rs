@@ -0,0 +1,57 @@
+//! Deleted todos wait in the trash for `TRASH_RETENTION_DAYS` before a
+//! background job purges them for good.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::attachments::AttachmentStorage;
+use crate::store::TodoStore;
+
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// How often the purge job looks for expired trash.
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Reads `TRASH_RETENTION_DAYS`, falling back to the default.
+pub fn retention_from_env() -> chrono::Duration {
+    let days = std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .filter(|&days: &i64| days >= 0)
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+
+    chrono::Duration::days(days)
+}
+
+/// Purges todos trashed longer than `retention` ago, then the attachment
+/// files nothing uses any more, returning how many todos were purged.
+pub async fn purge_expired(db: &dyn TodoStore, storage: &AttachmentStorage, retention: chrono::Duration) -> Result<u64> {
+    let purged = db.purge_trash(Utc::now() - retention).await?;
+    storage.remove_orphans(db).await?;
+
+    Ok(purged)
+}
+
+/// Runs [`purge_expired`] now and then every [`PURGE_INTERVAL`]. Failures
+/// are logged and retried on the next round.
+pub fn spawn_purge_job(
+    db: Arc<dyn TodoStore>,
+    storage: Arc<AttachmentStorage>,
+    retention: chrono::Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_expired(db.as_ref(), &storage, retention).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {} todos from the trash", purged),
+                Err(e) => tracing::error!("Failed to purge the trash: {:#}", e),
+            }
+        }
+    })
+}
@@ -1,85 +1,364 @@
 use axum::{
-    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Extension},
-    response::Response,
+    extract::{ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade}, Extension, Query},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
 };
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use futures::{sink::SinkExt, stream::StreamExt};
+use std::time::Duration;
+use tokio::sync::{broadcast::{self, error::RecvError}, Mutex};
+use futures::{sink::SinkExt, stream::{SplitSink, StreamExt}};
 use serde_json::json;
+use uuid::Uuid;
 
-use crate::database::Database;
-use crate::models::WebSocketMessage;
+use crate::auth::{hash_token, session_token};
+use crate::error::{accept_request_id, with_request_id, AppError};
+use crate::handlers::{apply_delete, apply_todo_update, apply_toggle, completed_cutoff, create_todo_in_list, require_list};
+use crate::store::{TodoStore, EVENT_RETENTION};
+use crate::models::{ClientCommand, ClientMessage, ServerReply, TodoFilter, User, WebSocketMessage};
+
+/// A reconnecting client further behind than this gets a `snapshot` instead
+/// of having every missed event replayed.
+const MAX_REPLAY: i64 = 200;
+
+// A household's events after `since` can only have been pruned if there are
+// more than EVENT_RETENTION of them, and then more than MAX_REPLAY are left
+const _: () = assert!(MAX_REPLAY < EVENT_RETENTION);
+
+/// How often a connection checks that its session is still valid, so a
+/// socket stops receiving events soon after its user signs out.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 // Global broadcaster for WebSocket messages
 lazy_static::lazy_static! {
-    static ref BROADCASTER: broadcast::Sender<WebSocketMessage> = {
+    static ref BROADCASTER: broadcast::Sender<Broadcast> = {
         let (tx, _) = broadcast::channel(100);
         tx
     };
+
+    /// One per household, held while its event is stored and sent so the
+    /// household's events reach the channel in sequence order.
+    static ref PUBLISH_LOCKS: std::sync::Mutex<HashMap<i32, Arc<Mutex<()>>>> = Default::default();
+}
+
+/// An event on its way to the connections of one household.
+#[derive(Debug, Clone)]
+struct Broadcast {
+    household_id: i32,
+    message: WebSocketMessage,
+}
+
+/// `/ws?list_id=3` only receives events for that list (plus household-wide
+/// events such as member changes); without it a client receives everything.
+///
+/// `since` is the `seq` of the last event the client saw. Events after it are
+/// replayed before live ones, or a `snapshot` is sent if too much was missed.
+#[derive(Debug, Deserialize)]
+pub struct WebSocketParams {
+    pub list_id: Option<i32>,
+    pub since: Option<i64>,
 }
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
-    Extension(db): Extension<Arc<Database>>,
+    Extension(db): Extension<Arc<dyn TodoStore>>,
+    Extension(user): Extension<User>,
+    Query(params): Query<WebSocketParams>,
+    headers: HeaderMap,
 ) -> Response {
-    ws.on_upgrade(|socket| websocket_connection(socket, db))
+    // `require_session` has already checked the cookie is there
+    let Some(token) = session_token(&headers) else {
+        return AppError::Unauthorized("Not signed in").into_response();
+    };
+    let token_hash = hash_token(&token);
+    ws.on_upgrade(move |socket| websocket_connection(socket, db, user, token_hash, params))
 }
 
-async fn websocket_connection(socket: WebSocket, _db: Arc<Database>) {
-    let (mut sender, mut receiver) = socket.split();
+async fn websocket_connection(
+    socket: WebSocket,
+    db: Arc<dyn TodoStore>,
+    user: User,
+    token_hash: String,
+    params: WebSocketParams,
+) {
+    let household_id = user.household_id;
+    let (sender, mut receiver) = socket.split();
+
+    // Subscribe before reading the event log so no event falls in between
     let mut rx = BROADCASTER.subscribe();
-    
+    let latest_seq = match db.latest_event_seq(household_id).await {
+        Ok(seq) => seq,
+        Err(e) => {
+            tracing::error!("Failed to read event log: {:#}", e);
+            return;
+        }
+    };
+
+    let mut connection = Connection {
+        sender,
+        db,
+        user,
+        token_hash,
+        list_filter: params.list_id,
+        last_seq: latest_seq,
+    };
+
     // Send initial data
     let initial_message = WebSocketMessage {
         message_type: "connected".to_string(),
-        data: json!({"status": "connected"}),
+        seq: None,
+        list_id: params.list_id,
+        data: json!({"status": "connected", "seq": latest_seq}),
     };
-    
-    if let Ok(msg) = serde_json::to_string(&initial_message) {
-        let _ = sender.send(Message::Text(msg)).await;
+    if connection.send(&initial_message).await.is_err() {
+        return;
     }
-    
-    // Handle incoming messages
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if let Ok(json_msg) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(json_msg)).await.is_err() {
-                    break;
-                }
-            }
+
+    if let Some(since) = params.since {
+        if connection.catch_up(since).await.is_err() {
+            return;
         }
-    });
-    
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            match msg {
-                Message::Text(_) => {
-                    // Handle incoming text messages if needed
-                }
-                Message::Close(_) => {
-                    break;
+    }
+
+    let mut session_check = tokio::time::interval(SESSION_CHECK_INTERVAL);
+    session_check.tick().await;
+
+    loop {
+        let result = tokio::select! {
+            event = rx.recv() => match event {
+                Ok(broadcast) if broadcast.household_id == household_id => connection.forward(broadcast.message).await,
+                Ok(_) => Ok(()),
+                // Fell behind the channel: fetch what was dropped from the log
+                Err(RecvError::Lagged(_)) => connection.catch_up(connection.last_seq).await,
+                Err(RecvError::Closed) => break,
+            },
+            incoming = receiver.next() => match incoming {
+                Some(Ok(Message::Text(text))) => connection.handle_command(&text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => Ok(()),
+            },
+            _ = session_check.tick() => match connection.refresh_user().await {
+                Ok(()) => Ok(()),
+                Err(AppError::Unauthorized(_)) => connection.close_signed_out().await,
+                Err(e) => {
+                    tracing::error!("Failed to check session: {:#}", e);
+                    Ok(())
                 }
-                _ => {}
+            },
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+}
+
+/// One client connection: the events it follows and the commands it sends.
+struct Connection {
+    sender: SplitSink<WebSocket, Message>,
+    db: Arc<dyn TodoStore>,
+    /// Who is signed in; commands run with their household and role.
+    user: User,
+    /// The session the socket was opened with. It is looked up again before
+    /// every command, so signing out or a change of role takes effect at once.
+    token_hash: String,
+    list_filter: Option<i32>,
+    /// Newest event this client has been sent, or skipped as not visible.
+    last_seq: i64,
+}
+
+impl Connection {
+    async fn send(&mut self, message: &impl Serialize) -> anyhow::Result<()> {
+        let json_msg = serde_json::to_string(message)?;
+        self.sender.send(Message::Text(json_msg)).await?;
+        Ok(())
+    }
+
+    /// Reloads the signed-in user from the session. Fails once the session
+    /// has ended: signed out, expired or its account deleted.
+    async fn refresh_user(&mut self) -> Result<(), AppError> {
+        self.user = self
+            .db
+            .get_session_user(&self.token_hash)
+            .await?
+            .ok_or(AppError::Unauthorized("Session expired, please sign in again"))?;
+        Ok(())
+    }
+
+    /// Closes the connection, telling the client its session has ended.
+    async fn close_signed_out(&mut self) -> anyhow::Result<()> {
+        let frame = CloseFrame { code: close_code::POLICY, reason: "Session ended".into() };
+        self.sender.send(Message::Close(Some(frame))).await?;
+        anyhow::bail!("session ended")
+    }
+
+    /// Sends a live or replayed event unless the client already has it.
+    async fn forward(&mut self, message: WebSocketMessage) -> anyhow::Result<()> {
+        if let Some(seq) = message.seq {
+            if seq <= self.last_seq {
+                return Ok(());
             }
+            self.last_seq = seq;
         }
-    });
-    
-    // Wait for either task to finish
-    tokio::select! {
-        _ = (&mut send_task) => {
-            recv_task.abort();
+
+        if is_visible_to(&message, self.list_filter) {
+            self.send(&message).await?;
         }
-        _ = (&mut recv_task) => {
-            send_task.abort();
+        Ok(())
+    }
+
+    /// Replays the household's events after `since`, or sends a snapshot
+    /// when there are too many (which covers any having been pruned), or
+    /// `since` is from a different event log altogether.
+    async fn catch_up(&mut self, since: i64) -> anyhow::Result<()> {
+        let latest_seq = self.db.latest_event_seq(self.user.household_id).await?;
+        let events = self.db.get_events_since(self.user.household_id, since, MAX_REPLAY + 1).await?;
+
+        let too_far_behind = since > latest_seq || events.len() as i64 > MAX_REPLAY;
+        if too_far_behind {
+            return self.send_snapshot(latest_seq).await;
+        }
+
+        self.last_seq = since;
+        for event in events {
+            self.forward(event.into()).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the lists, members and (visible) todos as of event `seq`,
+    /// leaving out long-completed todos as the REST listing does.
+    async fn send_snapshot(&mut self, seq: i64) -> anyhow::Result<()> {
+        let lists = self.db.get_lists(self.user.household_id).await?;
+        let members = self.db.get_members(self.user.household_id).await?;
+        let filter = TodoFilter { list_id: self.list_filter, completed_since: completed_cutoff(), ..Default::default() };
+        let todos = self.db.find_todos(self.user.household_id, &filter).await?;
+
+        self.last_seq = seq;
+        let snapshot = WebSocketMessage {
+            message_type: "snapshot".to_string(),
+            seq: Some(seq),
+            list_id: self.list_filter,
+            data: json!({"lists": lists, "members": members, "todos": todos}),
+        };
+        self.send(&snapshot).await
+    }
+
+    /// Runs a [`ClientMessage`] and replies to it. Only a broken connection
+    /// is an error here; a failed command gets an `error` reply.
+    async fn handle_command(&mut self, text: &str) -> anyhow::Result<()> {
+        // Read the request id on its own so even a malformed command can be answered
+        let raw: serde_json::Value = serde_json::from_str(text).unwrap_or_default();
+        let request_id = raw
+            .get("request_id")
+            .and_then(|id| id.as_str())
+            .and_then(accept_request_id);
+        let scope_id = request_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let (reply, resubscribed, signed_out) = with_request_id(scope_id, async {
+            if let Err(error) = self.refresh_user().await {
+                let signed_out = matches!(error, AppError::Unauthorized(_));
+                return (ServerReply::Error { request_id: request_id.clone(), error: error.to_json() }, false, signed_out);
+            }
+
+            let command = match serde_json::from_value::<ClientMessage>(raw) {
+                Ok(message) => message.command,
+                Err(e) => {
+                    let error = AppError::BadRequest(format!("Invalid command: {}", e));
+                    return (ServerReply::Error { request_id: request_id.clone(), error: error.to_json() }, false, false);
+                }
+            };
+
+            let resubscribed = matches!(command, ClientCommand::Subscribe { .. });
+            let reply = match self.run(command).await {
+                Ok(None) => ServerReply::Pong { request_id: request_id.clone() },
+                Ok(Some(data)) => ServerReply::Ack { request_id: request_id.clone(), data },
+                Err(error) => ServerReply::Error { request_id: request_id.clone(), error: error.to_json() },
+            };
+            let succeeded = matches!(reply, ServerReply::Ack { .. });
+            (reply, resubscribed && succeeded, false)
+        })
+        .await;
+
+        self.send(&reply).await?;
+        if signed_out {
+            return self.close_signed_out().await;
+        }
+
+        // A new subscription starts from the current state of its list
+        if resubscribed {
+            let latest_seq = self.db.latest_event_seq(self.user.household_id).await?;
+            self.send_snapshot(latest_seq).await?;
         }
+        Ok(())
+    }
+
+    /// Carries out a command with the same validation and broadcasts as the
+    /// REST handlers. Returns the acknowledgement's data, or `None` for a ping.
+    async fn run(&mut self, command: ClientCommand) -> Result<Option<serde_json::Value>, AppError> {
+        let data = match command {
+            ClientCommand::Create { todo } => {
+                json!(create_todo_in_list(&self.db, &self.user, todo.list_id, todo).await?)
+            }
+            ClientCommand::Update { id, version, changes } => {
+                json!(apply_todo_update(&self.db, &self.user, id, changes, version).await?)
+            }
+            ClientCommand::Toggle { id, version } => {
+                json!(apply_toggle(&self.db, &self.user, id, version).await?)
+            }
+            ClientCommand::Delete { id } => {
+                apply_delete(&self.db, &self.user, id).await?;
+                json!({"id": id})
+            }
+            ClientCommand::Subscribe { list_id } => {
+                if let Some(list_id) = list_id {
+                    require_list(&self.db, self.user.household_id, list_id).await?;
+                }
+                self.list_filter = list_id;
+                json!({"list_id": list_id})
+            }
+            ClientCommand::Ping => return Ok(None),
+        };
+
+        Ok(Some(data))
     }
 }
 
-pub fn broadcast_todo_update(message_type: &str, data: serde_json::Value) {
-    let message = WebSocketMessage {
-        message_type: message_type.to_string(),
-        data,
+fn is_visible_to(message: &WebSocketMessage, list_filter: Option<i32>) -> bool {
+    match (message.list_id, list_filter) {
+        (Some(list_id), Some(filter)) => list_id == filter,
+        _ => true,
+    }
+}
+
+/// Records an event and broadcasts it to the household's connected clients.
+/// Pass the todo's `list_id` so clients following other lists skip it, or
+/// `None` for household-wide events.
+///
+/// If the event cannot be stored it is still broadcast, without a `seq`;
+/// clients that miss it will not get it replayed.
+pub async fn broadcast_todo_update(
+    db: &Arc<dyn TodoStore>,
+    household_id: i32,
+    message_type: &str,
+    list_id: Option<i32>,
+    data: serde_json::Value,
+) {
+    let lock = PUBLISH_LOCKS.lock().unwrap().entry(household_id).or_default().clone();
+    let _guard = lock.lock().await;
+
+    let message = match db.append_event(household_id, message_type, list_id, data.clone()).await {
+        Ok(event) => event.into(),
+        Err(e) => {
+            tracing::error!("Failed to record {} event: {:#}", message_type, e);
+            WebSocketMessage {
+                message_type: message_type.to_string(),
+                seq: None,
+                list_id,
+                data,
+            }
+        }
     };
-    
-    let _ = BROADCASTER.send(message);
-}
\ No newline at end of file
+
+    let _ = BROADCASTER.send(Broadcast { household_id, message });
+}
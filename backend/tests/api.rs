@@ -0,0 +1,1639 @@
+//! Router-level tests. Each test runs against the in-memory store and against
+//! an in-memory SQLite database, so the shared SQL gets exercised too, and
+//! against a scratch Postgres database when `TEST_DATABASE_URL` is set.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{HeaderMap, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use family_todo_backend::{
+    app,
+    attachments::AttachmentStorage,
+    models::TodoFilter,
+    store::{MemoryStore, PostgresStore, SqliteStore, TodoStore, EVENT_RETENTION},
+    trash,
+};
+
+async fn stores() -> Vec<Arc<dyn TodoStore>> {
+    // Full-strength password hashing is far too slow for unoptimised test builds
+    std::env::set_var("PASSWORD_ROUNDS", "1000");
+    let attachments_dir = std::env::temp_dir().join(format!("family-todo-attachments-{}", std::process::id()));
+    std::env::set_var("ATTACHMENTS_DIR", attachments_dir);
+    std::env::set_var("MAX_ATTACHMENT_BYTES", "100000");
+
+    let sqlite = SqliteStore::new("sqlite::memory:").await.unwrap();
+    sqlite.migrate().await.unwrap();
+
+    let mut stores: Vec<Arc<dyn TodoStore>> = vec![Arc::new(MemoryStore::new()), Arc::new(sqlite)];
+    if let Some(postgres) = postgres().await {
+        stores.push(Arc::new(postgres));
+    }
+    stores
+}
+
+/// A freshly created and migrated Postgres database, when `TEST_DATABASE_URL`
+/// points at a server the tests may create databases on. Every call gets its
+/// own database, since each test expects to start from an empty one.
+async fn postgres() -> Option<PostgresStore> {
+    use sqlx::{Connection, PgConnection};
+
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let name = format!("family_todo_test_{:016x}", rand::random::<u64>());
+    let mut admin = PgConnection::connect(&url).await.unwrap();
+    sqlx::query(&format!("CREATE DATABASE {}", name)).execute(&mut admin).await.unwrap();
+    admin.close().await.unwrap();
+
+    let (server, _) = url.rsplit_once('/').unwrap();
+    let postgres = PostgresStore::new(&format!("{}/{}", server, name)).await.unwrap();
+    postgres.migrate().await.unwrap();
+    Some(postgres)
+}
+
+/// A router with an account signed in to it.
+struct TestApp {
+    router: Router,
+    /// `Cookie` header value carrying the account's session.
+    cookie: String,
+    household_id: i32,
+}
+
+async fn apps() -> Vec<TestApp> {
+    let mut apps = Vec::new();
+    for store in stores().await {
+        apps.push(signed_in(store).await);
+    }
+    apps
+}
+
+async fn signed_in(store: Arc<dyn TodoStore>) -> TestApp {
+    let router = app(store);
+    let (cookie, account) = register(&router, "parent@example.com", None).await;
+
+    TestApp {
+        router,
+        cookie,
+        household_id: account["household"]["id"].as_i64().unwrap() as i32,
+    }
+}
+
+/// Registers an account, returning its session cookie and the account.
+async fn register(router: &Router, email: &str, household_name: Option<&str>) -> (String, Value) {
+    let body = json!({"email": email, "password": "correct horse", "household_name": household_name});
+    let (status, headers, account) = request(router, Method::POST, "/api/auth/register", &[], Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    (session_cookie(&headers), account)
+}
+
+/// The `name=value` part of the `Set-Cookie` header.
+fn session_cookie(headers: &HeaderMap) -> String {
+    let set_cookie = headers["set-cookie"].to_str().unwrap();
+    set_cookie.split(';').next().unwrap().to_string()
+}
+
+/// Adds a family member with `role` and an account signing in as them.
+/// Returns the account's app and the member's id.
+async fn member_account(app: &TestApp, name: &str, role: &str) -> (TestApp, i32) {
+    let (_, member) = send(app, Method::POST, "/api/members", Some(json!({"display_name": name, "role": role}))).await;
+    let email = format!("{}@example.com", name.to_lowercase());
+    let body = json!({"email": email, "password": "correct horse", "member_id": member["id"]});
+    let (status, _) = send(app, Method::POST, "/api/users", Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let login = json!({"email": email, "password": "correct horse"});
+    let (_, headers, account) = request(&app.router, Method::POST, "/api/auth/login", &[], Some(login)).await;
+    assert_eq!(account["user"]["role"], role);
+    let account_app = TestApp {
+        router: app.router.clone(),
+        cookie: session_cookie(&headers),
+        household_id: app.household_id,
+    };
+
+    (account_app, member["id"].as_i64().unwrap() as i32)
+}
+
+async fn send(app: &TestApp, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let (status, _, body) = send_with_headers(app, method, uri, &[], body).await;
+
+    (status, body)
+}
+
+async fn send_with_headers(
+    app: &TestApp,
+    method: Method,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut headers = headers.to_vec();
+    headers.push(("cookie", &app.cookie));
+
+    request(&app.router, method, uri, &headers, body).await
+}
+
+/// Uploads `bytes` as the multipart field `file`.
+async fn upload(app: &TestApp, uri: &str, file_name: &str, content_type: &str, bytes: &[u8]) -> (StatusCode, Value) {
+    let boundary = "family-todo-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("cookie", &app.cookie)
+        .header("content-type", format!("multipart/form-data; boundary={boundary}"))
+        .body(Body::from(body))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// A GET whose response body is returned as raw bytes.
+async fn download(app: &TestApp, uri: &str) -> (StatusCode, HeaderMap, Vec<u8>) {
+    let request = Request::builder().uri(uri).header("cookie", &app.cookie).body(Body::empty()).unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, headers, bytes.to_vec())
+}
+
+async fn request(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+
+    (status, headers, body)
+}
+
+/// Flattens the date-grouped todo listing into its todos.
+fn todos_in(groups: &Value) -> Vec<Value> {
+    groups
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|group| group["todos"].as_array().unwrap().clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn starts_with_the_general_list() {
+    for app in apps().await {
+        let (status, lists) = send(&app, Method::GET, "/api/lists", None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(lists.as_array().unwrap().len(), 1);
+        assert_eq!(lists[0]["name"], "General");
+    }
+}
+
+#[tokio::test]
+async fn created_todos_are_listed_by_due_date() {
+    for app in apps().await {
+        let (status, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Buy milk", "due_date": "2024-03-02"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(todo["completed"], false);
+        send(&app, Method::POST, "/api/todos", Some(json!({"text": "Walk dog"}))).await;
+
+        let (status, groups) = send(&app, Method::GET, "/api/todos", None).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(groups[0]["date"], "No Due Date");
+        assert_eq!(groups[0]["todos"][0]["text"], "Walk dog");
+        assert_eq!(groups[1]["date"], "2024-03-02");
+        assert_eq!(groups[1]["todos"][0]["id"], todo["id"]);
+    }
+}
+
+#[tokio::test]
+async fn todos_are_filtered_sorted_and_searched_by_the_server() {
+    for app in apps().await {
+        let (_, sam) = send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Sam"}))).await;
+        let texts = |todos: &Value| -> Vec<String> {
+            todos.as_array().unwrap().iter().map(|todo| todo["text"].as_str().unwrap().to_string()).collect()
+        };
+        let (_, milk) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Buy milk", "due_date": "2024-03-02", "assignee_id": sam["id"], "tags": [" Groceries "]})),
+        )
+        .await;
+        assert_eq!(milk["tags"], json!(["groceries"]));
+        send(&app, Method::POST, "/api/todos", Some(json!({"text": "Walk 100% of the dog", "due_date": "2024-03-05"}))).await;
+        let (_, read) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Read"}))).await;
+        send(&app, Method::PUT, &format!("/api/todos/{}/toggle", read["id"]), None).await;
+        send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Bake bread", "due_date": "2024-03-01", "tags": ["groceries", "baking"]})),
+        )
+        .await;
+
+        let list = |query: &str| {
+            let app = &app;
+            let uri = format!("/api/todos?group_by=none&{}", query);
+            async move { send(app, Method::GET, &uri, None).await }
+        };
+        let (status, todos) = list(&format!("assignee={}", sam["id"])).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(texts(&todos), vec!["Buy milk"]);
+        assert_eq!(list("assignee=unassigned").await.1.as_array().unwrap().len(), 3);
+        assert_eq!(texts(&list("completed=true").await.1), vec!["Read"]);
+        assert_eq!(texts(&list("due_from=2024-03-02&due_to=2024-03-05&sort=due_date").await.1), vec!["Buy milk", "Walk 100% of the dog"]);
+        assert_eq!(texts(&list("tag=GROCERIES&sort=text").await.1), vec!["Bake bread", "Buy milk"]);
+        assert_eq!(texts(&list("q=100%25").await.1), vec!["Walk 100% of the dog"]);
+        assert_eq!(texts(&list("q=MILK").await.1), vec!["Buy milk"]);
+        assert_eq!(texts(&list("q=0_").await.1), Vec::<String>::new());
+
+        // Undated todos sort last either way
+        assert_eq!(
+            texts(&list("sort=due_date").await.1),
+            vec!["Bake bread", "Buy milk", "Walk 100% of the dog", "Read"]
+        );
+        assert_eq!(
+            texts(&list("sort=-due_date").await.1),
+            vec!["Walk 100% of the dog", "Buy milk", "Bake bread", "Read"]
+        );
+        assert_eq!(texts(&list("sort=-text").await.1), vec!["Walk 100% of the dog", "Read", "Buy milk", "Bake bread"]);
+
+        // Filters apply to the grouped whiteboard view and to a single list too
+        let (_, groups) = send(&app, Method::GET, "/api/todos?completed=false&due_to=2024-03-02", None).await;
+        assert_eq!(groups[0]["date"], "2024-03-01");
+        assert_eq!(todos_in(&groups).len(), 2);
+        let uri = format!("/api/lists/{}/todos?tag=baking", milk["list_id"]);
+        let (_, groups) = send(&app, Method::GET, &uri, None).await;
+        assert_eq!(todos_in(&groups).len(), 1);
+
+        let (_, milk) = send(&app, Method::PATCH, &format!("/api/todos/{}", milk["id"]), Some(json!({"tags": ["baking"]}))).await;
+        assert_eq!(milk["tags"], json!(["baking"]));
+        assert_eq!(list("tag=baking").await.1.as_array().unwrap().len(), 2);
+
+        let (status, _) = list("assignee=someone").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = list("sort=priority").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = list("due_from=2024-03-05&due_to=2024-03-01").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "due_to");
+        let (status, body) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Tagged", "tags": [""]}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "tags");
+    }
+}
+
+#[tokio::test]
+async fn todo_listings_page_through_with_cursors() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        for (text, due_date) in [
+            ("One", Some("2024-03-02")),
+            ("Two", None),
+            ("Three", Some("2024-03-01")),
+            ("Four", Some("2024-03-02")),
+            ("Five", None),
+        ] {
+            // With details, which each page has to load for its own todos
+            let todo = json!({"text": text, "due_date": due_date, "tags": [text.to_lowercase()]});
+            let (_, todo) = send(&app, Method::POST, "/api/todos", Some(todo)).await;
+            send(&app, Method::POST, &format!("/api/todos/{}/checklist", todo["id"]), Some(json!({"text": text}))).await;
+        }
+
+        for sort in ["-created_at", "created_at", "due_date", "-due_date"] {
+            let (_, everything) = send(&app, Method::GET, &format!("/api/todos?group_by=none&sort={}", sort), None).await;
+            let mut paged = Vec::new();
+            let mut cursor = String::new();
+            loop {
+                let uri = format!("/api/todos?group_by=none&sort={}&limit=2{}", sort, cursor);
+                let (status, page) = send(&app, Method::GET, &uri, None).await;
+                assert_eq!(status, StatusCode::OK);
+                paged.extend(page["todos"].as_array().unwrap().iter().cloned());
+                match page["next_cursor"].as_str() {
+                    Some(next) => cursor = format!("&cursor={}", next),
+                    None => break,
+                }
+            }
+            assert!(paged.iter().all(|todo| todo["tags"][0] == todo["checklist"][0]["text"].as_str().unwrap().to_lowercase()));
+            assert_eq!(Value::Array(paged), everything, "paging sort={}", sort);
+        }
+
+        // Pages of the whiteboard view are grouped too
+        let (_, page) = send(&app, Method::GET, "/api/todos?sort=due_date&limit=3", None).await;
+        assert_eq!(page["todos"][0]["date"], "2024-03-01");
+        assert_eq!(todos_in(&page["todos"]).len(), 3);
+        let next = page["next_cursor"].as_str().unwrap().to_string();
+
+        let (status, body) = send(&app, Method::GET, &format!("/api/todos?sort=created_at&limit=3&cursor={}", next), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "cursor");
+        let (status, _) = send(&app, Method::GET, "/api/todos?limit=3&cursor=not-a-cursor", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = send(&app, Method::GET, "/api/todos?sort=text&limit=500", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "limit");
+        assert_eq!(body["fields"][1]["field"], "sort");
+
+        // Todos completed recently are listed; ones completed before the cutoff are not
+        let (_, todos) = send(&app, Method::GET, "/api/todos?group_by=none&sort=created_at", None).await;
+        send(&app, Method::PUT, &format!("/api/todos/{}/toggle", todos[0]["id"]), None).await;
+        let (_, listed) = send(&app, Method::GET, "/api/todos?group_by=none&completed=true", None).await;
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        let (_, listed) = send(&app, Method::GET, "/api/todos?group_by=none&include_old_completed=true", None).await;
+        assert_eq!(listed.as_array().unwrap().len(), 5);
+
+        let filter = TodoFilter {
+            completed_since: Some(chrono::Utc::now() + chrono::Duration::minutes(1)),
+            ..TodoFilter::default()
+        };
+        let recent = store.find_todos(app.household_id, &filter).await.unwrap();
+        assert_eq!(recent.len(), 4);
+        assert!(recent.iter().all(|todo| !todo.completed));
+    }
+}
+
+#[tokio::test]
+async fn todos_are_bucketed_relative_to_today() {
+    for app in apps().await {
+        for (text, due_date) in [
+            ("Library books", Some("2024-03-05")),
+            ("Bins", Some("2024-03-07")),
+            ("Swimming", Some("2024-03-08")),
+            ("Party", Some("2024-03-10")),
+            ("Holiday", Some("2024-04-01")),
+            ("Someday", None),
+        ] {
+            send(&app, Method::POST, "/api/todos", Some(json!({"text": text, "due_date": due_date}))).await;
+        }
+
+        let (status, buckets) = send(&app, Method::GET, "/api/todos?group_by=bucket&today=2024-03-07", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let labels: Vec<&str> = buckets.as_array().unwrap().iter().map(|bucket| bucket["label"].as_str().unwrap()).collect();
+        assert_eq!(labels, vec!["Overdue", "Today", "Tomorrow", "This Week", "Later", "No Due Date"]);
+        assert_eq!(buckets[0]["bucket"], "overdue");
+        assert_eq!(buckets[0]["todos"][0]["text"], "Library books");
+
+        // Once done, a todo moves to the end whenever it was due
+        let library_books = buckets[0]["todos"][0]["id"].clone();
+        let bins = buckets[1]["todos"][0]["id"].clone();
+        for id in [&library_books, &bins] {
+            send(&app, Method::PUT, &format!("/api/todos/{}/toggle", id), None).await;
+        }
+        let (_, buckets) = send(&app, Method::GET, "/api/todos?group_by=bucket&today=2024-03-07", None).await;
+        assert_eq!(buckets[0]["bucket"], "tomorrow");
+        assert_eq!(buckets[4]["bucket"], "done");
+        let done: Vec<&Value> = buckets[4]["todos"].as_array().unwrap().iter().map(|todo| &todo["id"]).collect();
+        assert_eq!(done, vec![&library_books, &bins]);
+        for id in [&library_books, &bins] {
+            send(&app, Method::PUT, &format!("/api/todos/{}/toggle", id), None).await;
+        }
+
+        // Without a date, today is taken from the clock in the given timezone
+        let (status, buckets) = send(&app, Method::GET, "/api/todos?group_by=bucket&timezone=Pacific/Auckland", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(buckets[0]["todos"].as_array().unwrap().len(), 5);
+
+        let (status, body) = send(&app, Method::GET, "/api/todos?group_by=bucket&timezone=Somewhere/Else", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "timezone");
+    }
+}
+
+#[tokio::test]
+async fn due_times_are_read_in_the_household_timezone() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (_, household) = send(&app, Method::GET, "/api/household", None).await;
+        assert_eq!(household["timezone"], "UTC");
+        let (status, household) =
+            send(&app, Method::PUT, "/api/household", Some(json!({"timezone": " Pacific/Kiritimati "}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(household["timezone"], "Pacific/Kiritimati");
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "household_updated");
+        assert_eq!(events.last().unwrap().data["timezone"], "Pacific/Kiritimati");
+        let (_, account) = send(&app, Method::GET, "/api/auth/me", None).await;
+        assert_eq!(account["household"]["timezone"], "Pacific/Kiritimati");
+
+        // Kiritimati is 14 hours ahead of UTC, so its today is often UTC's tomorrow
+        let today = family_todo_core::today_in("Pacific/Kiritimati".parse().unwrap(), chrono::Utc::now());
+        let (status, breakfast) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Breakfast", "due_date": today, "due_time": "00:00"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(breakfast["due_time"], "00:00:00");
+        send(&app, Method::POST, "/api/todos", Some(json!({"text": "Dinner", "due_date": today, "due_time": "23:59:59"}))).await;
+
+        let (_, buckets) = send(&app, Method::GET, "/api/todos?group_by=bucket", None).await;
+        assert_eq!(buckets[0]["bucket"], "overdue");
+        assert_eq!(buckets[0]["todos"][0]["text"], "Breakfast");
+        assert_eq!(buckets[1]["bucket"], "today");
+        assert_eq!(buckets[1]["todos"][0]["text"], "Dinner");
+
+        // Clearing the due date clears the due time with it
+        let uri = format!("/api/todos/{}", breakfast["id"]);
+        let (_, breakfast) = send(&app, Method::PATCH, &uri, Some(json!({"due_date": null}))).await;
+        assert_eq!(breakfast["due_time"], Value::Null);
+
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(json!({"due_time": "08:00"}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "due_time");
+        let (status, body) = send(&app, Method::PUT, "/api/household", Some(json!({"timezone": "Middle/Earth"}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "timezone");
+    }
+}
+
+#[tokio::test]
+async fn rejects_invalid_todos_field_by_field() {
+    for app in apps().await {
+        let (status, error) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "   ", "assignee_id": 42, "recurrence": {"freq": "daily"}})),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "validation_failed");
+        let fields: Vec<&str> = error["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["text", "due_date", "assignee_id"]);
+        assert!(error["request_id"].is_string());
+
+        let (status, error) = send(
+            &app,
+            Method::POST,
+            "/api/lists/42/todos",
+            Some(json!({"text": "Feed cat"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["code"], "not_found");
+        assert_eq!(error["error"], "List not found");
+    }
+}
+
+#[tokio::test]
+async fn malformed_requests_get_json_errors() {
+    for app in apps().await {
+        let (status, error) = send(&app, Method::POST, "/api/todos", Some(json!({"txt": "typo"}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "bad_request");
+
+        let (status, error) = send(&app, Method::PUT, "/api/todos/abc/toggle", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "bad_request");
+    }
+}
+
+#[tokio::test]
+async fn echoes_the_client_request_id() {
+    let app = apps().await.remove(0);
+    let request = Request::builder()
+        .uri("/api/todos/999/occurrences")
+        .header("x-request-id", "kitchen-tablet-7")
+        .header("cookie", &app.cookie)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.router.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["x-request-id"], "kitchen-tablet-7");
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let error: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(error["request_id"], "kitchen-tablet-7");
+}
+
+#[tokio::test]
+async fn completing_a_recurring_todo_schedules_the_next_one() {
+    for app in apps().await {
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({
+                "text": "Take out bins",
+                "due_date": "2024-03-04",
+                "recurrence": {"freq": "weekly", "weekdays": ["Mon"]}
+            })),
+        )
+        .await;
+        let id = todo["id"].as_i64().unwrap();
+
+        let (status, toggled) = send(&app, Method::PUT, &format!("/api/todos/{id}/toggle"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(toggled["completed"], true);
+        assert!(toggled["next_occurrence_id"].is_number());
+
+        // Un-completing and completing again must not spawn a second copy
+        send(&app, Method::PUT, &format!("/api/todos/{id}/toggle"), None).await;
+        send(&app, Method::PUT, &format!("/api/todos/{id}/toggle"), None).await;
+
+        let (_, groups) = send(&app, Method::GET, "/api/todos", None).await;
+        let todos = todos_in(&groups);
+        assert_eq!(todos.len(), 2);
+        let next = todos.iter().find(|todo| todo["id"] == toggled["next_occurrence_id"]).unwrap();
+        assert_eq!(next["due_date"], "2024-03-11");
+        assert_eq!(next["completed"], false);
+    }
+}
+
+#[tokio::test]
+async fn stale_if_match_is_rejected_with_the_current_todo() {
+    for app in apps().await {
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Water plants"}))).await;
+        let uri = format!("/api/todos/{}", todo["id"]);
+        assert_eq!(todo["version"], 1);
+
+        // Tablet A saves on top of version 1
+        let (status, headers, updated) = send_with_headers(
+            &app,
+            Method::PUT,
+            &uri,
+            &[("if-match", "\"1\"")],
+            Some(json!({"text": "Water all plants"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(updated["version"], 2);
+        assert_eq!(headers["etag"], "\"2\"");
+
+        // Tablet B still thinks it is at version 1
+        let (status, headers, error) = send_with_headers(
+            &app,
+            Method::PUT,
+            &uri,
+            &[("if-match", "\"1\"")],
+            Some(json!({"text": "Water the cactus"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["code"], "version_conflict");
+        assert_eq!(error["current"]["text"], "Water all plants");
+        assert_eq!(headers["etag"], "\"2\"");
+
+        let toggle_uri = format!("{uri}/toggle");
+        let (status, _, _) =
+            send_with_headers(&app, Method::PUT, &toggle_uri, &[("if-match", "\"1\"")], None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _, toggled) =
+            send_with_headers(&app, Method::PUT, &toggle_uri, &[("if-match", "W/\"2\"")], None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(toggled["version"], 3);
+
+        // Without If-Match the last writer still wins
+        let (status, _) = send(&app, Method::PUT, &uri, Some(json!({"text": "Water the cactus"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, headers, current) = send_with_headers(&app, Method::GET, &uri, &[], None).await;
+        assert_eq!(current["text"], "Water the cactus");
+        assert_eq!(headers["etag"], "\"4\"");
+    }
+}
+
+#[tokio::test]
+async fn patch_distinguishes_missing_fields_from_null() {
+    for app in apps().await {
+        let (_, member) = send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Kid"}))).await;
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Piano practice", "assignee_id": member["id"], "due_date": "2024-05-01"})),
+        )
+        .await;
+        let uri = format!("/api/todos/{}", todo["id"]);
+
+        // Leaving a field out keeps it
+        let (status, patched) = send(&app, Method::PATCH, &uri, Some(json!({"text": "Piano"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(patched["due_date"], "2024-05-01");
+        assert_eq!(patched["assignee_id"], member["id"]);
+
+        // null clears it
+        let (_, patched) = send(&app, Method::PATCH, &uri, Some(json!({"due_date": null}))).await;
+        assert_eq!(patched["due_date"], Value::Null);
+        assert_eq!(patched["assignee_id"], member["id"]);
+        assert_eq!(patched["text"], "Piano");
+    }
+}
+
+#[tokio::test]
+async fn put_replaces_the_whole_todo() {
+    for app in apps().await {
+        let (_, member) = send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Kid"}))).await;
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Piano practice", "assignee_id": member["id"], "due_date": "2024-05-01"})),
+        )
+        .await;
+        let uri = format!("/api/todos/{}", todo["id"]);
+
+        let (status, replaced) = send(&app, Method::PUT, &uri, Some(json!({"text": "Guitar practice"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(replaced["text"], "Guitar practice");
+        assert_eq!(replaced["due_date"], Value::Null);
+        assert_eq!(replaced["assignee_id"], Value::Null);
+        assert_eq!(replaced["list_id"], todo["list_id"]);
+
+        let (status, error) = send(&app, Method::PUT, &uri, Some(json!({"due_date": "2024-05-02"}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "bad_request");
+    }
+}
+
+#[tokio::test]
+async fn recurring_todos_keep_their_due_date() {
+    for app in apps().await {
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Bins", "due_date": "2024-03-04", "recurrence": {"freq": "daily"}})),
+        )
+        .await;
+        let uri = format!("/api/todos/{}", todo["id"]);
+
+        let (status, error) = send(&app, Method::PATCH, &uri, Some(json!({"due_date": null}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "due_date");
+
+        // Dropping the rule at the same time is fine
+        let (status, patched) =
+            send(&app, Method::PATCH, &uri, Some(json!({"due_date": null, "recurrence": null}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(patched["recurrence"], Value::Null);
+    }
+}
+
+#[tokio::test]
+async fn moving_a_todo_between_lists() {
+    for app in apps().await {
+        let (_, chores) = send(&app, Method::POST, "/api/lists", Some(json!({"name": "Chores"}))).await;
+        assert_eq!(chores["position"], 1);
+        let chores_id = chores["id"].as_i64().unwrap();
+
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Vacuum"}))).await;
+        let id = todo["id"].as_i64().unwrap();
+
+        let (status, moved) = send(
+            &app,
+            Method::PATCH,
+            &format!("/api/todos/{id}"),
+            Some(json!({"list_id": chores_id})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(moved["list_id"], chores_id);
+        let general_id = todo["list_id"].clone();
+        assert_eq!(moved["text"], "Vacuum");
+
+        let (_, groups) = send(&app, Method::GET, &format!("/api/lists/{chores_id}/todos"), None).await;
+        assert_eq!(todos_in(&groups).len(), 1);
+
+        let (status, error) = send(&app, Method::PUT, &format!("/api/lists/{chores_id}"), Some(json!({"name": "  "}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "name");
+
+        // A list is only deleted once nothing is left on it, not even in the trash
+        let list_uri = format!("/api/lists/{chores_id}");
+        let (status, error) = send(&app, Method::DELETE, &list_uri, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["code"], "conflict");
+        send(&app, Method::DELETE, &format!("/api/todos/{id}"), None).await;
+        let (status, _) = send(&app, Method::DELETE, &list_uri, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = send(&app, Method::POST, &format!("/api/trash/{id}/restore"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        send(&app, Method::PATCH, &format!("/api/todos/{id}"), Some(json!({"list_id": general_id}))).await;
+        let (status, _) = send(&app, Method::DELETE, &list_uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (_, todo) = send(&app, Method::GET, &format!("/api/todos/{id}"), None).await;
+        assert_eq!(todo["text"], "Vacuum");
+    }
+}
+
+#[tokio::test]
+async fn member_names_are_unique() {
+    for app in apps().await {
+        let (status, member) = send(
+            &app,
+            Method::POST,
+            "/api/members",
+            Some(json!({"display_name": "Mom"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(member["initials"], "M");
+
+        let (status, error) = send(
+            &app,
+            Method::POST,
+            "/api/members",
+            Some(json!({"display_name": "Mom"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["code"], "conflict");
+
+        // Surrounding spaces are dropped before the name is checked and stored
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/api/members",
+            Some(json!({"display_name": " Mom "})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let padded = format!("  {}  ", "D".repeat(50));
+        let (status, member) = send(
+            &app,
+            Method::POST,
+            "/api/members",
+            Some(json!({"display_name": padded})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(member["display_name"], "D".repeat(50));
+    }
+}
+
+#[tokio::test]
+async fn deleting_a_member_unassigns_their_todos() {
+    for app in apps().await {
+        let (_, member) = send(
+            &app,
+            Method::POST,
+            "/api/members",
+            Some(json!({"display_name": "Dad"})),
+        )
+        .await;
+        let member_id = member["id"].as_i64().unwrap();
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Mow lawn", "assignee_id": member_id})),
+        )
+        .await;
+        assert_eq!(todo["assignee_id"], member_id);
+
+        let (status, _) = send(&app, Method::DELETE, &format!("/api/members/{member_id}"), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (_, groups) = send(&app, Method::GET, "/api/todos", None).await;
+        assert_eq!(todos_in(&groups)[0]["assignee_id"], Value::Null);
+    }
+}
+
+#[tokio::test]
+async fn changes_are_recorded_in_order_for_replay() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        assert_eq!(store.latest_event_seq(app.household_id).await.unwrap(), 0);
+
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Feed the cat"}))).await;
+        let toggle_uri = format!("/api/todos/{}/toggle", todo["id"]);
+        send(&app, Method::PUT, &toggle_uri, None).await;
+        send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Sam"}))).await;
+
+        let events = store.get_events_since(app.household_id, 0, 10).await.unwrap();
+        let types: Vec<&str> = events.iter().map(|event| event.event_type.as_str()).collect();
+        assert_eq!(types, ["todo_created", "todo_toggled", "member_created"]);
+        assert_eq!(events.iter().map(|event| event.seq).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(events[0].list_id, todo["list_id"].as_i64().map(|id| id as i32));
+        assert_eq!(events[1].data["completed"], true);
+        assert_eq!(events[2].list_id, None);
+
+        let missed = store.get_events_since(app.household_id, 1, 1).await.unwrap();
+        assert_eq!(missed.len(), 1);
+        assert_eq!(missed[0].event_type, "todo_toggled");
+        assert_eq!(store.latest_event_seq(app.household_id).await.unwrap(), 3);
+    }
+}
+
+#[tokio::test]
+async fn old_events_are_pruned() {
+    for store in stores().await {
+        store.append_event(2, "list_updated", None, json!({})).await.unwrap();
+        for _ in 0..EVENT_RETENTION + 5 {
+            store.append_event(1, "list_updated", None, json!({})).await.unwrap();
+        }
+
+        let events = store.get_events_since(1, 0, EVENT_RETENTION * 2).await.unwrap();
+        assert_eq!(events.len() as i64, EVENT_RETENTION);
+        assert_eq!(events[0].seq, 7);
+        assert_eq!(store.latest_event_seq(1).await.unwrap(), EVENT_RETENTION + 6);
+
+        // Each household keeps its own newest events
+        let quiet = store.get_events_since(2, 0, 10).await.unwrap();
+        assert_eq!(quiet.len(), 1);
+        assert_eq!(store.latest_event_seq(2).await.unwrap(), 1);
+    }
+}
+
+#[tokio::test]
+async fn the_api_requires_a_session() {
+    for app in apps().await {
+        for uri in ["/api/todos", "/api/lists", "/api/auth/me", "/ws"] {
+            let (status, _, error) = request(&app.router, Method::GET, uri, &[], None).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", uri);
+            assert_eq!(error["code"], "unauthorized");
+        }
+
+        let forged = [("cookie", "session=0000")];
+        let (status, _, _) = request(&app.router, Method::GET, "/api/todos", &forged, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn accounts_sign_in_and_out() {
+    for app in apps().await {
+        let (status, account) = send(&app, Method::GET, "/api/auth/me", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(account["user"]["email"], "parent@example.com");
+        assert!(account["user"].get("password_hash").is_none());
+
+        let (status, headers, _) = send_with_headers(&app, Method::POST, "/api/auth/logout", &[], None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(headers["set-cookie"].to_str().unwrap().contains("Max-Age=0"));
+        let (status, _) = send(&app, Method::GET, "/api/lists", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let wrong = json!({"email": "parent@example.com", "password": "wrong horse"});
+        let (status, _, error) = request(&app.router, Method::POST, "/api/auth/login", &[], Some(wrong)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error["error"], "Invalid email or password");
+
+        let right = json!({"email": " Parent@Example.com ", "password": "correct horse"});
+        let (status, headers, _) = request(&app.router, Method::POST, "/api/auth/login", &[], Some(right)).await;
+        assert_eq!(status, StatusCode::OK);
+        let cookie = session_cookie(&headers);
+        let (status, _, _) = request(&app.router, Method::GET, "/api/lists", &[("cookie", &cookie)], None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let again = json!({"email": "parent@example.com", "password": "short"});
+        let (status, _, error) = request(&app.router, Method::POST, "/api/auth/register", &[], Some(again)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "password");
+        assert_eq!(error["fields"][1], json!({"field": "email", "message": "is already registered"}));
+    }
+}
+
+#[tokio::test]
+async fn only_one_first_registration_claims_the_migrated_household() {
+    for store in stores().await {
+        let router = app(store);
+        let ((_, first), (_, second)) = tokio::join!(
+            register(&router, "first@example.com", None),
+            register(&router, "second@example.com", None),
+        );
+        assert_ne!(first["household"]["id"], second["household"]["id"]);
+    }
+}
+
+#[tokio::test]
+async fn households_only_see_their_own_data() {
+    for app in apps().await {
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Pay rent"}))).await;
+        send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Sam"}))).await;
+
+        let (cookie, account) = register(&app.router, "neighbour@example.com", Some("Next Door")).await;
+        let neighbour = TestApp {
+            router: app.router.clone(),
+            cookie,
+            household_id: account["household"]["id"].as_i64().unwrap() as i32,
+        };
+        assert_ne!(neighbour.household_id, app.household_id);
+        assert_eq!(account["household"]["name"], "Next Door");
+
+        let (_, lists) = send(&neighbour, Method::GET, "/api/lists", None).await;
+        assert_eq!(lists.as_array().unwrap().len(), 1);
+        assert_eq!(lists[0]["name"], "General");
+        assert_ne!(lists[0]["id"], todo["list_id"]);
+
+        let (_, groups) = send(&neighbour, Method::GET, "/api/todos", None).await;
+        assert!(todos_in(&groups).is_empty());
+        let todo_uri = format!("/api/todos/{}", todo["id"]);
+        let (status, _) = send(&neighbour, Method::GET, &todo_uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&neighbour, Method::DELETE, &todo_uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, error) = send(
+            &neighbour,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Sneaky", "list_id": todo["list_id"]})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "list_id");
+
+        // Names only clash within a household
+        let (status, _) = send(&neighbour, Method::POST, "/api/members", Some(json!({"display_name": "Sam"}))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn added_users_share_the_household() {
+    for app in apps().await {
+        send(&app, Method::POST, "/api/todos", Some(json!({"text": "Mow the lawn"}))).await;
+
+        let (status, user) = send(
+            &app,
+            Method::POST,
+            "/api/users",
+            Some(json!({"email": "partner@example.com", "password": "another horse"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(user["household_id"], app.household_id);
+
+        let login = json!({"email": "partner@example.com", "password": "another horse"});
+        let (_, headers, account) = request(&app.router, Method::POST, "/api/auth/login", &[], Some(login)).await;
+        assert_eq!(account["household"]["id"], app.household_id);
+
+        let cookie = session_cookie(&headers);
+        let (_, _, groups) = request(&app.router, Method::GET, "/api/todos", &[("cookie", &cookie)], None).await;
+        assert_eq!(todos_in(&groups)[0]["text"], "Mow the lawn");
+    }
+}
+
+#[tokio::test]
+async fn roles_limit_what_members_can_change() {
+    for app in apps().await {
+        let (_, other) = send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Alex"}))).await;
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+        let (guest, _) = member_account(&app, "Grandma", "guest").await;
+
+        // Children add and edit their own todos...
+        let (status, todo) = send(&child, Method::POST, "/api/todos", Some(json!({"text": "Feed the cat", "assignee_id": child_id}))).await;
+        assert_eq!(status, StatusCode::OK);
+        let todo_uri = format!("/api/todos/{}", todo["id"]);
+        let (status, _) = send(&child, Method::PATCH, &todo_uri, Some(json!({"text": "Feed both cats"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&child, Method::PUT, &format!("{}/toggle", todo_uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // ...but cannot reassign or delete them, or manage the household
+        let (status, error) = send(&child, Method::PATCH, &todo_uri, Some(json!({"assignee_id": other["id"]}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(error["code"], "forbidden");
+        let (status, _) = send(&child, Method::POST, "/api/todos", Some(json!({"text": "Chores", "assignee_id": other["id"]}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&child, Method::DELETE, &todo_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (_, todo) = send(&child, Method::POST, &format!("{}/checklist", todo_uri), Some(json!({"text": "Fill the bowl"}))).await;
+        let step_uri = format!("{}/checklist/{}", todo_uri, todo["checklist"][0]["id"]);
+        let (status, _) = send(&child, Method::DELETE, &step_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, attachment) = upload(&child, &format!("{}/attachments", todo_uri), "bowl.txt", "text/plain", b"blue").await;
+        assert_eq!(status, StatusCode::OK);
+        let attachment_uri = format!("/api/attachments/{}", attachment["id"]);
+        let (status, _) = send(&child, Method::DELETE, &attachment_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, _) = download(&child, &attachment_uri).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&child, Method::POST, "/api/lists", Some(json!({"name": "Games"}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&child, Method::PUT, &format!("/api/members/{}", child_id), Some(json!({"role": "parent"}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Guests only look
+        let (status, _) = send(&guest, Method::GET, &todo_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&guest, Method::PUT, &format!("{}/toggle", todo_uri), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&guest, Method::POST, "/api/todos", Some(json!({"text": "Knit"}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&app, Method::DELETE, &todo_uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+}
+
+#[tokio::test]
+async fn completions_needing_approval_wait_for_a_parent() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Tidy room", "assignee_id": child_id, "needs_approval": true})),
+        )
+        .await;
+        let todo_uri = format!("/api/todos/{}", todo["id"]);
+        let toggle_uri = format!("{}/toggle", todo_uri);
+
+        let (status, pending) = send(&child, Method::PUT, &toggle_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(pending["completed"], false);
+        assert_eq!(pending["approval_requested_by"], child_id);
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "todo_approval_requested");
+
+        let (status, _) = send(&child, Method::PATCH, &todo_uri, Some(json!({"completed": true}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&child, Method::PATCH, &todo_uri, Some(json!({"needs_approval": false}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&child, Method::POST, &format!("{}/approve", todo_uri), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Turned down: back to open
+        let (_, rejected) = send(&app, Method::POST, &format!("{}/reject", todo_uri), None).await;
+        assert_eq!(rejected["approval_requested_by"], Value::Null);
+        assert_eq!(rejected["completed"], false);
+
+        send(&child, Method::PUT, &toggle_uri, None).await;
+        let (status, approved) = send(&app, Method::POST, &format!("{}/approve", todo_uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(approved["completed"], true);
+        assert_eq!(approved["approval_requested_by"], Value::Null);
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "todo_approved");
+
+        let (status, error) = send(&app, Method::POST, &format!("{}/approve", todo_uri), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["error"], "Todo is not waiting for approval");
+    }
+}
+
+#[tokio::test]
+async fn removing_a_member_removes_their_account() {
+    for app in apps().await {
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+
+        send(&app, Method::DELETE, &format!("/api/members/{}", child_id), None).await;
+
+        let (status, _) = send(&child, Method::GET, "/api/todos", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let login = json!({"email": "sam@example.com", "password": "correct horse"});
+        let (status, _, _) = request(&app.router, Method::POST, "/api/auth/login", &[], Some(login)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn completing_todos_earns_points_to_spend_on_rewards() {
+    for app in apps().await {
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+        let balance_uri = format!("/api/members/{}/balance", child_id);
+
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Feed the cat", "assignee_id": child_id, "points": 30})),
+        )
+        .await;
+        let toggle_uri = format!("/api/todos/{}/toggle", todo["id"]);
+
+        send(&child, Method::PUT, &toggle_uri, None).await;
+        let (_, balance) = send(&child, Method::GET, &balance_uri, None).await;
+        assert_eq!(balance["balance"], 30);
+
+        // Reopening takes the points back, and completing again only pays once
+        send(&child, Method::PUT, &toggle_uri, None).await;
+        let (_, balance) = send(&child, Method::GET, &balance_uri, None).await;
+        assert_eq!(balance["balance"], 0);
+        send(&child, Method::PUT, &toggle_uri, None).await;
+        let (_, balance) = send(&child, Method::GET, &balance_uri, None).await;
+        assert_eq!(balance["balance"], 30);
+
+        let (status, _) = send(&child, Method::POST, "/api/rewards", Some(json!({"name": "Movie night", "cost": 20}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, error) = send(&app, Method::POST, "/api/rewards", Some(json!({"name": "", "cost": 0}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"].as_array().unwrap().len(), 2);
+        let (_, movie) = send(&app, Method::POST, "/api/rewards", Some(json!({"name": "Movie night", "cost": 20}))).await;
+        let redeem_uri = format!("/api/rewards/{}/redeem", movie["id"]);
+
+        let (status, entry) = send(&app, Method::POST, &redeem_uri, Some(json!({"member_id": child_id}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(entry["amount"], -20);
+        assert_eq!(entry["reason"], "reward_redeemed");
+
+        let (status, error) = send(&app, Method::POST, &redeem_uri, Some(json!({"member_id": child_id}))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["error"], "Not enough points (10 available)");
+
+        let (_, ledger) = send(&app, Method::GET, &format!("/api/members/{}/ledger", child_id), None).await;
+        let reasons: Vec<_> = ledger.as_array().unwrap().iter().map(|entry| entry["reason"].clone()).collect();
+        assert_eq!(reasons, vec!["reward_redeemed", "todo_completed", "todo_reopened", "todo_completed"]);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_redemptions_cannot_overspend() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Feed the cat", "assignee_id": child_id, "points": 30})),
+        )
+        .await;
+        send(&child, Method::PUT, &format!("/api/todos/{}/toggle", todo["id"]), None).await;
+        let (_, movie) = send(&app, Method::POST, "/api/rewards", Some(json!({"name": "Movie night", "cost": 20}))).await;
+        let redeem_uri = format!("/api/rewards/{}/redeem", movie["id"]);
+
+        let app = Arc::new(app);
+        let redemptions = (0..8).map(|_| {
+            let (app, redeem_uri) = (app.clone(), redeem_uri.clone());
+            tokio::spawn(async move { send(&app, Method::POST, &redeem_uri, Some(json!({"member_id": child_id}))).await.0 })
+        });
+        let statuses: Vec<_> = futures::future::try_join_all(redemptions).await.unwrap();
+        assert_eq!(statuses.iter().filter(|&&status| status == StatusCode::OK).count(), 1, "{:?}", statuses);
+        assert!(statuses.iter().all(|&status| status == StatusCode::OK || status == StatusCode::CONFLICT));
+        assert_eq!(store.get_point_balance(app.household_id, child_id).await.unwrap(), 10);
+    }
+}
+
+#[tokio::test]
+async fn completing_through_an_update_counts_like_a_toggle() {
+    for app in apps().await {
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+        let balance_uri = format!("/api/members/{}/balance", child_id);
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({
+                "text": "Feed the cat",
+                "assignee_id": child_id,
+                "points": 30,
+                "due_date": "2024-03-04",
+                "recurrence": {"freq": "daily"}
+            })),
+        )
+        .await;
+        let todo_uri = format!("/api/todos/{}", todo["id"]);
+
+        let (status, updated) = send(&child, Method::PATCH, &todo_uri, Some(json!({"completed": true}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(updated["completed"], true);
+        assert!(updated["next_occurrence_id"].is_number());
+        let (_, balance) = send(&child, Method::GET, &balance_uri, None).await;
+        assert_eq!(balance["balance"], 30);
+
+        // Reopening by an update takes the points back, so toggling again cannot pay twice
+        send(&child, Method::PATCH, &todo_uri, Some(json!({"completed": false, "text": "Feed the cats"}))).await;
+        let (_, balance) = send(&child, Method::GET, &balance_uri, None).await;
+        assert_eq!(balance["balance"], 0);
+        send(&child, Method::PUT, &format!("{}/toggle", todo_uri), None).await;
+        let (_, balance) = send(&child, Method::GET, &balance_uri, None).await;
+        assert_eq!(balance["balance"], 30);
+
+        // A PUT without `completed` leaves the todo done
+        let (status, replaced) = send(&app, Method::PUT, &todo_uri, Some(json!({"text": "Feed the cat"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(replaced["completed"], true);
+    }
+}
+
+#[tokio::test]
+async fn only_parents_award_points() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+
+        let (status, _) = send(&child, Method::POST, "/api/todos", Some(json!({"text": "Nap", "points": 500}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Nap", "points": -1}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({"text": "Tidy room", "assignee_id": child_id, "points": 50, "needs_approval": true})),
+        )
+        .await;
+        let todo_uri = format!("/api/todos/{}", todo["id"]);
+        let (status, _) = send(&child, Method::PATCH, &todo_uri, Some(json!({"points": 1000}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Asking for approval earns nothing until a parent approves
+        send(&child, Method::PUT, &format!("{}/toggle", todo_uri), None).await;
+        assert_eq!(store.get_point_balance(app.household_id, child_id).await.unwrap(), 0);
+        send(&app, Method::POST, &format!("{}/approve", todo_uri), None).await;
+        assert_eq!(store.get_point_balance(app.household_id, child_id).await.unwrap(), 50);
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "points_changed");
+    }
+}
+
+#[tokio::test]
+async fn recurring_chores_rotate_between_members() {
+    for app in apps().await {
+        let mut ids = Vec::new();
+        for name in ["Joe", "Shannon", "Kid"] {
+            let (_, member) = send(&app, Method::POST, "/api/members", Some(json!({"display_name": name}))).await;
+            ids.push(member["id"].clone());
+        }
+
+        let (status, error) = send(&app, Method::POST, "/api/rotations", Some(json!({"member_ids": [ids[0], ids[0]]}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "member_ids");
+        let (_, rotation) = send(&app, Method::POST, "/api/rotations", Some(json!({"member_ids": ids, "skip_away": true}))).await;
+        assert_eq!(rotation["member_ids"], json!(ids));
+
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({
+                "text": "Dishes",
+                "assignee_id": ids[0],
+                "due_date": "2024-03-04",
+                "recurrence": {"freq": "daily"},
+                "rotation_id": rotation["id"]
+            })),
+        )
+        .await;
+
+        // Shannon is away, so the kid is next
+        send(&app, Method::PUT, &format!("/api/members/{}", ids[1]), Some(json!({"away": true}))).await;
+        let (_, toggled) = send(&app, Method::PUT, &format!("/api/todos/{}/toggle", todo["id"]), None).await;
+        let (_, next) = send(&app, Method::GET, &format!("/api/todos/{}", toggled["next_occurrence_id"]), None).await;
+        assert_eq!(next["assignee_id"], ids[2]);
+        assert_eq!(next["rotation_id"], rotation["id"]);
+
+        // ...and then back around to Joe
+        let (_, toggled) = send(&app, Method::PUT, &format!("/api/todos/{}/toggle", next["id"]), None).await;
+        let (_, next) = send(&app, Method::GET, &format!("/api/todos/{}", toggled["next_occurrence_id"]), None).await;
+        assert_eq!(next["assignee_id"], ids[0]);
+
+        // Without the rotation the chore stays with whoever has it
+        send(&app, Method::DELETE, &format!("/api/rotations/{}", rotation["id"]), None).await;
+        let (_, toggled) = send(&app, Method::PUT, &format!("/api/todos/{}/toggle", next["id"]), None).await;
+        let (_, next) = send(&app, Method::GET, &format!("/api/todos/{}", toggled["next_occurrence_id"]), None).await;
+        assert_eq!(next["assignee_id"], ids[0]);
+        assert_eq!(next["rotation_id"], Value::Null);
+    }
+}
+
+#[tokio::test]
+async fn checklists_track_progress_and_can_complete_the_todo() {
+    for app in apps().await {
+        let (_, todo) = send(
+            &app,
+            Method::POST,
+            "/api/todos",
+            Some(json!({
+                "text": "Pack for the trip",
+                "due_date": "2024-03-04",
+                "recurrence": {"freq": "weekly", "weekdays": ["Mon"]},
+                "auto_complete": true
+            })),
+        )
+        .await;
+        let checklist_uri = format!("/api/todos/{}/checklist", todo["id"]);
+
+        send(&app, Method::POST, &checklist_uri, Some(json!({"text": "Socks"}))).await;
+        send(&app, Method::POST, &checklist_uri, Some(json!({"text": "Passports"}))).await;
+        let (status, todo) = send(&app, Method::POST, &checklist_uri, Some(json!({"text": "Tickets", "position": -1}))).await;
+        assert_eq!(status, StatusCode::OK);
+        let texts: Vec<_> = todo["checklist"].as_array().unwrap().iter().map(|item| item["text"].clone()).collect();
+        assert_eq!(texts, vec!["Tickets", "Socks", "Passports"]);
+        assert_eq!(todo["progress"], json!({"done": 0, "total": 3}));
+        assert_eq!(todo["version"], 4);
+
+        let (status, _) = send(&app, Method::POST, &checklist_uri, Some(json!({"text": " "}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(&app, Method::PATCH, &format!("{}/999", checklist_uri), Some(json!({"completed": true}))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let ids: Vec<_> = todo["checklist"].as_array().unwrap().iter().map(|item| item["id"].clone()).collect();
+        send(&app, Method::DELETE, &format!("{}/{}", checklist_uri, ids[1]), None).await;
+        let (_, todo) = send(&app, Method::PATCH, &format!("{}/{}", checklist_uri, ids[0]), Some(json!({"completed": true}))).await;
+        assert_eq!(todo["progress"], json!({"done": 1, "total": 2}));
+        assert_eq!(todo["completed"], false);
+
+        // Ticking the last step completes the todo, which schedules the next trip
+        let (_, todo) = send(&app, Method::PATCH, &format!("{}/{}", checklist_uri, ids[2]), Some(json!({"completed": true}))).await;
+        assert_eq!(todo["completed"], true);
+        assert_eq!(todo["progress"], json!({"done": 2, "total": 2}));
+
+        let (_, groups) = send(&app, Method::GET, "/api/todos", None).await;
+        let todos = todos_in(&groups);
+        let next = todos.iter().find(|next| next["id"] == todo["next_occurrence_id"]).unwrap();
+        assert_eq!(next["progress"], json!({"done": 0, "total": 2}));
+        assert_eq!(next["checklist"][0]["text"], "Tickets");
+        assert_ne!(next["checklist"][0]["id"], ids[0]);
+    }
+}
+
+#[tokio::test]
+async fn comments_are_attributed_and_broadcast() {
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+        let (_, dad) = send(&app, Method::POST, "/api/members", Some(json!({"display_name": "Dad"}))).await;
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Buy shoes"}))).await;
+        let comments_uri = format!("/api/todos/{}/comments", todo["id"]);
+
+        let (status, comment) = send(&child, Method::POST, &comments_uri, Some(json!({"text": "Size 4 please"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(comment["author_id"], child_id);
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "comment_added");
+        assert_eq!(events.last().unwrap().list_id, todo["list_id"].as_i64().map(|id| id as i32));
+
+        let (status, _) = send(&child, Method::POST, &comments_uri, Some(json!({"text": "From Dad", "author_id": dad["id"]}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, error) = send(&app, Method::POST, &comments_uri, Some(json!({"text": "", "author_id": 999}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"].as_array().unwrap().len(), 2);
+
+        // The first account has no member of its own, so it names one
+        send(&app, Method::POST, &comments_uri, Some(json!({"text": "Bought the wrong size, returning it", "author_id": dad["id"]}))).await;
+
+        let (_, comments) = send(&child, Method::GET, &comments_uri, None).await;
+        let authors: Vec<_> = comments.as_array().unwrap().iter().map(|comment| comment["author_id"].clone()).collect();
+        assert_eq!(authors, vec![json!(child_id), dad["id"].clone()]);
+
+        let (status, _) = send(&app, Method::GET, "/api/todos/999/comments", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn attachments_are_stored_with_thumbnails_and_limits() {
+    let mut png = Vec::new();
+    image::RgbImage::from_pixel(600, 300, image::Rgb([200, 40, 40]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (guest, _) = member_account(&app, "Grandma", "guest").await;
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Sign permission slip"}))).await;
+        let uri = format!("/api/todos/{}/attachments", todo["id"]);
+
+        let (status, photo) = upload(&app, &uri, "C:\\Photos\\slip.png", "image/png", &png).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(photo["file_name"], "slip.png");
+        assert_eq!(photo["size"], png.len());
+        assert_eq!(photo["has_thumbnail"], true);
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "todo_updated");
+
+        let (status, notes) = upload(&app, &uri, "notes.txt", "text/plain", b"Bring a packed lunch").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(notes["has_thumbnail"], false);
+
+        let (_, todo) = send(&app, Method::GET, &format!("/api/todos/{}", todo["id"]), None).await;
+        assert_eq!(todo["attachments"].as_array().unwrap().len(), 2);
+        assert_eq!(todo["version"], 3);
+
+        let (status, headers, bytes) = download(&guest, &format!("/api/attachments/{}", photo["id"])).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-type"], "image/png");
+        assert_eq!(bytes, png);
+        let (status, _, bytes) = download(&guest, &format!("/api/attachments/{}/thumbnail", photo["id"])).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(image::load_from_memory(&bytes).unwrap().width(), 256);
+        let (_, headers, _) = download(&app, &format!("/api/attachments/{}", notes["id"])).await;
+        assert_eq!(headers["content-disposition"], "attachment; filename=\"notes.txt\"");
+        let (status, _, _) = download(&app, &format!("/api/attachments/{}/thumbnail", notes["id"])).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = upload(&guest, &uri, "slip.png", "image/png", &png).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, error) = upload(&app, &uri, "page.html", "text/html", b"<script></script>").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "file");
+        let (status, _) = upload(&app, &uri, "fake.png", "image/png", b"not really a png").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, error) = upload(&app, &uri, "big.txt", "text/plain", &vec![b'a'; 100_001]).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(error["code"], "payload_too_large");
+        let (status, _) = upload(&app, "/api/todos/999/attachments", "notes.txt", "text/plain", b"hi").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, Method::DELETE, &format!("/api/attachments/{}", notes["id"]), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _, _) = download(&app, &format!("/api/attachments/{}", notes["id"])).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn todo_changes_are_kept_in_history() {
+    for store in stores().await {
+        let app = signed_in(store).await;
+        let (child, child_id) = member_account(&app, "Sam", "child").await;
+        let (_, todo) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Dishes"}))).await;
+        let todo_uri = format!("/api/todos/{}", todo["id"]);
+
+        send(&child, Method::PUT, &format!("{}/toggle", todo_uri), None).await;
+        send(&child, Method::PUT, &format!("{}/toggle", todo_uri), None).await;
+        send(&app, Method::PATCH, &todo_uri, Some(json!({"text": "Dishes and pans"}))).await;
+        // Changes nothing, so leaves no trace
+        send(&app, Method::PATCH, &todo_uri, Some(json!({"text": "Dishes and pans"}))).await;
+
+        let (status, history) = send(&child, Method::GET, &format!("{}/history", todo_uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let actions: Vec<_> = history.as_array().unwrap().iter().map(|entry| entry["action"].clone()).collect();
+        assert_eq!(actions, vec![json!("updated"), json!("reopened"), json!("completed"), json!("created")]);
+
+        // Who un-ticked the dishes?
+        let reopened = &history[1];
+        assert_eq!(reopened["member_id"], child_id);
+        assert_eq!(reopened["changes"]["completed"], json!({"old": true, "new": false}));
+        assert_eq!(history[0]["changes"], json!({"text": {"old": "Dishes", "new": "Dishes and pans"}}));
+        assert_eq!(history[3]["changes"]["text"], json!({"old": null, "new": "Dishes"}));
+
+        let (status, _) = send(&app, Method::DELETE, &todo_uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, history) = send(&app, Method::GET, &format!("{}/history", todo_uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(history[0]["action"], "deleted");
+        assert_eq!(history[0]["todo_text"], "Dishes and pans");
+
+        send(&app, Method::POST, "/api/todos", Some(json!({"text": "Laundry"}))).await;
+        let (_, activity) = send(&app, Method::GET, "/api/activity?limit=2", None).await;
+        let texts: Vec<_> = activity.as_array().unwrap().iter().map(|entry| entry["todo_text"].clone()).collect();
+        assert_eq!(texts, vec![json!("Laundry"), json!("Dishes and pans")]);
+        let (_, older) = send(&app, Method::GET, &format!("/api/activity?before={}", activity[1]["id"]), None).await;
+        assert_eq!(older.as_array().unwrap().len(), 4);
+
+        let (status, _) = send(&app, Method::GET, "/api/todos/999/history", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn todos_are_found_by_their_text_checklist_and_comments() {
+    // The in-memory and SQLite stores forgive typos, Postgres full-text search does not
+    for (app, forgives_typos) in apps().await.into_iter().zip([true, true, false]) {
+        let (_, dentist) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Call the dentist"}))).await;
+        send(&app, Method::PUT, &format!("/api/todos/{}/toggle", dentist["id"]), None).await;
+        let (_, milk) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Buy milk"}))).await;
+        let comment = json!({"text": "Pick up the dentist's bill on the way"});
+        send(&app, Method::POST, &format!("/api/todos/{}/comments", milk["id"]), Some(comment)).await;
+        let (_, trip) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Pack for the trip"}))).await;
+        send(&app, Method::POST, &format!("/api/todos/{}/checklist", trip["id"]), Some(json!({"text": "Toothbrush"}))).await;
+
+        // Completed todos are found too, and a match in the text ranks above one in a comment
+        let (status, hits) = send(&app, Method::GET, "/api/search?q=Dentist", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(hits.as_array().unwrap().len(), 2);
+        assert_eq!(hits[0]["todo"]["id"], dentist["id"]);
+        assert_eq!(hits[0]["snippet"], "Call the <mark>dentist</mark>");
+        assert_eq!(hits[1]["todo"]["id"], milk["id"]);
+        assert!(hits[1]["snippet"].as_str().unwrap().contains("<mark>dentist</mark>"));
+        assert!(hits[0]["rank"].as_f64() > hits[1]["rank"].as_f64());
+
+        let (_, hits) = send(&app, Method::GET, "/api/search?q=tooth", None).await;
+        assert_eq!(hits[0]["todo"]["id"], trip["id"]);
+        // Postgres quotes the todo text around the checklist item, the fallback only the item
+        assert!(hits[0]["snippet"].as_str().unwrap().contains("<mark>Toothbrush</mark>"));
+        if forgives_typos {
+            let (_, hits) = send(&app, Method::GET, "/api/search?q=dentst", None).await;
+            assert_eq!(hits[0]["todo"]["id"], dentist["id"]);
+        }
+        let (_, hits) = send(&app, Method::GET, "/api/search?q=dentist&limit=1", None).await;
+        assert_eq!(hits.as_array().unwrap().len(), 1);
+
+        send(&app, Method::DELETE, &format!("/api/todos/{}", dentist["id"]), None).await;
+        let (_, hits) = send(&app, Method::GET, "/api/search?q=dentist", None).await;
+        assert_eq!(hits.as_array().unwrap().len(), 1);
+
+        let (status, error) = send(&app, Method::GET, "/api/search?q=%20-", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["fields"][0]["field"], "q");
+    }
+}
+
+#[tokio::test]
+async fn deleted_todos_wait_in_the_trash() {
+    // Its own directory, so sweeping orphans cannot touch other tests' files
+    let storage = AttachmentStorage::new(
+        std::env::temp_dir().join(format!("family-todo-trash-{}", std::process::id())),
+        1000,
+    );
+
+    for store in stores().await {
+        let app = signed_in(store.clone()).await;
+        let (child, _) = member_account(&app, "Sam", "child").await;
+        let (_, dishes) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Dishes"}))).await;
+        let (_, laundry) = send(&app, Method::POST, "/api/todos", Some(json!({"text": "Laundry"}))).await;
+        let dishes_uri = format!("/api/todos/{}", dishes["id"]);
+
+        let (status, _) = send(&app, Method::DELETE, &dishes_uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, Method::GET, &dishes_uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, Method::PUT, &format!("{}/toggle", dishes_uri), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, groups) = send(&app, Method::GET, "/api/todos", None).await;
+        assert_eq!(todos_in(&groups).len(), 1);
+
+        let (_, trashed) = send(&child, Method::GET, "/api/trash", None).await;
+        assert_eq!(trashed.as_array().unwrap().len(), 1);
+        assert_eq!(trashed[0]["id"], dishes["id"]);
+        assert!(trashed[0]["deleted_at"].is_string());
+
+        let restore_uri = format!("/api/trash/{}/restore", dishes["id"]);
+        let (status, _) = send(&child, Method::POST, &restore_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, restored) = send(&app, Method::POST, &restore_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(restored["deleted_at"], Value::Null);
+        let events = store.get_events_since(app.household_id, 0, 100).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, "todo_restored");
+        let (_, history) = send(&app, Method::GET, &format!("{}/history", dishes_uri), None).await;
+        assert_eq!(history[0]["action"], "restored");
+        assert_eq!(history[0]["changes"]["deleted_at"]["new"], Value::Null);
+        let (status, _) = send(&app, Method::POST, &restore_uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Only trashed todos can be purged
+        let purge_uri = format!("/api/trash/{}", dishes["id"]);
+        let (status, _) = send(&app, Method::DELETE, &purge_uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        send(&app, Method::DELETE, &dishes_uri, None).await;
+        let (status, _) = send(&app, Method::DELETE, &purge_uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (_, trashed) = send(&app, Method::GET, "/api/trash", None).await;
+        assert_eq!(trashed, json!([]));
+        let (_, history) = send(&app, Method::GET, &format!("{}/history", dishes_uri), None).await;
+        assert_eq!(history[0]["action"], "purged");
+
+        // The purge job leaves trash younger than the retention window alone
+        send(&app, Method::DELETE, &format!("/api/todos/{}", laundry["id"]), None).await;
+        let purged = trash::purge_expired(store.as_ref(), &storage, chrono::Duration::days(30)).await.unwrap();
+        assert_eq!(purged, 0);
+        let purged = trash::purge_expired(store.as_ref(), &storage, chrono::Duration::zero()).await.unwrap();
+        assert_eq!(purged, 1);
+        let (_, trashed) = send(&app, Method::GET, "/api/trash", None).await;
+        assert_eq!(trashed, json!([]));
+    }
+}
@@ -0,0 +1,218 @@
+//! Commands sent over the WebSocket, against a server on a local port.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request},
+    Router,
+};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+use tower::ServiceExt;
+
+use family_todo_backend::{
+    app,
+    store::{MemoryStore, PostgresStore, SqliteStore, TodoStore},
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn stores() -> Vec<Arc<dyn TodoStore>> {
+    // Full-strength password hashing is far too slow for unoptimised test builds
+    std::env::set_var("PASSWORD_ROUNDS", "1000");
+
+    let sqlite = SqliteStore::new("sqlite::memory:").await.unwrap();
+    sqlite.migrate().await.unwrap();
+
+    let mut stores: Vec<Arc<dyn TodoStore>> = vec![Arc::new(MemoryStore::new()), Arc::new(sqlite)];
+    if let Some(postgres) = postgres().await {
+        stores.push(Arc::new(postgres));
+    }
+    stores
+}
+
+/// A freshly created and migrated Postgres database, when `TEST_DATABASE_URL`
+/// points at a server the tests may create databases on. Every call gets its
+/// own database, since each test expects to start from an empty one.
+async fn postgres() -> Option<PostgresStore> {
+    use sqlx::{Connection, PgConnection};
+
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let name = format!("family_todo_test_{:016x}", rand::random::<u64>());
+    let mut admin = PgConnection::connect(&url).await.unwrap();
+    sqlx::query(&format!("CREATE DATABASE {}", name)).execute(&mut admin).await.unwrap();
+    admin.close().await.unwrap();
+
+    let (server, _) = url.rsplit_once('/').unwrap();
+    let postgres = PostgresStore::new(&format!("{}/{}", server, name)).await.unwrap();
+    postgres.migrate().await.unwrap();
+    Some(postgres)
+}
+
+/// Serves `store` on a free port, registers an account and opens a
+/// WebSocket to it signed in as that account.
+async fn connect(store: Arc<dyn TodoStore>) -> Socket {
+    let (_, socket, _) = serve(store).await;
+    socket
+}
+
+/// Like [`connect`], also returning the router and the account's cookie.
+async fn serve(store: Arc<dyn TodoStore>) -> (Router, Socket, String) {
+    let router = app(store);
+    let register = Request::builder()
+        .method(Method::POST)
+        .uri("/api/auth/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({"email": "parent@example.com", "password": "correct horse"}).to_string()))
+        .unwrap();
+    let response = router.clone().oneshot(register).await.unwrap();
+    let set_cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+    let cookie = set_cookie.split(';').next().unwrap().to_string();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = router.clone();
+    tokio::spawn(async move { axum::serve(listener, server).await.unwrap() });
+
+    let mut request = format!("ws://{}/ws", address).into_client_request().unwrap();
+    request.headers_mut().insert(header::COOKIE, cookie.parse().unwrap());
+    let (socket, _) = connect_async(request).await.unwrap();
+    (router, socket, cookie)
+}
+
+/// Sends a command and waits for the reply carrying its `request_id`,
+/// skipping the broadcast events in between.
+async fn request(socket: &mut Socket, command: Value) -> Value {
+    let request_id = command["request_id"].clone();
+    socket.send(Message::Text(command.to_string())).await.unwrap();
+
+    loop {
+        let Some(Ok(Message::Text(text))) = socket.next().await else {
+            panic!("connection closed before the reply to {}", request_id);
+        };
+        let message: Value = serde_json::from_str(&text).unwrap();
+        if message["request_id"] == request_id {
+            return message;
+        }
+    }
+}
+
+#[tokio::test]
+async fn todos_can_be_managed_over_the_socket() {
+    for store in stores().await {
+        let mut socket = connect(store.clone()).await;
+
+        let reply = request(&mut socket, json!({
+            "type": "create", "request_id": "1", "todo": {"text": "Water the plants"}
+        }))
+        .await;
+        assert_eq!(reply["message_type"], "ack");
+        assert_eq!(reply["data"]["text"], "Water the plants");
+        let id = reply["data"]["id"].clone();
+
+        let reply = request(&mut socket, json!({
+            "type": "update", "request_id": "2", "id": id, "version": 1,
+            "changes": {"text": "Water the garden", "due_date": "2025-06-01"}
+        }))
+        .await;
+        assert_eq!(reply["message_type"], "ack");
+        assert_eq!(reply["data"]["text"], "Water the garden");
+        assert_eq!(reply["data"]["version"], 2);
+
+        let reply = request(&mut socket, json!({"type": "toggle", "request_id": "3", "id": id})).await;
+        assert_eq!(reply["data"]["completed"], true);
+
+        let reply = request(&mut socket, json!({"type": "delete", "request_id": "4", "id": id})).await;
+        assert_eq!(reply["message_type"], "ack");
+        assert!(store.get_todo_by_id(1, id.as_i64().unwrap() as i32).await.unwrap().is_none());
+
+        let reply = request(&mut socket, json!({"type": "ping", "request_id": "5"})).await;
+        assert_eq!(reply["message_type"], "pong");
+    }
+}
+
+#[tokio::test]
+async fn failed_commands_get_rest_style_errors() {
+    for store in stores().await {
+        let mut socket = connect(store).await;
+
+        let reply = request(&mut socket, json!({
+            "type": "create", "request_id": "bad-text", "todo": {"text": "  ", "assignee_id": 99}
+        }))
+        .await;
+        assert_eq!(reply["message_type"], "error");
+        assert_eq!(reply["error"]["code"], "validation_failed");
+        assert_eq!(reply["error"]["request_id"], "bad-text");
+        assert_eq!(reply["error"]["fields"].as_array().unwrap().len(), 2);
+
+        let reply = request(&mut socket, json!({
+            "type": "create", "request_id": "first", "todo": {"text": "Vacuum"}
+        }))
+        .await;
+        let id = reply["data"]["id"].clone();
+        request(&mut socket, json!({"type": "toggle", "request_id": "second", "id": id})).await;
+
+        let reply = request(&mut socket, json!({
+            "type": "toggle", "request_id": "stale", "id": id, "version": 1
+        }))
+        .await;
+        assert_eq!(reply["error"]["code"], "version_conflict");
+        assert_eq!(reply["error"]["current"]["version"], 2);
+
+        let reply = request(&mut socket, json!({"type": "delete", "request_id": "gone", "id": 999})).await;
+        assert_eq!(reply["error"]["code"], "not_found");
+
+        let reply = request(&mut socket, json!({"type": "launch", "request_id": "huh"})).await;
+        assert_eq!(reply["error"]["code"], "bad_request");
+    }
+}
+
+#[tokio::test]
+async fn subscribing_to_a_list_sends_its_snapshot() {
+    for store in stores().await {
+        let mut socket = connect(store).await;
+
+        let reply = request(&mut socket, json!({"type": "subscribe", "request_id": "sub", "list_id": 1})).await;
+        assert_eq!(reply["message_type"], "ack");
+
+        let Some(Ok(Message::Text(text))) = socket.next().await else {
+            panic!("no snapshot");
+        };
+        let snapshot: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(snapshot["message_type"], "snapshot");
+        assert_eq!(snapshot["list_id"], 1);
+        assert_eq!(snapshot["data"]["lists"][0]["name"], "General");
+
+        let reply = request(&mut socket, json!({"type": "subscribe", "request_id": "nope", "list_id": 42})).await;
+        assert_eq!(reply["error"]["code"], "not_found");
+    }
+}
+
+#[tokio::test]
+async fn signing_out_ends_the_socket() {
+    for store in stores().await {
+        let (router, mut socket, cookie) = serve(store).await;
+
+        let reply = request(&mut socket, json!({"type": "ping", "request_id": "before"})).await;
+        assert_eq!(reply["message_type"], "pong");
+
+        let logout = Request::builder()
+            .method(Method::POST)
+            .uri("/api/auth/logout")
+            .header(header::COOKIE, &cookie)
+            .body(Body::empty())
+            .unwrap();
+        router.oneshot(logout).await.unwrap();
+
+        let reply = request(&mut socket, json!({"type": "ping", "request_id": "after"})).await;
+        assert_eq!(reply["error"]["code"], "unauthorized");
+        assert!(matches!(socket.next().await, Some(Ok(Message::Close(Some(_))))));
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// How far through its checklist a todo is, e.g. 3 of 10 steps done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecklistProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl ChecklistProgress {
+    /// Counts the completion flags of a todo's checklist items.
+    pub fn of(completed: impl IntoIterator<Item = bool>) -> Self {
+        completed.into_iter().fold(Self::default(), |progress, completed| Self {
+            done: progress.done + usize::from(completed),
+            total: progress.total + 1,
+        })
+    }
+
+    /// True once every item is done. An empty checklist is never complete,
+    /// so it cannot complete its todo on its own.
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.done == self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checklist_progress() {
+        let progress = ChecklistProgress::of([true, false, true]);
+        assert_eq!(progress, ChecklistProgress { done: 2, total: 3 });
+        assert!(!progress.is_complete());
+
+        assert!(ChecklistProgress::of([true, true]).is_complete());
+        assert!(!ChecklistProgress::of([]).is_complete());
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Label of the group holding todos without a due date. It is always listed first.
+pub const NO_DUE_DATE_GROUP: &str = "No Due Date";
+
+/// The parts of a todo the sorting and grouping rules look at.
+pub trait SortableTodo {
+    fn id(&self) -> i64;
+    fn completed(&self) -> bool;
+    fn due_date(&self) -> Option<NaiveDate>;
+
+    /// The time on the due date it is due by, if any.
+    fn due_time(&self) -> Option<NaiveTime> {
+        None
+    }
+}
+
+impl<T: SortableTodo> SortableTodo for &T {
+    fn id(&self) -> i64 {
+        (**self).id()
+    }
+
+    fn completed(&self) -> bool {
+        (**self).completed()
+    }
+
+    fn due_date(&self) -> Option<NaiveDate> {
+        (**self).due_date()
+    }
+
+    fn due_time(&self) -> Option<NaiveTime> {
+        (**self).due_time()
+    }
+}
+
+/// Todos sharing a due date, keyed by the ISO date or [`NO_DUE_DATE_GROUP`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DateGroup<T> {
+    pub date: String,
+    pub todos: Vec<T>,
+}
+
+/// Incomplete todos come first, then todos are ordered by id.
+pub fn compare_todos<T: SortableTodo>(a: &T, b: &T) -> Ordering {
+    a.completed()
+        .cmp(&b.completed())
+        .then_with(|| a.id().cmp(&b.id()))
+}
+
+pub fn date_group_key(due_date: Option<NaiveDate>) -> String {
+    match due_date {
+        Some(date) => date.to_string(),
+        None => NO_DUE_DATE_GROUP.to_string(),
+    }
+}
+
+/// Groups todos by due date: the no-due-date group first, then dates in
+/// ascending order, each group sorted with [`compare_todos`].
+pub fn group_by_due_date<T: SortableTodo>(todos: impl IntoIterator<Item = T>) -> Vec<DateGroup<T>> {
+    let mut grouped: BTreeMap<Option<NaiveDate>, Vec<T>> = BTreeMap::new();
+
+    for todo in todos {
+        grouped.entry(todo.due_date()).or_default().push(todo);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(due_date, mut todos)| {
+            todos.sort_by(compare_todos);
+            DateGroup {
+                date: date_group_key(due_date),
+                todos,
+            }
+        })
+        .collect()
+}
+
+/// Where a todo's due date falls relative to now, in the order the buckets
+/// are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DueBucket {
+    /// Due before today, or earlier today when it has a due time.
+    Overdue,
+    Today,
+    Tomorrow,
+    /// Due later in today's Monday-to-Sunday week.
+    ThisWeek,
+    Later,
+    NoDueDate,
+    /// Done, whenever it was due.
+    Done,
+}
+
+impl DueBucket {
+    /// The bucket of a todo: [`DueBucket::Done`] once it is done, otherwise
+    /// [`DueBucket::for_due`].
+    pub fn for_todo(todo: &impl SortableTodo, now: NaiveDateTime) -> Self {
+        if todo.completed() {
+            DueBucket::Done
+        } else {
+            DueBucket::for_due(todo.due_date(), todo.due_time(), now)
+        }
+    }
+
+    /// Where a due date and time fall relative to `now`, regardless of
+    /// whether the todo is done. `now` is the wall-clock time in the
+    /// household's timezone.
+    pub fn for_due(due_date: Option<NaiveDate>, due_time: Option<NaiveTime>, now: NaiveDateTime) -> Self {
+        let Some(due_date) = due_date else {
+            return DueBucket::NoDueDate;
+        };
+        let today = now.date();
+        if due_date == today && due_time.is_some_and(|due_time| due_time < now.time()) {
+            return DueBucket::Overdue;
+        }
+        let days_left_in_week = u64::from(6 - today.weekday().num_days_from_monday());
+        let end_of_week = today.checked_add_days(Days::new(days_left_in_week)).unwrap_or(today);
+
+        if due_date < today {
+            DueBucket::Overdue
+        } else if due_date == today {
+            DueBucket::Today
+        } else if today.succ_opt() == Some(due_date) {
+            DueBucket::Tomorrow
+        } else if due_date <= end_of_week {
+            DueBucket::ThisWeek
+        } else {
+            DueBucket::Later
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DueBucket::Overdue => "Overdue",
+            DueBucket::Today => "Today",
+            DueBucket::Tomorrow => "Tomorrow",
+            DueBucket::ThisWeek => "This Week",
+            DueBucket::Later => "Later",
+            DueBucket::NoDueDate => NO_DUE_DATE_GROUP,
+            DueBucket::Done => "Done",
+        }
+    }
+}
+
+/// Todos sharing a [`DueBucket`], with the bucket's label for display.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketGroup<T> {
+    pub bucket: DueBucket,
+    pub label: String,
+    pub todos: Vec<T>,
+}
+
+/// Groups todos into [`DueBucket`]s relative to `now`, leaving out empty
+/// buckets. Within a bucket, incomplete todos come first, then todos are
+/// ordered by when they are due and by id.
+pub fn group_by_bucket<T: SortableTodo>(todos: impl IntoIterator<Item = T>, now: NaiveDateTime) -> Vec<BucketGroup<T>> {
+    let mut grouped: BTreeMap<DueBucket, Vec<T>> = BTreeMap::new();
+
+    for todo in todos {
+        grouped.entry(DueBucket::for_todo(&todo, now)).or_default().push(todo);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(bucket, mut todos)| {
+            todos.sort_by(|a, b| {
+                a.completed()
+                    .cmp(&b.completed())
+                    .then_with(|| a.due_date().cmp(&b.due_date()))
+                    .then_with(|| a.due_time().cmp(&b.due_time()))
+                    .then_with(|| a.id().cmp(&b.id()))
+            });
+            BucketGroup {
+                bucket,
+                label: bucket.label().to_string(),
+                todos,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item {
+        id: i64,
+        completed: bool,
+        due_date: Option<NaiveDate>,
+    }
+
+    impl SortableTodo for Item {
+        fn id(&self) -> i64 {
+            self.id
+        }
+
+        fn completed(&self) -> bool {
+            self.completed
+        }
+
+        fn due_date(&self) -> Option<NaiveDate> {
+            self.due_date
+        }
+    }
+
+    fn item(id: i64, completed: bool, due_date: Option<&str>) -> Item {
+        Item {
+            id,
+            completed,
+            due_date: due_date.map(|date| date.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_no_due_date_group_comes_first() {
+        let groups = group_by_due_date(vec![
+            item(1, false, Some("2024-01-02")),
+            item(2, false, None),
+            item(3, false, Some("2024-01-01")),
+        ]);
+
+        let keys: Vec<&str> = groups.iter().map(|group| group.date.as_str()).collect();
+        assert_eq!(keys, vec![NO_DUE_DATE_GROUP, "2024-01-01", "2024-01-02"]);
+    }
+
+    #[test]
+    fn test_incomplete_todos_sort_first_then_by_id() {
+        let groups = group_by_due_date(vec![
+            item(1, true, None),
+            item(3, false, None),
+            item(2, false, None),
+        ]);
+
+        let ids: Vec<i64> = groups[0].todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+    }
+
+    fn start_of(date: &str) -> NaiveDateTime {
+        date.parse::<NaiveDate>().unwrap().and_time(NaiveTime::MIN)
+    }
+
+    #[test]
+    fn test_buckets_are_relative_to_today() {
+        // A Thursday
+        let today = start_of("2024-03-07");
+        let bucket = |date: &str| DueBucket::for_due(Some(date.parse().unwrap()), None, today);
+
+        assert_eq!(bucket("2024-03-06"), DueBucket::Overdue);
+        assert_eq!(bucket("2024-03-07"), DueBucket::Today);
+        assert_eq!(bucket("2024-03-08"), DueBucket::Tomorrow);
+        assert_eq!(bucket("2024-03-10"), DueBucket::ThisWeek);
+        assert_eq!(bucket("2024-03-11"), DueBucket::Later);
+        assert_eq!(DueBucket::for_due(None, None, today), DueBucket::NoDueDate);
+
+        // On a Sunday the week is over once tomorrow comes
+        let sunday = start_of("2024-03-10");
+        let monday = DueBucket::for_due(Some("2024-03-11".parse().unwrap()), None, sunday);
+        let tuesday = DueBucket::for_due(Some("2024-03-12".parse().unwrap()), None, sunday);
+        assert_eq!((monday, tuesday), (DueBucket::Tomorrow, DueBucket::Later));
+    }
+
+    #[test]
+    fn test_todos_due_earlier_today_are_overdue() {
+        let now: NaiveDateTime = "2024-03-07T14:30:00".parse().unwrap();
+        let today = Some(now.date());
+        let at = |time: &str| Some(time.parse::<NaiveTime>().unwrap());
+
+        assert_eq!(DueBucket::for_due(today, at("09:00:00"), now), DueBucket::Overdue);
+        assert_eq!(DueBucket::for_due(today, at("18:00:00"), now), DueBucket::Today);
+        assert_eq!(DueBucket::for_due(today, None, now), DueBucket::Today);
+    }
+
+    #[test]
+    fn test_buckets_are_listed_in_order_with_open_todos_first() {
+        let groups = group_by_bucket(
+            vec![
+                item(1, false, None),
+                item(2, true, Some("2024-03-01")),
+                item(3, false, Some("2024-03-20")),
+                item(4, false, Some("2024-03-05")),
+                item(5, false, Some("2024-03-07")),
+                item(6, true, Some("2024-03-20")),
+            ],
+            start_of("2024-03-07"),
+        );
+
+        // Done todos are listed last, whenever they were due
+        let labels: Vec<&str> = groups.iter().map(|group| group.label.as_str()).collect();
+        assert_eq!(labels, vec!["Overdue", "Today", "Later", NO_DUE_DATE_GROUP, "Done"]);
+        let overdue: Vec<i64> = groups[0].todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(overdue, vec![4]);
+        let done: Vec<i64> = groups[4].todos.iter().map(|todo| todo.id).collect();
+        assert_eq!(done, vec![2, 6]);
+    }
+}
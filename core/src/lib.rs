@@ -0,0 +1,20 @@
+//! Domain model and rules shared by the WASM `TodoApp` and the axum backend,
+//! so that both sort, group and validate todos the same way.
+
+pub mod checklist;
+pub mod grouping;
+pub mod member;
+pub mod recurrence;
+pub mod rotation;
+pub mod search;
+pub mod timezone;
+pub mod validation;
+
+pub use checklist::ChecklistProgress;
+pub use grouping::{group_by_bucket, group_by_due_date, BucketGroup, DateGroup, DueBucket, SortableTodo, NO_DUE_DATE_GROUP};
+pub use member::{initials_for, DEFAULT_MEMBER_COLOR};
+pub use recurrence::Recurrence;
+pub use rotation::next_in_rotation;
+pub use search::{match_fields, search_terms, SearchMatch};
+pub use timezone::{now_in, parse_timezone, today_in, Tz};
+pub use validation::ValidationError;
@@ -0,0 +1,27 @@
+/// Colour given to members created without one.
+pub const DEFAULT_MEMBER_COLOR: &str = "#6c757d";
+
+/// Derives avatar initials from a display name, e.g. "Grandma Sue" -> "GS".
+pub fn initials_for(display_name: &str) -> String {
+    display_name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        // Some letters uppercase to more than one, as 'ß' does to "SS"
+        .take(3)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initials_for() {
+        assert_eq!(initials_for("Grandma Sue"), "GS");
+        assert_eq!(initials_for("joe"), "J");
+        assert_eq!(initials_for("Mary Jo Smith"), "MJ");
+        assert_eq!(initials_for("ßa ßb"), "SSS");
+    }
+}
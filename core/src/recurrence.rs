@@ -0,0 +1,124 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::validation::ValidationError;
+
+/// How often a recurring todo repeats, e.g. `{"freq": "weekly", "weekdays": ["Tue"]}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "freq", rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+    Weekly {
+        weekdays: Vec<Weekday>,
+    },
+    /// Days past the end of a short month fall on its last day.
+    Monthly {
+        day: u32,
+    },
+    EveryNDays {
+        interval: u32,
+    },
+}
+
+impl Recurrence {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let message = match self {
+            Recurrence::Weekly { weekdays } if weekdays.is_empty() => {
+                "weekly recurrence needs at least one weekday"
+            }
+            Recurrence::Monthly { day } if !(1..=31).contains(day) => {
+                "monthly recurrence day must be between 1 and 31"
+            }
+            Recurrence::EveryNDays { interval } if !(1..=366).contains(interval) => {
+                "recurrence interval must be between 1 and 366 days"
+            }
+            _ => return Ok(()),
+        };
+        Err(ValidationError::new("recurrence", message))
+    }
+
+    /// Returns the first occurrence strictly after `date`.
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily => date.checked_add_days(Days::new(1)),
+            Recurrence::Weekly { weekdays } => (1..=7)
+                .filter_map(|offset| date.checked_add_days(Days::new(offset)))
+                .find(|candidate| weekdays.contains(&candidate.weekday())),
+            Recurrence::Monthly { day } => {
+                let this_month = Self::day_in_month(date, *day)?;
+                if this_month > date {
+                    Some(this_month)
+                } else {
+                    let next_month = date.with_day(1)?.checked_add_months(Months::new(1))?;
+                    Self::day_in_month(next_month, *day)
+                }
+            }
+            Recurrence::EveryNDays { interval } => {
+                date.checked_add_days(Days::new(u64::from(*interval)))
+            }
+        }
+    }
+
+    /// Lists the next `count` occurrences after `date`.
+    pub fn occurrences_after(&self, date: NaiveDate, count: usize) -> Vec<NaiveDate> {
+        std::iter::successors(self.next_after(date), |previous| self.next_after(*previous))
+            .take(count)
+            .collect()
+    }
+
+    fn day_in_month(date: NaiveDate, day: u32) -> Option<NaiveDate> {
+        let first = date.with_day(1)?;
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        first.with_day(day.min(last.day()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_weekly_picks_next_listed_weekday() {
+        let rule = Recurrence::Weekly {
+            weekdays: vec![Weekday::Tue, Weekday::Fri],
+        };
+        // 2024-01-02 is a Tuesday
+        assert_eq!(
+            rule.next_after(date("2024-01-02")),
+            Some(date("2024-01-05"))
+        );
+        assert_eq!(
+            rule.next_after(date("2024-01-05")),
+            Some(date("2024-01-09"))
+        );
+    }
+
+    #[test]
+    fn test_monthly_clamps_to_end_of_short_month() {
+        let rule = Recurrence::Monthly { day: 31 };
+        assert_eq!(
+            rule.occurrences_after(date("2024-01-31"), 3),
+            vec![date("2024-02-29"), date("2024-03-31"), date("2024-04-30")]
+        );
+    }
+
+    #[test]
+    fn test_every_n_days() {
+        let rule = Recurrence::EveryNDays { interval: 3 };
+        assert_eq!(
+            rule.next_after(date("2024-12-30")),
+            Some(date("2025-01-02"))
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_weekdays() {
+        assert!(Recurrence::Weekly { weekdays: vec![] }.validate().is_err());
+        assert!(Recurrence::Monthly { day: 0 }.validate().is_err());
+        assert!(Recurrence::Daily.validate().is_ok());
+    }
+}
@@ -0,0 +1,40 @@
+/// Picks who a chore rotation hands the next occurrence to: the member after
+/// `current` in `order`, wrapping around, or the first member when `current`
+/// is not part of the rotation.
+///
+/// Members for whom `is_away` holds are passed over. If everyone is away the
+/// turn goes to the next member regardless, so the chore is never left
+/// without an assignee.
+pub fn next_in_rotation(order: &[i32], current: Option<i32>, is_away: impl Fn(i32) -> bool) -> Option<i32> {
+    let start = current
+        .and_then(|current| order.iter().position(|&id| id == current))
+        .map_or(0, |index| index + 1);
+    let mut turns = order.iter().cycle().skip(start).take(order.len());
+
+    turns
+        .clone()
+        .find(|&&id| !is_away(id))
+        .or_else(|| turns.next())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_in_rotation() {
+        let order = [3, 1, 2];
+        let nobody_away = |_| false;
+
+        assert_eq!(next_in_rotation(&order, Some(3), nobody_away), Some(1));
+        assert_eq!(next_in_rotation(&order, Some(2), nobody_away), Some(3));
+        assert_eq!(next_in_rotation(&order, None, nobody_away), Some(3));
+        assert_eq!(next_in_rotation(&order, Some(9), nobody_away), Some(3));
+        assert_eq!(next_in_rotation(&[], Some(3), nobody_away), None);
+
+        assert_eq!(next_in_rotation(&order, Some(3), |id| id == 1), Some(2));
+        assert_eq!(next_in_rotation(&order, Some(3), |id| id != 3), Some(3));
+        assert_eq!(next_in_rotation(&order, Some(3), |_| true), Some(1));
+    }
+}
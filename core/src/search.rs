@@ -0,0 +1,207 @@
+//! Simple fuzzy search, for where Postgres full-text search is not at hand:
+//! the WASM app offline and the SQLite and in-memory stores.
+
+use serde::Serialize;
+
+/// Wrapped around matched words in snippets, as Postgres is asked to do too.
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+
+/// Words of context shown before the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 3;
+
+/// Longest snippet, in words.
+const SNIPPET_WORDS: usize = 12;
+
+/// How well a todo matched and the part of it that did, with the matched
+/// words highlighted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchMatch {
+    pub score: f64,
+    pub snippet: String,
+}
+
+/// The words of a search, lowercased. Anything but letters and digits
+/// separates words.
+pub fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Matches `terms` against a todo's `fields`, its text first, then anything
+/// else worth searching such as checklist steps and comments, which count
+/// for half as much.
+///
+/// Every term has to match some word: exactly, as the start of the word, or
+/// with a typo (two in terms of eight letters or more).
+pub fn match_fields(terms: &[String], fields: &[&str]) -> Option<SearchMatch> {
+    if terms.is_empty() {
+        return None;
+    }
+
+    let spans: Vec<Vec<(usize, usize)>> = fields.iter().map(|field| word_spans(field)).collect();
+    let mut highlighted: Vec<Vec<bool>> = spans.iter().map(|words| vec![false; words.len()]).collect();
+    let mut score = 0.0;
+
+    for term in terms {
+        let mut best: Option<f64> = None;
+        for (index, (field, words)) in fields.iter().zip(&spans).enumerate() {
+            let weight = if index == 0 { 1.0 } else { 0.5 };
+            for (word_index, &(start, end)) in words.iter().enumerate() {
+                let Some(word_score) = word_score(term, &field[start..end].to_lowercase()) else {
+                    continue;
+                };
+                highlighted[index][word_index] = true;
+                best = Some(best.unwrap_or_default().max(word_score * weight));
+            }
+        }
+        score += best?;
+    }
+
+    let index = highlighted.iter().position(|words| words.contains(&true))?;
+    Some(SearchMatch {
+        score,
+        snippet: snippet(fields[index], &spans[index], &highlighted[index]),
+    })
+}
+
+fn word_score(term: &str, word: &str) -> Option<f64> {
+    if word == term {
+        return Some(1.0);
+    }
+    if word.starts_with(term) {
+        return Some(0.75);
+    }
+
+    let length = term.chars().count();
+    let typos = match length {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let prefix: String = word.chars().take(length).collect();
+    if edit_distance(term, word) <= typos || edit_distance(term, &prefix) <= typos {
+        return Some(0.5);
+    }
+    None
+}
+
+/// Levenshtein distance, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Byte ranges of the words in `text`.
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (index, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(from)) => {
+                spans.push((from, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        spans.push((from, text.len()));
+    }
+    spans
+}
+
+/// A window of `text` around its first highlighted word.
+fn snippet(text: &str, spans: &[(usize, usize)], highlighted: &[bool]) -> String {
+    let first = highlighted.iter().position(|&matched| matched).unwrap_or_default();
+    let from = first.saturating_sub(SNIPPET_CONTEXT);
+    let to = (from + SNIPPET_WORDS).min(spans.len());
+
+    let mut snippet = String::new();
+    let mut position = 0;
+    if from > 0 {
+        snippet.push('…');
+        position = spans[from].0;
+    }
+    for (&(start, end), &matched) in spans.iter().zip(highlighted).take(to).skip(from) {
+        snippet.push_str(&text[position..start]);
+        if matched {
+            snippet.push_str(HIGHLIGHT_START);
+            snippet.push_str(&text[start..end]);
+            snippet.push_str(HIGHLIGHT_END);
+        } else {
+            snippet.push_str(&text[start..end]);
+        }
+        position = end;
+    }
+    if to < spans.len() {
+        snippet.push('…');
+    } else {
+        snippet.push_str(&text[position..]);
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(query: &str, fields: &[&str]) -> Option<SearchMatch> {
+        match_fields(&search_terms(query), fields)
+    }
+
+    #[test]
+    fn test_search_terms_split_on_punctuation() {
+        assert_eq!(search_terms("  Dentist, 3pm!"), vec!["dentist", "3pm"]);
+        assert!(search_terms("-- ").is_empty());
+    }
+
+    #[test]
+    fn test_matches_whole_words_prefixes_and_typos() {
+        let exact = search("dentist", &["Call the dentist"]).unwrap();
+        let prefix = search("dent", &["Call the dentist"]).unwrap();
+        let typo = search("dentst", &["Call the dentist"]).unwrap();
+        assert!(exact.score > prefix.score && prefix.score > typo.score);
+        assert_eq!(exact.snippet, "Call the <mark>dentist</mark>");
+
+        // Short terms must match exactly or as a prefix
+        assert!(search("cal", &["Call the dentist"]).is_some());
+        assert!(search("cel", &["Call the dentist"]).is_none());
+        // Every term has to match
+        assert!(search("dentist tuesday", &["Call the dentist"]).is_none());
+    }
+
+    #[test]
+    fn test_other_fields_count_for_less_and_are_quoted() {
+        let in_text = search("dentist", &["Dentist", "checkup"]).unwrap();
+        let in_comment = search("dentist", &["Checkup", "Booked with the dentist on Main St"]).unwrap();
+        assert!(in_text.score > in_comment.score);
+        assert_eq!(in_comment.snippet, "Booked with the <mark>dentist</mark> on Main St");
+    }
+
+    #[test]
+    fn test_long_fields_are_cut_around_the_match() {
+        let text = "one two three four five six seven eight nine ten dentist twelve thirteen \
+                    fourteen fifteen sixteen seventeen eighteen nineteen twenty";
+        let found = search("dentist", &[text]).unwrap();
+        assert_eq!(
+            found.snippet,
+            "…eight nine ten <mark>dentist</mark> twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen…"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Households name their timezone the IANA way (`Europe/London`), so that
+//! "today" is the same day on the server and on every device.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+pub use chrono_tz::Tz;
+
+use crate::validation::ValidationError;
+
+pub fn parse_timezone(name: &str) -> Result<Tz, ValidationError> {
+    name.trim()
+        .parse()
+        .map_err(|_| ValidationError::new("timezone", "must be an IANA timezone such as Europe/London"))
+}
+
+/// The date it is in `timezone` at `now`.
+pub fn today_in(timezone: Tz, now: DateTime<Utc>) -> NaiveDate {
+    now_in(timezone, now).date()
+}
+
+/// The wall-clock time in `timezone` at `now`, which due dates and times
+/// are compared against.
+pub fn now_in(timezone: Tz, now: DateTime<Utc>) -> NaiveDateTime {
+    now.with_timezone(&timezone).naive_local()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_today_depends_on_the_timezone() {
+        let now: DateTime<Utc> = "2024-03-01T23:30:00Z".parse().unwrap();
+        assert_eq!(today_in(parse_timezone("UTC").unwrap(), now).to_string(), "2024-03-01");
+        assert_eq!(today_in(parse_timezone("Europe/Berlin").unwrap(), now).to_string(), "2024-03-02");
+        assert_eq!(today_in(parse_timezone(" America/New_York ").unwrap(), now).to_string(), "2024-03-01");
+        assert_eq!(now_in(parse_timezone("Asia/Kolkata").unwrap(), now).to_string(), "2024-03-02 05:00:00");
+        assert_eq!(parse_timezone("Mars/Olympus").unwrap_err().field, "timezone");
+    }
+}
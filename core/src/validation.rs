@@ -0,0 +1,330 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::Serialize;
+use std::fmt;
+
+use crate::recurrence::Recurrence;
+
+/// Longest display name or list name accepted.
+pub const MAX_NAME_LENGTH: usize = 50;
+
+/// Most points a single todo can be worth.
+pub const MAX_TODO_POINTS: i32 = 1000;
+
+/// Most points a reward can cost.
+pub const MAX_REWARD_COST: i32 = 100_000;
+
+/// Longest comment that can be left on a todo.
+pub const MAX_COMMENT_LENGTH: usize = 2000;
+
+/// Most members a chore rotation can cycle through.
+pub const MAX_ROTATION_MEMBERS: usize = 20;
+
+/// Most tags a single todo can carry.
+pub const MAX_TAGS: usize = 10;
+
+/// Longest tag accepted.
+pub const MAX_TAG_LENGTH: usize = 30;
+
+/// Shortest password accepted for an account.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Why a single field of a request was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+pub fn validate_todo_text(text: &str) -> Result<(), ValidationError> {
+    if text.trim().is_empty() {
+        return Err(ValidationError::new("text", "must not be empty"));
+    }
+    Ok(())
+}
+
+pub fn validate_comment_text(text: &str) -> Result<(), ValidationError> {
+    if text.trim().is_empty() {
+        return Err(ValidationError::new("text", "must not be empty"));
+    }
+    if text.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(ValidationError::new(
+            "text",
+            format!("must be at most {} characters", MAX_COMMENT_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+/// Tags are matched exactly, so they are trimmed, lowercased, sorted and
+/// listed once before being validated and stored.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.len() > MAX_TAGS {
+        return Err(ValidationError::new(
+            "tags",
+            format!("cannot list more than {} tags", MAX_TAGS),
+        ));
+    }
+    if tags.iter().any(|tag| tag.trim().is_empty()) {
+        return Err(ValidationError::new("tags", "must not contain empty tags"));
+    }
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+        return Err(ValidationError::new(
+            "tags",
+            format!("must be at most {} characters each", MAX_TAG_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+/// Recurring todos repeat from their due date, so they must have one.
+pub fn validate_recurrence(
+    recurrence: Option<&Recurrence>,
+    due_date: Option<NaiveDate>,
+) -> Result<(), ValidationError> {
+    match recurrence {
+        Some(_) if due_date.is_none() => Err(ValidationError::new(
+            "due_date",
+            "is required for recurring todos",
+        )),
+        Some(rule) => rule.validate(),
+        None => Ok(()),
+    }
+}
+
+/// A due time is a time on the due date, so it needs one.
+pub fn validate_due_time(due_time: Option<NaiveTime>, due_date: Option<NaiveDate>) -> Result<(), ValidationError> {
+    if due_time.is_some() && due_date.is_none() {
+        return Err(ValidationError::new("due_time", "needs a due_date"));
+    }
+    Ok(())
+}
+
+pub fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    crate::timezone::parse_timezone(timezone).map(|_| ())
+}
+
+pub fn validate_points(points: i32) -> Result<(), ValidationError> {
+    if !(0..=MAX_TODO_POINTS).contains(&points) {
+        return Err(ValidationError::new(
+            "points",
+            format!("must be between 0 and {}", MAX_TODO_POINTS),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_reward_cost(cost: i32) -> Result<(), ValidationError> {
+    if !(1..=MAX_REWARD_COST).contains(&cost) {
+        return Err(ValidationError::new(
+            "cost",
+            format!("must be between 1 and {}", MAX_REWARD_COST),
+        ));
+    }
+    Ok(())
+}
+
+/// A rotation needs someone to hand chores to and names each member once.
+pub fn validate_rotation_members(member_ids: &[i32]) -> Result<(), ValidationError> {
+    if member_ids.is_empty() {
+        return Err(ValidationError::new("member_ids", "cannot be empty"));
+    }
+    if member_ids.len() > MAX_ROTATION_MEMBERS {
+        return Err(ValidationError::new(
+            "member_ids",
+            format!("cannot list more than {} members", MAX_ROTATION_MEMBERS),
+        ));
+    }
+    if member_ids.iter().enumerate().any(|(i, id)| member_ids[..i].contains(id)) {
+        return Err(ValidationError::new("member_ids", "cannot list a member twice"));
+    }
+    Ok(())
+}
+
+pub fn validate_display_name(name: &str) -> Result<(), ValidationError> {
+    validate_name("display_name", name)
+}
+
+pub fn validate_list_name(name: &str) -> Result<(), ValidationError> {
+    validate_name("name", name)
+}
+
+pub fn validate_reward_name(name: &str) -> Result<(), ValidationError> {
+    validate_name("name", name)
+}
+
+pub fn validate_household_name(name: &str) -> Result<(), ValidationError> {
+    validate_name("household_name", name)
+}
+
+/// A loose sanity check; the address is never mailed, only used to sign in.
+pub fn validate_email(email: &str) -> Result<(), ValidationError> {
+    let looks_valid = email.len() <= 254
+        && !email.chars().any(char::is_whitespace)
+        && email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+    if !looks_valid {
+        return Err(ValidationError::new("email", "must be an email address"));
+    }
+    Ok(())
+}
+
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ValidationError::new(
+            "password",
+            format!("must be at least {} characters", MIN_PASSWORD_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+/// Colours are `#rrggbb` hex strings.
+pub fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let is_hex = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !is_hex {
+        return Err(ValidationError::new("color", "must look like #rrggbb"));
+    }
+    Ok(())
+}
+
+pub fn validate_initials(initials: &str) -> Result<(), ValidationError> {
+    let count = initials.chars().count();
+    if !(1..=3).contains(&count) || initials.chars().any(char::is_whitespace) {
+        return Err(ValidationError::new(
+            "initials",
+            "must be 1 to 3 characters without spaces",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_name(field: &'static str, name: &str) -> Result<(), ValidationError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ValidationError::new(field, "must not be empty"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(ValidationError::new(
+            field,
+            format!("must be at most {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_todo_text() {
+        assert!(validate_todo_text("Buy milk").is_ok());
+        assert_eq!(validate_todo_text("   ").unwrap_err().field, "text");
+    }
+
+    #[test]
+    fn test_validate_color() {
+        assert!(validate_color("#1a2B3c").is_ok());
+        assert!(validate_color("red").is_err());
+        assert!(validate_color("#12345g").is_err());
+    }
+
+    #[test]
+    fn test_recurrence_requires_due_date() {
+        let error = validate_recurrence(Some(&Recurrence::Daily), None).unwrap_err();
+        assert_eq!(error.field, "due_date");
+        assert!(validate_recurrence(None, None).is_ok());
+    }
+
+    #[test]
+    fn test_due_time_requires_due_date() {
+        let nine = NaiveTime::from_hms_opt(9, 0, 0);
+        assert_eq!(validate_due_time(nine, None).unwrap_err().field, "due_time");
+        assert!(validate_due_time(nine, NaiveDate::from_ymd_opt(2024, 3, 1)).is_ok());
+        assert!(validate_due_time(None, None).is_ok());
+    }
+
+    #[test]
+    fn test_validate_points_and_costs() {
+        assert!(validate_points(0).is_ok());
+        assert!(validate_points(MAX_TODO_POINTS).is_ok());
+        assert_eq!(validate_points(-1).unwrap_err().field, "points");
+        assert!(validate_reward_cost(0).is_err());
+        assert!(validate_reward_cost(50).is_ok());
+    }
+
+    #[test]
+    fn test_validate_comment_text() {
+        assert!(validate_comment_text("bought the wrong size, returning it").is_ok());
+        assert!(validate_comment_text(" ").is_err());
+        assert!(validate_comment_text(&"a".repeat(MAX_COMMENT_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_rotation_members() {
+        assert!(validate_rotation_members(&[1, 2, 3]).is_ok());
+        assert!(validate_rotation_members(&[]).is_err());
+        assert!(validate_rotation_members(&[1, 2, 1]).is_err());
+    }
+
+    #[test]
+    fn test_tags_are_normalized_and_validated() {
+        let tags = normalize_tags(&["School ".to_string(), "chores".to_string(), "school".to_string()]);
+        assert_eq!(tags, vec!["chores", "school"]);
+        assert!(validate_tags(&tags).is_ok());
+        assert!(validate_tags(&["".to_string()]).is_err());
+        assert!(validate_tags(&["x".repeat(MAX_TAG_LENGTH + 1)]).is_err());
+        let too_many: Vec<String> = (0..=MAX_TAGS).map(|i| i.to_string()).collect();
+        assert_eq!(validate_tags(&too_many).unwrap_err().field, "tags");
+    }
+
+    #[test]
+    fn test_validate_email() {
+        assert!(validate_email("sam@example.com").is_ok());
+        assert!(validate_email("sam").is_err());
+        assert!(validate_email("@example.com").is_err());
+        assert!(validate_email("sam @example.com").is_err());
+    }
+
+    #[test]
+    fn test_validate_password() {
+        assert!(validate_password("correct horse").is_ok());
+        assert_eq!(validate_password("short").unwrap_err().field, "password");
+    }
+
+    #[test]
+    fn test_validate_name_length() {
+        assert!(validate_display_name(&"x".repeat(MAX_NAME_LENGTH)).is_ok());
+        assert!(validate_list_name(&"x".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+}
@@ -73,7 +73,7 @@ db() {
 
 migrate() {
     print_info "Running database migrations..."
-    docker compose -f docker-compose.dev.yml exec backend sqlx migrate run
+    docker compose -f docker-compose.dev.yml exec backend sqlx migrate run --source backend/migrations
     print_success "Migrations complete!"
 }
 
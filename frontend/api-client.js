@@ -1,79 +1,354 @@
 // API Client for Backend Communication
 
+// Error returned by the backend: `code` is stable, `fields` lists
+// per-field validation problems, `current` holds the server's copy of a todo
+// after a `version_conflict` and `requestId` matches the server logs.
+class ApiError extends Error {
+    constructor(status, body, fallbackMessage) {
+        super(body?.error || fallbackMessage);
+        this.name = 'ApiError';
+        this.status = status;
+        this.code = body?.code || null;
+        this.fields = body?.fields || [];
+        this.current = body?.current || null;
+        this.requestId = body?.request_id || null;
+    }
+}
+
 class TodoApiClient {
     constructor(baseUrl = 'http://localhost:3000') {
         this.baseUrl = baseUrl;
         this.websocket = null;
         this.eventListeners = new Map();
+        // Sequence number of the last event received, so a reconnect can
+        // ask for just the events missed in between
+        this.lastSeq = null;
+        // Commands sent over the WebSocket that await their reply, by request id
+        this.pendingCommands = new Map();
+        this.nextRequestId = 0;
+        // Cleared by disconnectWebSocket so a deliberate close stays closed
+        this.reconnect = false;
     }
 
-    // REST API Methods
-    async getTodos() {
-        const response = await fetch(`${this.baseUrl}/api/todos`);
-        if (!response.ok) {
-            throw new Error(`Failed to fetch todos: ${response.statusText}`);
+    // Sends the session cookie along, even when the API is on another origin
+    fetch(url, options = {}) {
+        return fetch(url, { credentials: 'include', ...options });
+    }
+
+    async ensureOk(response, action) {
+        if (response.ok) {
+            return;
         }
+        const body = await response.json().catch(() => null);
+        throw new ApiError(response.status, body, `${action}: ${response.statusText}`);
+    }
+
+    // Accounts. Each of these (bar logout) resolves with `{ user, household }`;
+    // the session itself lives in an HttpOnly cookie the browser keeps.
+    async register(email, password, householdName = null) {
+        const response = await this.fetch(`${this.baseUrl}/api/auth/register`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({ email, password, household_name: householdName }),
+        });
+
+        await this.ensureOk(response, 'Failed to register');
         return response.json();
     }
 
-    async createTodo(text, assignee, dueDate) {
-        const response = await fetch(`${this.baseUrl}/api/todos`, {
+    async login(email, password) {
+        const response = await this.fetch(`${this.baseUrl}/api/auth/login`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({ email, password }),
+        });
+
+        await this.ensureOk(response, 'Failed to sign in');
+        return response.json();
+    }
+
+    async logout() {
+        this.disconnectWebSocket();
+        const response = await this.fetch(`${this.baseUrl}/api/auth/logout`, {
+            method: 'POST',
+        });
+
+        await this.ensureOk(response, 'Failed to sign out');
+        return true;
+    }
+
+    // Rejects with an ApiError whose code is `unauthorized` when signed out
+    async getAccount() {
+        const response = await this.fetch(`${this.baseUrl}/api/auth/me`);
+        await this.ensureOk(response, 'Failed to fetch account');
+        return response.json();
+    }
+
+    // REST API Methods
+    // Filters are sent as query parameters, e.g. { assignee: 'unassigned', completed: false }
+    async getTodos(filters = {}) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos${this.queryString(filters)}`);
+        await this.ensureOk(response, 'Failed to fetch todos');
+        return response.json();
+    }
+
+    async getListTodos(listId, filters = {}) {
+        const response = await this.fetch(`${this.baseUrl}/api/lists/${listId}/todos${this.queryString(filters)}`);
+        await this.ensureOk(response, 'Failed to fetch todos');
+        return response.json();
+    }
+
+    queryString(params) {
+        const query = new URLSearchParams();
+        for (const [key, value] of Object.entries(params)) {
+            if (value !== null && value !== undefined && value !== '') {
+                query.append(key, value);
+            }
+        }
+        const text = query.toString();
+        return text ? `?${text}` : '';
+    }
+
+    async createTodo(text, assigneeId, dueDate, recurrence = null, listId = null) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos`, {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
             },
             body: JSON.stringify({
+                list_id: listId,
                 text,
-                assignee,
+                assignee_id: assigneeId,
                 due_date: dueDate || null,
+                recurrence,
             }),
         });
         
-        if (!response.ok) {
-            throw new Error(`Failed to create todo: ${response.statusText}`);
-        }
+        await this.ensureOk(response, 'Failed to create todo');
+        return response.json();
+    }
+
+    // Sends only the fields present in `updates` (PATCH); an empty `date`
+    // clears the due date. Pass the version the edit was based on to have the
+    // server reject it with a `version_conflict` when someone else changed
+    // the todo meanwhile.
+    async updateTodo(id, updates, version = null) {
+        const body = {};
+        if (updates.listId !== undefined) body.list_id = updates.listId;
+        if (updates.text !== undefined) body.text = updates.text;
+        if (updates.assigneeId !== undefined) body.assignee_id = updates.assigneeId;
+        if (updates.date !== undefined) body.due_date = updates.date || null;
+        if (updates.completed !== undefined) body.completed = updates.completed;
+        if (updates.recurrence !== undefined) body.recurrence = updates.recurrence;
+
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${id}`, {
+            method: 'PATCH',
+            headers: {
+                'Content-Type': 'application/json',
+                ...this.ifMatch(version),
+            },
+            body: JSON.stringify(body),
+        });
+        
+        await this.ensureOk(response, 'Failed to update todo');
         return response.json();
     }
 
-    async updateTodo(id, updates) {
-        const response = await fetch(`${this.baseUrl}/api/todos/${id}`, {
+    async toggleTodo(id, version = null) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${id}/toggle`, {
             method: 'PUT',
+            headers: this.ifMatch(version),
+        });
+        
+        await this.ensureOk(response, 'Failed to toggle todo');
+        return response.json();
+    }
+
+    // Parents settle a child's request to have a todo counted as done
+    async approveTodo(id, version = null) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${id}/approve`, {
+            method: 'POST',
+            headers: this.ifMatch(version),
+        });
+
+        await this.ensureOk(response, 'Failed to approve todo');
+        return response.json();
+    }
+
+    async rejectTodo(id, version = null) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${id}/reject`, {
+            method: 'POST',
+            headers: this.ifMatch(version),
+        });
+
+        await this.ensureOk(response, 'Failed to reject todo');
+        return response.json();
+    }
+
+    ifMatch(version) {
+        return version == null ? {} : { 'If-Match': `"${version}"` };
+    }
+
+    async getUpcomingOccurrences(id, count = 5) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${id}/occurrences?count=${count}`);
+        await this.ensureOk(response, 'Failed to fetch occurrences');
+        return response.json();
+    }
+
+    async deleteTodo(id) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${id}`, {
+            method: 'DELETE',
+        });
+        
+        await this.ensureOk(response, 'Failed to delete todo');
+        return true;
+    }
+
+    async search(query, limit = 20) {
+        const response = await this.fetch(`${this.baseUrl}/api/search${this.queryString({ q: query, limit })}`);
+        await this.ensureOk(response, 'Failed to search todos');
+        return response.json();
+    }
+
+    async getTrash() {
+        const response = await this.fetch(`${this.baseUrl}/api/trash`);
+        await this.ensureOk(response, 'Failed to fetch trash');
+        return response.json();
+    }
+
+    async restoreTodo(id) {
+        const response = await this.fetch(`${this.baseUrl}/api/trash/${id}/restore`, {
+            method: 'POST',
+        });
+
+        await this.ensureOk(response, 'Failed to restore todo');
+        return response.json();
+    }
+
+    async getComments(todoId) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${todoId}/comments`);
+        await this.ensureOk(response, 'Failed to fetch comments');
+        return response.json();
+    }
+
+    async addComment(todoId, text) {
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${todoId}/comments`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({ text }),
+        });
+
+        await this.ensureOk(response, 'Failed to add comment');
+        return response.json();
+    }
+
+    // `file` is a File or Blob, e.g. from an <input type="file">
+    async uploadAttachment(todoId, file) {
+        const body = new FormData();
+        body.append('file', file);
+        const response = await this.fetch(`${this.baseUrl}/api/todos/${todoId}/attachments`, {
+            method: 'POST',
+            body,
+        });
+
+        await this.ensureOk(response, 'Failed to upload attachment');
+        return response.json();
+    }
+
+    attachmentUrl(attachment, { thumbnail = false } = {}) {
+        const url = `${this.baseUrl}/api/attachments/${attachment.id}`;
+        return thumbnail ? `${url}/thumbnail` : url;
+    }
+
+    async deleteAttachment(attachmentId) {
+        const response = await this.fetch(`${this.baseUrl}/api/attachments/${attachmentId}`, {
+            method: 'DELETE',
+        });
+
+        await this.ensureOk(response, 'Failed to delete attachment');
+        return true;
+    }
+
+    async getLists() {
+        const response = await this.fetch(`${this.baseUrl}/api/lists`);
+        await this.ensureOk(response, 'Failed to fetch lists');
+        return response.json();
+    }
+
+    async createList(name) {
+        const response = await this.fetch(`${this.baseUrl}/api/lists`, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({ name }),
+        });
+        
+        await this.ensureOk(response, 'Failed to create list');
+        return response.json();
+    }
+
+    async deleteList(id) {
+        const response = await this.fetch(`${this.baseUrl}/api/lists/${id}`, {
+            method: 'DELETE',
+        });
+        
+        await this.ensureOk(response, 'Failed to delete list');
+        return true;
+    }
+
+    async getMembers() {
+        const response = await this.fetch(`${this.baseUrl}/api/members`);
+        await this.ensureOk(response, 'Failed to fetch members');
+        return response.json();
+    }
+
+    async createMember(displayName, color, initials) {
+        const response = await this.fetch(`${this.baseUrl}/api/members`, {
+            method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
             },
             body: JSON.stringify({
-                text: updates.text,
-                assignee: updates.assignee,
-                due_date: updates.date || null,
-                completed: updates.completed,
+                display_name: displayName,
+                color: color || null,
+                initials: initials || null,
             }),
         });
         
-        if (!response.ok) {
-            throw new Error(`Failed to update todo: ${response.statusText}`);
-        }
+        await this.ensureOk(response, 'Failed to create member');
         return response.json();
     }
 
-    async toggleTodo(id) {
-        const response = await fetch(`${this.baseUrl}/api/todos/${id}/toggle`, {
+    async updateMember(id, updates) {
+        const response = await this.fetch(`${this.baseUrl}/api/members/${id}`, {
             method: 'PUT',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({
+                display_name: updates.displayName,
+                color: updates.color,
+                initials: updates.initials,
+            }),
         });
         
-        if (!response.ok) {
-            throw new Error(`Failed to toggle todo: ${response.statusText}`);
-        }
+        await this.ensureOk(response, 'Failed to update member');
         return response.json();
     }
 
-    async deleteTodo(id) {
-        const response = await fetch(`${this.baseUrl}/api/todos/${id}`, {
+    async deleteMember(id) {
+        const response = await this.fetch(`${this.baseUrl}/api/members/${id}`, {
             method: 'DELETE',
         });
         
-        if (!response.ok) {
-            throw new Error(`Failed to delete todo: ${response.statusText}`);
-        }
+        await this.ensureOk(response, 'Failed to delete member');
         return true;
     }
 
@@ -82,8 +357,12 @@ class TodoApiClient {
         if (this.websocket) {
             return; // Already connected
         }
+        this.reconnect = true;
 
-        const wsUrl = this.baseUrl.replace('http', 'ws') + '/ws';
+        let wsUrl = this.baseUrl.replace('http', 'ws') + '/ws';
+        if (this.lastSeq !== null) {
+            wsUrl += `?since=${this.lastSeq}`;
+        }
         this.websocket = new WebSocket(wsUrl);
 
         this.websocket.onopen = () => {
@@ -95,7 +374,15 @@ class TodoApiClient {
             try {
                 const message = JSON.parse(event.data);
                 console.log('WebSocket message received:', message);
-                this.emit(message.message_type, message.data);
+                if (this.settleCommand(message)) {
+                    return;
+                }
+                if (message.seq != null) {
+                    this.lastSeq = message.seq;
+                } else if (message.message_type === 'connected' && this.lastSeq === null) {
+                    this.lastSeq = message.data.seq;
+                }
+                this.emit(message.message_type, message.data, message.list_id);
             } catch (error) {
                 console.error('Failed to parse WebSocket message:', error);
             }
@@ -104,11 +391,15 @@ class TodoApiClient {
         this.websocket.onclose = () => {
             console.log('WebSocket disconnected');
             this.websocket = null;
+            this.pendingCommands.forEach(({ reject }) => reject(new Error('WebSocket disconnected')));
+            this.pendingCommands.clear();
             this.emit('disconnected');
             
             // Attempt to reconnect after 3 seconds
             setTimeout(() => {
-                this.connectWebSocket();
+                if (this.reconnect) {
+                    this.connectWebSocket();
+                }
             }, 3000);
         };
 
@@ -118,7 +409,38 @@ class TodoApiClient {
         };
     }
 
+    // Sends a command such as `toggle` over the WebSocket. Resolves with the
+    // acknowledged data (what the REST endpoint would return) or rejects with
+    // an ApiError, so a display can skip REST entirely.
+    sendCommand(type, fields = {}) {
+        if (!this.websocket || this.websocket.readyState !== WebSocket.OPEN) {
+            return Promise.reject(new Error('WebSocket is not connected'));
+        }
+
+        const requestId = String(++this.nextRequestId);
+        return new Promise((resolve, reject) => {
+            this.pendingCommands.set(requestId, { resolve, reject });
+            this.websocket.send(JSON.stringify({ type, request_id: requestId, ...fields }));
+        });
+    }
+
+    settleCommand(message) {
+        const pending = this.pendingCommands.get(message.request_id);
+        if (!pending || !['ack', 'error', 'pong'].includes(message.message_type)) {
+            return false;
+        }
+
+        this.pendingCommands.delete(message.request_id);
+        if (message.message_type === 'error') {
+            pending.reject(new ApiError(null, message.error, 'WebSocket command failed'));
+        } else {
+            pending.resolve(message.data ?? null);
+        }
+        return true;
+    }
+
     disconnectWebSocket() {
+        this.reconnect = false;
         if (this.websocket) {
             this.websocket.close();
             this.websocket = null;
@@ -143,11 +465,11 @@ class TodoApiClient {
         }
     }
 
-    emit(eventType, data) {
+    emit(eventType, data, listId) {
         if (this.eventListeners.has(eventType)) {
             this.eventListeners.get(eventType).forEach(callback => {
                 try {
-                    callback(data);
+                    callback(data, listId);
                 } catch (error) {
                     console.error(`Error in event listener for ${eventType}:`, error);
                 }
@@ -159,10 +481,16 @@ class TodoApiClient {
     transformTodoFromBackend(todo) {
         return {
             id: todo.id,
+            listId: todo.list_id,
             text: todo.text,
-            assignee: todo.assignee,
+            assigneeId: todo.assignee_id,
             date: todo.due_date || '',
             completed: todo.completed,
+            needsApproval: todo.needs_approval,
+            approvalRequestedBy: todo.approval_requested_by,
+            points: todo.points,
+            progress: todo.progress,
+            version: todo.version,
         };
     }
 
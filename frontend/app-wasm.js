@@ -17,6 +17,7 @@ class TodoAppController {
         await init();
         this.app = new TodoApp();
         this.setupEventListeners();
+        this.populateMemberSelects();
         this.renderTodos();
     }
 
@@ -28,6 +29,21 @@ class TodoAppController {
             }
         });
 
+        // Ctrl+Z undoes, Ctrl+Shift+Z or Ctrl+Y redoes, unless typing in a field
+        document.addEventListener('keydown', (e) => {
+            if (!(e.ctrlKey || e.metaKey) || e.target.matches('input, textarea, select')) {
+                return;
+            }
+            const key = e.key.toLowerCase();
+            if (key === 'z' && !e.shiftKey) {
+                e.preventDefault();
+                this.undo();
+            } else if (key === 'y' || (key === 'z' && e.shiftKey)) {
+                e.preventDefault();
+                this.redo();
+            }
+        });
+
         // Close modal when clicking outside
         document.getElementById('filterModal').addEventListener('click', (e) => {
             if (e.target === e.currentTarget) {
@@ -36,10 +52,23 @@ class TodoAppController {
         });
     }
 
+    // Member Management Methods
+    addMember(displayName, color = '') {
+        const id = this.app.add_member(displayName, color);
+        this.populateMemberSelects();
+        return id;
+    }
+
+    removeMember(id) {
+        this.app.remove_member(id);
+        this.populateMemberSelects();
+        this.renderTodos();
+    }
+
     // Todo Management Methods
     addTodo() {
         const text = document.getElementById('todoText').value.trim();
-        const assignee = document.getElementById('assignee').value;
+        const assigneeId = this.parseAssigneeId(document.getElementById('assignee').value);
         const date = document.getElementById('dueDate').value;
         
         if (!text) {
@@ -47,7 +76,7 @@ class TodoAppController {
             return;
         }
         
-        this.app.add_todo(text, assignee, date);
+        this.app.add_todo(text, assigneeId, date);
         this.clearForm();
         this.renderTodos();
     }
@@ -68,7 +97,7 @@ class TodoAppController {
 
     saveTodo(id) {
         const text = document.getElementById(`editText-${id}`).value.trim();
-        const assignee = document.getElementById(`editAssignee-${id}`).value;
+        const assigneeId = this.parseAssigneeId(document.getElementById(`editAssignee-${id}`).value);
         const date = document.getElementById(`editDate-${id}`).value;
         
         if (!text) {
@@ -76,7 +105,7 @@ class TodoAppController {
             return;
         }
         
-        this.app.edit_todo(id, text, assignee, date);
+        this.app.edit_todo(id, text, assigneeId, date);
         this.renderTodos();
     }
 
@@ -84,6 +113,23 @@ class TodoAppController {
         this.renderTodos();
     }
 
+    deleteTodo(id) {
+        this.app.delete_todo(id);
+        this.renderTodos();
+    }
+
+    undo() {
+        if (this.app.undo()) {
+            this.renderTodos();
+        }
+    }
+
+    redo() {
+        if (this.app.redo()) {
+            this.renderTodos();
+        }
+    }
+
     // Helper Methods
     findTodoById(id) {
         const groupedTodos = JSON.parse(this.app.get_todos_grouped_by_date_json());
@@ -94,6 +140,46 @@ class TodoAppController {
         return null;
     }
 
+    getMembers() {
+        return JSON.parse(this.app.get_members_json());
+    }
+
+    findMemberById(id) {
+        return this.getMembers().find(member => member.id === id) || null;
+    }
+
+    parseAssigneeId(value) {
+        return value ? Number(value) : null;
+    }
+
+    populateMemberSelects() {
+        document.getElementById('assignee').innerHTML =
+            `<option value="">Unassigned</option>${this.generateMemberOptions(null)}`;
+
+        const filterSelect = document.getElementById('filterAssignee');
+        filterSelect.innerHTML = `
+            <option value="all">All</option>
+            <option value="unassigned">Unassigned</option>
+            ${this.generateMemberOptions(null)}
+        `;
+        filterSelect.value = this.currentFilters.assignee;
+        if (filterSelect.value !== this.currentFilters.assignee) {
+            // The filtered member was removed
+            filterSelect.value = 'all';
+            this.currentFilters.assignee = 'all';
+        }
+    }
+
+    generateMemberOptions(selectedId) {
+        return this.getMembers()
+            .map(member => `
+                <option value="${member.id}" ${member.id === selectedId ? 'selected' : ''}>
+                    ${this.escapeHtml(member.display_name)}
+                </option>
+            `)
+            .join('');
+    }
+
     clearForm() {
         document.getElementById('todoText').value = '';
         document.getElementById('dueDate').value = '';
@@ -161,12 +247,13 @@ class TodoAppController {
                 <div class="todo-content">
                     <div class="todo-text">${this.escapeHtml(todo.text)}</div>
                     <div class="todo-meta">
-                        <span class="assignee ${todo.assignee.toLowerCase()}">${todo.assignee}</span>
+                        ${this.generateAssigneeBadge(todo.assignee_id)}
                         ${todo.date ? ` • Due: ${todo.date}` : ''}
                     </div>
                 </div>
                 <div class="todo-actions">
                     <button class="edit-btn" onclick="todoController.editTodo(${todo.id})">Edit</button>
+                    <button class="delete-btn" onclick="todoController.deleteTodo(${todo.id})">Delete</button>
                 </div>
             </div>
         `;
@@ -181,9 +268,8 @@ class TodoAppController {
                 <div class="edit-form">
                     <input type="text" id="editText-${todo.id}" value="${this.escapeHtml(todo.text)}">
                     <select id="editAssignee-${todo.id}">
-                        <option value="Unassigned" ${todo.assignee === 'Unassigned' ? 'selected' : ''}>Unassigned</option>
-                        <option value="Joe" ${todo.assignee === 'Joe' ? 'selected' : ''}>Joe</option>
-                        <option value="Shannon" ${todo.assignee === 'Shannon' ? 'selected' : ''}>Shannon</option>
+                        <option value="" ${todo.assignee_id === null ? 'selected' : ''}>Unassigned</option>
+                        ${this.generateMemberOptions(todo.assignee_id)}
                     </select>
                     <input type="date" id="editDate-${todo.id}" value="${todo.date}">
                     <div class="edit-actions">
@@ -195,6 +281,20 @@ class TodoAppController {
         `;
     }
 
+    generateAssigneeBadge(assigneeId) {
+        const member = this.findMemberById(assigneeId);
+        if (!member) {
+            return '<span class="assignee unassigned">Unassigned</span>';
+        }
+        
+        return `
+            <span class="assignee">
+                <span class="member-avatar" style="background-color: ${member.color}">${this.escapeHtml(member.initials)}</span>
+                ${this.escapeHtml(member.display_name)}
+            </span>
+        `;
+    }
+
     // Date and Time Utilities
     formatDateHeader(date) {
         if (date === 'No Due Date') {
@@ -303,7 +403,8 @@ class TodoAppController {
         const activeFilters = [];
         
         if (this.currentFilters.assignee !== 'all') {
-            activeFilters.push(`Assignee: ${this.currentFilters.assignee}`);
+            const member = this.findMemberById(this.parseAssigneeId(this.currentFilters.assignee));
+            activeFilters.push(`Assignee: ${member ? member.display_name : 'Unassigned'}`);
         }
         if (this.currentFilters.status !== 'all') {
             activeFilters.push(`Status: ${this.currentFilters.status}`);
@@ -319,7 +420,11 @@ class TodoAppController {
 
     filterTodos(todos) {
         return todos.filter(todo => {
-            if (this.currentFilters.assignee !== 'all' && todo.assignee !== this.currentFilters.assignee) {
+            if (this.currentFilters.assignee === 'unassigned' && todo.assignee_id !== null) {
+                return false;
+            }
+            if (!['all', 'unassigned'].includes(this.currentFilters.assignee)
+                && todo.assignee_id !== this.parseAssigneeId(this.currentFilters.assignee)) {
                 return false;
             }
             
@@ -366,6 +471,34 @@ class TodoAppController {
                this.currentFilters.dateTo;
     }
 
+    // Search, done offline by the WASM app
+    searchTodos(query) {
+        if (!query.trim()) {
+            document.getElementById('searchResults').innerHTML = '';
+            return;
+        }
+        this.renderSearchResults(JSON.parse(this.app.search_todos_json(query)));
+    }
+
+    renderSearchResults(hits) {
+        document.getElementById('searchResults').innerHTML = hits.length === 0
+            ? '<li class="empty-state">No todos match your search.</li>'
+            : hits
+                .map(hit => `
+                    <li class="search-result ${hit.todo.completed ? 'completed' : ''}">
+                        ${this.highlightSnippet(hit.snippet)}
+                    </li>
+                `)
+                .join('');
+    }
+
+    // Snippets wrap matches in <mark>; everything else in them is the todo's own text
+    highlightSnippet(snippet) {
+        return this.escapeHtml(snippet)
+            .replace(/&lt;mark&gt;/g, '<mark>')
+            .replace(/&lt;\/mark&gt;/g, '</mark>');
+    }
+
     // Utility Methods
     escapeHtml(text) {
         const div = document.createElement('div');
@@ -390,6 +523,7 @@ window.toggleDateGroup = (dateKey) => todoController.toggleDateGroup(dateKey);
 window.toggleFilterModal = () => todoController.toggleFilterModal();
 window.applyFilters = () => todoController.applyFilters();
 window.clearFilters = () => todoController.clearFilters();
+window.searchTodos = (query) => todoController.searchTodos(query);
 
 // Start the application
 todoController.initialize();
\ No newline at end of file
@@ -6,6 +6,10 @@ class TodoAppController {
     constructor() {
         this.apiClient = new TodoApiClient();
         this.todosData = [];
+        this.members = [];
+        this.lists = [];
+        this.account = null;
+        this.currentListId = Number(localStorage.getItem('currentListId')) || null;
         this.currentFilters = {
             assignee: 'all',
             status: 'all',
@@ -17,9 +21,70 @@ class TodoAppController {
     async initialize() {
         this.setupEventListeners();
         this.setupWebSocket();
+        try {
+            await this.start(await this.apiClient.getAccount());
+        } catch (error) {
+            if (error.code !== 'unauthorized') {
+                console.error('Failed to load account:', error);
+            }
+            this.showSignIn();
+        }
+    }
+
+    // Everything past sign-in: the live connection and the household's data
+    async start(account) {
+        this.account = account;
+        document.getElementById('authPanel').hidden = true;
+        document.getElementById('appContent').hidden = false;
+        document.getElementById('accountName').textContent =
+            `${account.household.name} · ${account.user.email}`;
+
+        this.apiClient.connectWebSocket();
+        await this.loadLists();
+        await this.loadMembers();
         await this.loadTodos();
     }
 
+    showSignIn(message = '') {
+        this.account = null;
+        document.getElementById('appContent').hidden = true;
+        document.getElementById('authPanel').hidden = false;
+        document.getElementById('authError').textContent = message;
+    }
+
+    // Signs in, or with `createAccount` registers a new household first
+    async signIn(createAccount = false) {
+        const email = document.getElementById('authEmail').value.trim();
+        const password = document.getElementById('authPassword').value;
+        const householdName = document.getElementById('authHousehold').value.trim() || null;
+
+        try {
+            const account = createAccount
+                ? await this.apiClient.register(email, password, householdName)
+                : await this.apiClient.login(email, password);
+            document.getElementById('authPassword').value = '';
+            await this.start(account);
+        } catch (error) {
+            console.error('Failed to sign in:', error);
+            const message = error.code === 'unauthorized'
+                ? error.message
+                : this.describeError(error, 'Could not sign in. Please try again.');
+            document.getElementById('authError').textContent = message;
+        }
+    }
+
+    async signOut() {
+        try {
+            await this.apiClient.logout();
+        } catch (error) {
+            console.error('Failed to sign out:', error);
+        }
+        this.todosData = [];
+        this.members = [];
+        this.lists = [];
+        this.showSignIn();
+    }
+
     setupEventListeners() {
         // Enter key for adding todos
         document.getElementById('todoText').addEventListener('keypress', (e) => {
@@ -38,24 +103,58 @@ class TodoAppController {
 
     setupWebSocket() {
         // Set up real-time WebSocket listeners
-        this.apiClient.on('todo_created', (todo) => {
+        // Todo events are scoped to a list; only refresh for the one on screen
+        const refreshIfCurrentList = (listId) => {
+            if (listId === undefined || listId === this.currentListId) {
+                this.loadTodos();
+            }
+        };
+
+        this.apiClient.on('todo_created', (todo, listId) => {
             console.log('Todo created:', todo);
-            this.loadTodos(); // Refresh the list
+            refreshIfCurrentList(listId);
         });
 
-        this.apiClient.on('todo_updated', (todo) => {
+        this.apiClient.on('todo_updated', (todo, listId) => {
             console.log('Todo updated:', todo);
-            this.loadTodos(); // Refresh the list
+            refreshIfCurrentList(listId);
         });
 
-        this.apiClient.on('todo_toggled', (todo) => {
+        this.apiClient.on('todo_toggled', (todo, listId) => {
             console.log('Todo toggled:', todo);
-            this.loadTodos(); // Refresh the list
+            refreshIfCurrentList(listId);
         });
 
-        this.apiClient.on('todo_deleted', (data) => {
+        // A child ticked off a todo that needs approval, or a parent approved it
+        this.apiClient.on('todo_approval_requested', (todo, listId) => refreshIfCurrentList(listId));
+        this.apiClient.on('todo_approved', (todo, listId) => refreshIfCurrentList(listId));
+
+        this.apiClient.on('todo_deleted', (data, listId) => {
             console.log('Todo deleted:', data);
-            this.loadTodos(); // Refresh the list
+            refreshIfCurrentList(listId);
+        });
+
+        this.apiClient.on('todo_restored', (todo, listId) => refreshIfCurrentList(listId));
+
+        this.apiClient.on('list_created', () => this.loadLists());
+        this.apiClient.on('list_updated', () => this.loadLists());
+        this.apiClient.on('list_deleted', async () => {
+            await this.loadLists();
+            await this.loadTodos(); // The current list may be gone
+        });
+
+        this.apiClient.on('member_created', () => this.loadMembers());
+        this.apiClient.on('member_updated', () => this.loadMembers());
+        this.apiClient.on('member_deleted', async () => {
+            await this.loadMembers();
+            await this.loadTodos(); // Their todos are now unassigned
+        });
+
+        // Sent after a reconnect that missed too much to replay
+        this.apiClient.on('snapshot', async () => {
+            await this.loadLists();
+            await this.loadMembers();
+            await this.loadTodos();
         });
 
         this.apiClient.on('connected', () => {
@@ -66,14 +165,16 @@ class TodoAppController {
             console.log('Real-time connection lost');
         });
 
-        // Connect to WebSocket
-        this.apiClient.connectWebSocket();
+        // The connection itself is opened once signed in
     }
 
     // Data loading
     async loadTodos() {
         try {
-            const groupedTodos = await this.apiClient.getTodos();
+            const filters = this.serverFilters();
+            const groupedTodos = this.currentListId
+                ? await this.apiClient.getListTodos(this.currentListId, filters)
+                : await this.apiClient.getTodos(filters);
             this.todosData = this.apiClient.transformTodosGroupedFromBackend(groupedTodos);
             this.renderTodos();
         } catch (error) {
@@ -84,10 +185,52 @@ class TodoAppController {
         }
     }
 
+    async loadLists() {
+        try {
+            this.lists = await this.apiClient.getLists();
+        } catch (error) {
+            console.error('Failed to load lists:', error);
+            this.lists = [];
+        }
+        
+        if (!this.lists.some(list => list.id === this.currentListId)) {
+            this.currentListId = this.lists.length > 0 ? this.lists[0].id : null;
+        }
+        this.populateListSelect();
+    }
+
+    async selectList(listId) {
+        this.currentListId = listId;
+        localStorage.setItem('currentListId', listId);
+        await this.loadTodos();
+    }
+
+    populateListSelect() {
+        const listSelect = document.getElementById('listSelect');
+        listSelect.innerHTML = this.lists
+            .map(list => `
+                <option value="${list.id}" ${list.id === this.currentListId ? 'selected' : ''}>
+                    ${this.escapeHtml(list.name)}
+                </option>
+            `)
+            .join('');
+    }
+
+    async loadMembers() {
+        try {
+            this.members = await this.apiClient.getMembers();
+        } catch (error) {
+            console.error('Failed to load members:', error);
+            this.members = [];
+        }
+        this.populateMemberSelects();
+        this.renderTodos();
+    }
+
     // Todo Management Methods
     async addTodo() {
         const text = document.getElementById('todoText').value.trim();
-        const assignee = document.getElementById('assignee').value;
+        const assigneeId = this.parseAssigneeId(document.getElementById('assignee').value);
         const date = document.getElementById('dueDate').value;
         
         if (!text) {
@@ -96,22 +239,42 @@ class TodoAppController {
         }
         
         try {
-            await this.apiClient.createTodo(text, assignee, date);
+            await this.apiClient.createTodo(text, assigneeId, date, null, this.currentListId);
             this.clearForm();
             // The WebSocket will trigger a refresh automatically
         } catch (error) {
             console.error('Failed to create todo:', error);
-            alert('Failed to create todo. Please try again.');
+            alert(this.describeError(error, 'Failed to create todo. Please try again.'));
         }
     }
 
     async toggleTodo(id) {
         try {
-            await this.apiClient.toggleTodo(id);
+            await this.apiClient.toggleTodo(id, this.findTodoById(id)?.version);
             // The WebSocket will trigger a refresh automatically
         } catch (error) {
+            if (error.code === 'version_conflict') {
+                // Someone else got there first; show what they did
+                await this.loadTodos();
+                return;
+            }
             console.error('Failed to toggle todo:', error);
-            alert('Failed to update todo. Please try again.');
+            alert(this.describeError(error, 'Failed to update todo. Please try again.'));
+        }
+    }
+
+    async settleApproval(id, approve) {
+        const version = this.findTodoById(id)?.version;
+        try {
+            if (approve) {
+                await this.apiClient.approveTodo(id, version);
+            } else {
+                await this.apiClient.rejectTodo(id, version);
+            }
+        } catch (error) {
+            console.error('Failed to settle approval:', error);
+            alert(this.describeError(error, 'Failed to update todo. Please try again.'));
+            await this.loadTodos();
         }
     }
 
@@ -126,7 +289,7 @@ class TodoAppController {
 
     async saveTodo(id) {
         const text = document.getElementById(`editText-${id}`).value.trim();
-        const assignee = document.getElementById(`editAssignee-${id}`).value;
+        const assigneeId = this.parseAssigneeId(document.getElementById(`editAssignee-${id}`).value);
         const date = document.getElementById(`editDate-${id}`).value;
         
         if (!text) {
@@ -134,12 +297,17 @@ class TodoAppController {
             return;
         }
         
+        const version = this.findTodoById(id)?.version;
         try {
-            await this.apiClient.updateTodo(id, { text, assignee, date });
+            await this.apiClient.updateTodo(id, { text, assigneeId, date }, version);
             // The WebSocket will trigger a refresh automatically
         } catch (error) {
+            if (error.code === 'version_conflict') {
+                await this.resolveEditConflict(id, { text, assigneeId, date }, error.current);
+                return;
+            }
             console.error('Failed to update todo:', error);
-            alert('Failed to update todo. Please try again.');
+            alert(this.describeError(error, 'Failed to update todo. Please try again.'));
         }
     }
 
@@ -147,6 +315,24 @@ class TodoAppController {
         this.renderTodos();
     }
 
+    // The todo changed on another device while it was being edited: let the
+    // user keep their edit on top of the new version or take the other one.
+    async resolveEditConflict(id, updates, current) {
+        const keepMine = confirm(
+            `This task was changed on another device to "${current.text}".\n\n` +
+            'OK keeps your edit, Cancel keeps theirs.'
+        );
+        if (keepMine) {
+            try {
+                await this.apiClient.updateTodo(id, updates, current.version);
+                return;
+            } catch (error) {
+                console.error('Failed to update todo:', error);
+            }
+        }
+        await this.loadTodos();
+    }
+
     // Helper Methods
     findTodoById(id) {
         for (const [date, todos] of this.todosData) {
@@ -156,6 +342,56 @@ class TodoAppController {
         return null;
     }
 
+    findMemberById(id) {
+        return this.members.find(member => member.id === id) || null;
+    }
+
+    parseAssigneeId(value) {
+        return value ? Number(value) : null;
+    }
+
+    // Turns a backend error into something worth showing, keeping the
+    // request id so it can be matched against the server logs.
+    describeError(error, fallback) {
+        if (!error.code) {
+            return fallback;
+        }
+        const details = error.fields.length > 0
+            ? error.fields.map(field => `${field.field}: ${field.message}`).join('\n')
+            : error.message;
+        return `${details}\n\n(request ${error.requestId})`;
+    }
+
+    populateMemberSelects() {
+        const assigneeSelect = document.getElementById('assignee');
+        const selectedAssignee = assigneeSelect.value;
+        assigneeSelect.innerHTML = `<option value="">Unassigned</option>${this.generateMemberOptions(null)}`;
+        assigneeSelect.value = this.findMemberById(this.parseAssigneeId(selectedAssignee)) ? selectedAssignee : '';
+
+        const filterSelect = document.getElementById('filterAssignee');
+        filterSelect.innerHTML = `
+            <option value="all">All</option>
+            <option value="unassigned">Unassigned</option>
+            ${this.generateMemberOptions(null)}
+        `;
+        filterSelect.value = this.currentFilters.assignee;
+        if (filterSelect.value !== this.currentFilters.assignee) {
+            // The filtered member was removed
+            filterSelect.value = 'all';
+            this.currentFilters.assignee = 'all';
+        }
+    }
+
+    generateMemberOptions(selectedId) {
+        return this.members
+            .map(member => `
+                <option value="${member.id}" ${member.id === selectedId ? 'selected' : ''}>
+                    ${this.escapeHtml(member.display_name)}
+                </option>
+            `)
+            .join('');
+    }
+
     clearForm() {
         document.getElementById('todoText').value = '';
         document.getElementById('dueDate').value = '';
@@ -164,24 +400,17 @@ class TodoAppController {
     // Rendering Methods (keeping most of the existing logic)
     renderTodos() {
         const todoList = document.getElementById('todoList');
-        const filteredGroupedTodos = this.applyFiltersToGroups(this.todosData);
         
-        if (filteredGroupedTodos.length === 0) {
+        if (this.todosData.length === 0) {
             todoList.innerHTML = this.generateEmptyState();
             return;
         }
         
-        todoList.innerHTML = filteredGroupedTodos
+        todoList.innerHTML = this.todosData
             .map(([date, todos]) => this.generateDateGroup(date, todos))
             .join('');
     }
 
-    applyFiltersToGroups(groupedTodos) {
-        return groupedTodos
-            .map(([date, todos]) => [date, this.filterTodos(todos)])
-            .filter(([date, todos]) => todos.length > 0);
-    }
-
     generateEmptyState() {
         const hasFilters = this.hasActiveFilters();
         const message = hasFilters 
@@ -221,17 +450,32 @@ class TodoAppController {
                 <div class="todo-content">
                     <div class="todo-text">${this.escapeHtml(todo.text)}</div>
                     <div class="todo-meta">
-                        <span class="assignee ${todo.assignee.toLowerCase()}">${todo.assignee}</span>
+                        ${this.generateAssigneeBadge(todo.assigneeId)}
                         ${todo.date ? ` • Due: ${todo.date}` : ''}
+                        ${todo.points ? ` • ${todo.points} pts` : ''}
+                        ${todo.progress?.total ? ` • ${todo.progress.done}/${todo.progress.total} steps` : ''}
+                        ${todo.approvalRequestedBy ? ' • Waiting for approval' : ''}
                     </div>
                 </div>
                 <div class="todo-actions">
+                    ${this.generateApprovalButtons(todo)}
                     <button class="edit-btn" onclick="todoController.editTodo(${todo.id})">Edit</button>
                 </div>
             </div>
         `;
     }
 
+    // Only parents can settle a child's completion
+    generateApprovalButtons(todo) {
+        if (!todo.approvalRequestedBy || this.account?.user.role !== 'parent') {
+            return '';
+        }
+        return `
+            <button class="edit-btn" onclick="todoController.settleApproval(${todo.id}, true)">Approve</button>
+            <button class="delete-btn" onclick="todoController.settleApproval(${todo.id}, false)">Reject</button>
+        `;
+    }
+
     generateEditForm(todo) {
         return `
             <input type="checkbox" class="todo-checkbox" 
@@ -241,9 +485,8 @@ class TodoAppController {
                 <div class="edit-form">
                     <input type="text" id="editText-${todo.id}" value="${this.escapeHtml(todo.text)}">
                     <select id="editAssignee-${todo.id}">
-                        <option value="Unassigned" ${todo.assignee === 'Unassigned' ? 'selected' : ''}>Unassigned</option>
-                        <option value="Joe" ${todo.assignee === 'Joe' ? 'selected' : ''}>Joe</option>
-                        <option value="Shannon" ${todo.assignee === 'Shannon' ? 'selected' : ''}>Shannon</option>
+                        <option value="" ${todo.assigneeId === null ? 'selected' : ''}>Unassigned</option>
+                        ${this.generateMemberOptions(todo.assigneeId)}
                     </select>
                     <input type="date" id="editDate-${todo.id}" value="${todo.date}">
                     <div class="edit-actions">
@@ -255,6 +498,20 @@ class TodoAppController {
         `;
     }
 
+    generateAssigneeBadge(assigneeId) {
+        const member = this.findMemberById(assigneeId);
+        if (!member) {
+            return '<span class="assignee unassigned">Unassigned</span>';
+        }
+        
+        return `
+            <span class="assignee">
+                <span class="member-avatar" style="background-color: ${member.color}">${this.escapeHtml(member.initials)}</span>
+                ${this.escapeHtml(member.display_name)}
+            </span>
+        `;
+    }
+
     // Date and Time Utilities
     formatDateHeader(date) {
         if (date === 'No Due Date') {
@@ -321,7 +578,7 @@ class TodoAppController {
         this.currentFilters.dateFrom = document.getElementById('filterDateFrom').value;
         this.currentFilters.dateTo = document.getElementById('filterDateTo').value;
         
-        this.renderTodos();
+        this.loadTodos();
         this.updateFilterStatus();
     }
 
@@ -338,7 +595,7 @@ class TodoAppController {
         document.getElementById('filterDateFrom').value = '';
         document.getElementById('filterDateTo').value = '';
         
-        this.renderTodos();
+        this.loadTodos();
         this.updateFilterStatus();
     }
 
@@ -363,7 +620,8 @@ class TodoAppController {
         const activeFilters = [];
         
         if (this.currentFilters.assignee !== 'all') {
-            activeFilters.push(`Assignee: ${this.currentFilters.assignee}`);
+            const member = this.findMemberById(this.parseAssigneeId(this.currentFilters.assignee));
+            activeFilters.push(`Assignee: ${member ? member.display_name : 'Unassigned'}`);
         }
         if (this.currentFilters.status !== 'all') {
             activeFilters.push(`Status: ${this.currentFilters.status}`);
@@ -377,46 +635,15 @@ class TodoAppController {
         return activeFilters;
     }
 
-    filterTodos(todos) {
-        return todos.filter(todo => {
-            if (this.currentFilters.assignee !== 'all' && todo.assignee !== this.currentFilters.assignee) {
-                return false;
-            }
-            
-            if (this.currentFilters.status !== 'all') {
-                const isCompleted = todo.completed;
-                if (this.currentFilters.status === 'completed' && !isCompleted) {
-                    return false;
-                }
-                if (this.currentFilters.status === 'pending' && isCompleted) {
-                    return false;
-                }
-            }
-            
-            if (this.currentFilters.dateFrom || this.currentFilters.dateTo) {
-                if (!todo.date) {
-                    return !this.currentFilters.dateFrom && !this.currentFilters.dateTo;
-                }
-                
-                const todoDate = new Date(todo.date + 'T00:00:00');
-                
-                if (this.currentFilters.dateFrom) {
-                    const fromDate = new Date(this.currentFilters.dateFrom + 'T00:00:00');
-                    if (todoDate < fromDate) {
-                        return false;
-                    }
-                }
-                
-                if (this.currentFilters.dateTo) {
-                    const toDate = new Date(this.currentFilters.dateTo + 'T23:59:59');
-                    if (todoDate > toDate) {
-                        return false;
-                    }
-                }
-            }
-            
-            return true;
-        });
+    // The filter modal's choices as GET /api/todos query parameters
+    serverFilters() {
+        const { assignee, status, dateFrom, dateTo } = this.currentFilters;
+        return {
+            assignee: assignee === 'all' ? null : assignee,
+            completed: status === 'all' ? null : status === 'completed',
+            due_from: dateFrom,
+            due_to: dateTo
+        };
     }
 
     hasActiveFilters() {
@@ -426,6 +653,42 @@ class TodoAppController {
                this.currentFilters.dateTo;
     }
 
+    // Search
+    async searchTodos(query) {
+        this.searchQuery = query;
+        if (!query.trim()) {
+            document.getElementById('searchResults').innerHTML = '';
+            return;
+        }
+        try {
+            const hits = await this.apiClient.search(query);
+            if (query === this.searchQuery) {
+                this.renderSearchResults(hits);
+            }
+        } catch (error) {
+            console.error('Search failed:', error);
+        }
+    }
+
+    renderSearchResults(hits) {
+        document.getElementById('searchResults').innerHTML = hits.length === 0
+            ? '<li class="empty-state">No todos match your search.</li>'
+            : hits
+                .map(hit => `
+                    <li class="search-result ${hit.todo.completed ? 'completed' : ''}">
+                        ${this.highlightSnippet(hit.snippet)}
+                    </li>
+                `)
+                .join('');
+    }
+
+    // Snippets wrap matches in <mark>; everything else in them is the todo's own text
+    highlightSnippet(snippet) {
+        return this.escapeHtml(snippet)
+            .replace(/&lt;mark&gt;/g, '<mark>')
+            .replace(/&lt;\/mark&gt;/g, '</mark>');
+    }
+
     // Utility Methods
     escapeHtml(text) {
         const div = document.createElement('div');
@@ -441,7 +704,11 @@ const todoController = new TodoAppController();
 window.todoController = todoController;
 
 // Legacy function support for existing HTML onclick handlers
+window.signIn = () => todoController.signIn();
+window.register = () => todoController.signIn(true);
+window.signOut = () => todoController.signOut();
 window.addTodo = () => todoController.addTodo();
+window.selectList = (listId) => todoController.selectList(Number(listId));
 window.toggleTodo = (id) => todoController.toggleTodo(id);
 window.editTodo = (id) => todoController.editTodo(id);
 window.saveTodo = (id) => todoController.saveTodo(id);
@@ -450,6 +717,7 @@ window.toggleDateGroup = (dateKey) => todoController.toggleDateGroup(dateKey);
 window.toggleFilterModal = () => todoController.toggleFilterModal();
 window.applyFilters = () => todoController.applyFilters();
 window.clearFilters = () => todoController.clearFilters();
+window.searchTodos = (query) => todoController.searchTodos(query);
 
 // Start the application
 todoController.initialize();
\ No newline at end of file
@@ -1,5 +1,11 @@
+use chrono::{NaiveDate, NaiveTime, Utc};
+use family_todo_core::grouping::compare_todos;
+use family_todo_core::validation::validate_todo_text;
+use family_todo_core::{
+    group_by_bucket, group_by_due_date, initials_for, match_fields, now_in, parse_timezone,
+    search_terms, ChecklistProgress, Recurrence, SortableTodo, Tz, DEFAULT_MEMBER_COLOR,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -8,45 +14,176 @@ extern "C" {
     fn log(s: &str);
 }
 
-const NO_DUE_DATE_GROUP: &str = "No Due Date";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+const TIME_FORMAT: &str = "%H:%M";
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, DATE_FORMAT).ok()
+}
+
+/// Dates are `YYYY-MM-DD`, or empty for no due date.
+fn is_valid_date(date: &str) -> bool {
+    date.is_empty() || parse_date(date).is_some()
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, TIME_FORMAT).ok()
+}
+
+fn parse_recurrence(rule_json: &str) -> Option<Recurrence> {
+    serde_json::from_str::<Recurrence>(rule_json)
+        .ok()
+        .filter(|rule| rule.validate().is_ok())
+}
+
+fn dates_to_json(dates: &[NaiveDate]) -> String {
+    let dates: Vec<String> = dates
+        .iter()
+        .map(|date| date.format(DATE_FORMAT).to_string())
+        .collect();
+    serde_json::to_string(&dates).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    id: u32,
+    display_name: String,
+    color: String,
+    initials: String,
+}
+
+impl Member {
+    fn new(id: u32, display_name: &str, color: &str) -> Self {
+        Self {
+            id,
+            display_name: display_name.to_string(),
+            color: Self::color_or_default(color),
+            initials: initials_for(display_name),
+        }
+    }
+
+    fn rename(&mut self, display_name: &str) {
+        self.display_name = display_name.to_string();
+        self.initials = initials_for(display_name);
+    }
+
+    fn color_or_default(color: &str) -> String {
+        if color.is_empty() {
+            DEFAULT_MEMBER_COLOR.to_string()
+        } else {
+            color.to_string()
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Member {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn display_name(&self) -> String {
+        self.display_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn color(&self) -> String {
+        self.color.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn initials(&self) -> String {
+        self.initials.clone()
+    }
+}
+
+/// One step of a todo's checklist, kept in display order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    id: u32,
+    text: String,
+    completed: bool,
+}
 
 #[wasm_bindgen]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TodoItem {
     id: u32,
     text: String,
-    assignee: String,
+    assignee_id: Option<u32>,
     date: String,
+    /// `HH:MM` on `date`, or empty for any time that day.
+    #[serde(default)]
+    time: String,
     completed: bool,
+    recurrence: Option<Recurrence>,
+    next_occurrence_id: Option<u32>,
+    #[serde(default)]
+    checklist: Vec<ChecklistItem>,
+    #[serde(default)]
+    progress: ChecklistProgress,
+    /// Complete the todo once every checklist item is done.
+    #[serde(default)]
+    auto_complete: bool,
 }
 
 impl TodoItem {
-    fn new(id: u32, text: &str, assignee: &str, date: &str) -> Self {
+    fn new(id: u32, text: &str, assignee_id: Option<u32>, date: &str) -> Self {
         Self {
             id,
             text: text.to_string(),
-            assignee: assignee.to_string(),
+            assignee_id,
             date: date.to_string(),
+            time: String::new(),
             completed: false,
+            recurrence: None,
+            next_occurrence_id: None,
+            checklist: Vec::new(),
+            progress: ChecklistProgress::default(),
+            auto_complete: false,
         }
     }
 
+    fn count_checklist(&mut self) {
+        self.progress = ChecklistProgress::of(self.checklist.iter().map(|item| item.completed));
+    }
+
+    /// Whether a checklist change has just finished an `auto_complete` todo.
+    fn ready_to_auto_complete(&self) -> bool {
+        self.auto_complete && !self.completed && self.progress.is_complete()
+    }
+
     fn toggle_completion(&mut self) {
         self.completed = !self.completed;
     }
 
-    fn update(&mut self, text: &str, assignee: &str, date: &str) {
-        self.text = text.to_string();
-        self.assignee = assignee.to_string();
-        self.date = date.to_string();
+    fn update(&mut self, fields: &TodoFields) {
+        self.text = fields.text.clone();
+        self.assignee_id = fields.assignee_id;
+        self.date = fields.date.clone();
+        self.time = fields.time.clone();
     }
+}
 
-    fn date_group_key(&self) -> String {
-        if self.date.is_empty() {
-            NO_DUE_DATE_GROUP.to_string()
-        } else {
-            self.date.clone()
-        }
+impl SortableTodo for TodoItem {
+    fn id(&self) -> i64 {
+        i64::from(self.id)
+    }
+
+    fn completed(&self) -> bool {
+        self.completed
+    }
+
+    fn due_date(&self) -> Option<NaiveDate> {
+        parse_date(&self.date)
+    }
+
+    fn due_time(&self) -> Option<NaiveTime> {
+        parse_time(&self.time)
     }
 }
 
@@ -63,8 +200,8 @@ impl TodoItem {
     }
 
     #[wasm_bindgen(getter)]
-    pub fn assignee(&self) -> String {
-        self.assignee.clone()
+    pub fn assignee_id(&self) -> Option<u32> {
+        self.assignee_id
     }
 
     #[wasm_bindgen(getter)]
@@ -72,16 +209,82 @@ impl TodoItem {
         self.date.clone()
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> String {
+        self.time.clone()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn completed(&self) -> bool {
         self.completed
     }
+
+    #[wasm_bindgen(getter)]
+    pub fn checklist_done(&self) -> usize {
+        self.progress.done
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn checklist_total(&self) -> usize {
+        self.progress.total
+    }
+}
+
+/// How many changes `TodoApp::undo` can step back through by default.
+pub const DEFAULT_UNDO_DEPTH: usize = 50;
+
+/// The fields `TodoApp::edit_todo` changes.
+#[derive(Clone, Debug, PartialEq)]
+struct TodoFields {
+    text: String,
+    assignee_id: Option<u32>,
+    date: String,
+    time: String,
+}
+
+impl TodoFields {
+    fn of(todo: &TodoItem) -> Self {
+        Self {
+            text: todo.text.clone(),
+            assignee_id: todo.assignee_id,
+            date: todo.date.clone(),
+            time: todo.time.clone(),
+        }
+    }
+}
+
+/// A change to the todos that can be undone and redone. Each holds only what
+/// it changed, so undoing it leaves later changes to other fields (such as a
+/// checklist) alone.
+#[derive(Clone, Debug)]
+enum Command {
+    Add(TodoItem),
+    Edit {
+        id: u32,
+        before: TodoFields,
+        after: TodoFields,
+    },
+    /// Completing a recurring todo for the first time also adds its next
+    /// occurrence.
+    Toggle {
+        id: u32,
+        next_occurrence: Option<TodoItem>,
+    },
+    Delete(TodoItem),
 }
 
 #[wasm_bindgen]
 pub struct TodoApp {
     todos: Vec<TodoItem>,
     next_id: u32,
+    members: Vec<Member>,
+    next_member_id: u32,
+    next_checklist_item_id: u32,
+    undo_stack: Vec<Command>,
+    redo_stack: Vec<Command>,
+    undo_depth: usize,
+    /// Where "today" and "overdue" are judged, normally the household's.
+    timezone: Tz,
 }
 
 impl Default for TodoApp {
@@ -97,25 +300,149 @@ impl TodoApp {
         TodoApp {
             todos: Vec::new(),
             next_id: 1,
+            members: Vec::new(),
+            next_member_id: 1,
+            next_checklist_item_id: 1,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            undo_depth: DEFAULT_UNDO_DEPTH,
+            timezone: Tz::UTC,
         }
     }
 
+    /// Adds a todo, returning `false` if it fails the shared validation rules
+    /// or `date` is neither empty nor `YYYY-MM-DD`.
     #[wasm_bindgen]
-    pub fn add_todo(&mut self, text: &str, assignee: &str, date: &str) {
-        let todo = TodoItem::new(self.next_id, text, assignee, date);
+    pub fn add_todo(&mut self, text: &str, assignee_id: Option<u32>, date: &str) -> bool {
+        if validate_todo_text(text).is_err() || !is_valid_date(date) {
+            return false;
+        }
+
+        let assignee_id = self.known_member_id(assignee_id);
+        let todo = TodoItem::new(self.next_id, text, assignee_id, date);
+        self.record(Command::Add(todo.clone()));
         self.todos.push(todo);
         self.next_id += 1;
         self.sort_todos();
+        true
     }
 
+    /// Removes a todo, returning `false` if there is no such todo.
+    #[wasm_bindgen]
+    pub fn delete_todo(&mut self, id: u32) -> bool {
+        let Some(index) = self.todos.iter().position(|todo| todo.id == id) else {
+            return false;
+        };
+
+        let todo = self.todos.remove(index);
+        self.record(Command::Delete(todo));
+        true
+    }
+
+    /// Toggles completion. Completing a recurring todo for the first time also
+    /// adds its next occurrence, with a fresh copy of its checklist.
     #[wasm_bindgen]
     pub fn toggle_todo(&mut self, id: u32) {
-        if let Some(todo) = self.find_todo_by_id_mut(id) {
-            todo.toggle_completion();
-            self.sort_todos();
+        let next_id = self.next_id;
+        let mut next_item_id = self.next_checklist_item_id;
+        let Some(todo) = self.find_todo_by_id_mut(id) else {
+            return;
+        };
+        todo.toggle_completion();
+        let mut next_occurrence = None;
+
+        let next_date = match (&todo.recurrence, parse_date(&todo.date)) {
+            (Some(rule), Some(date)) if todo.completed && todo.next_occurrence_id.is_none() => {
+                rule.next_after(date)
+            }
+            _ => None,
+        };
+
+        if let Some(next_date) = next_date {
+            todo.next_occurrence_id = Some(next_id);
+            let mut next = TodoItem::new(
+                next_id,
+                &todo.text,
+                todo.assignee_id,
+                &next_date.format(DATE_FORMAT).to_string(),
+            );
+            next.time = todo.time.clone();
+            next.recurrence = todo.recurrence.clone();
+            next.auto_complete = todo.auto_complete;
+            next.checklist = todo
+                .checklist
+                .iter()
+                .map(|item| {
+                    let id = next_item_id;
+                    next_item_id += 1;
+                    ChecklistItem {
+                        id,
+                        text: item.text.clone(),
+                        completed: false,
+                    }
+                })
+                .collect();
+            next.count_checklist();
+            next_occurrence = Some(next.clone());
+            self.todos.push(next);
+            self.next_id += 1;
+            self.next_checklist_item_id = next_item_id;
+        }
+
+        self.record(Command::Toggle { id, next_occurrence });
+        self.sort_todos();
+    }
+
+    /// Sets a todo's recurrence rule from JSON, or clears it when `rule_json` is
+    /// empty. Recurring todos need a valid due date to repeat from.
+    #[wasm_bindgen]
+    pub fn set_recurrence(&mut self, id: u32, rule_json: &str) -> bool {
+        let recurrence = if rule_json.is_empty() {
+            None
+        } else {
+            match parse_recurrence(rule_json) {
+                Some(rule) => Some(rule),
+                None => return false,
+            }
+        };
+
+        match self.find_todo_by_id_mut(id) {
+            Some(todo) if recurrence.is_none() || parse_date(&todo.date).is_some() => {
+                todo.recurrence = recurrence;
+                true
+            }
+            _ => false,
         }
     }
 
+    /// Lists the next `count` due dates of a recurring todo as a JSON array.
+    #[wasm_bindgen]
+    pub fn get_upcoming_occurrences_json(&self, id: u32, count: usize) -> String {
+        let occurrences = self
+            .todos
+            .iter()
+            .find(|todo| todo.id == id)
+            .and_then(|todo| Some((todo.recurrence.as_ref()?, parse_date(&todo.date)?)))
+            .map(|(rule, date)| rule.occurrences_after(date, count))
+            .unwrap_or_default();
+        dates_to_json(&occurrences)
+    }
+
+    /// Previews a rule before it is saved, starting after `start_date`.
+    #[wasm_bindgen]
+    pub fn preview_recurrence_json(
+        &self,
+        rule_json: &str,
+        start_date: &str,
+        count: usize,
+    ) -> String {
+        let occurrences = parse_recurrence(rule_json)
+            .zip(parse_date(start_date))
+            .map(|(rule, date)| rule.occurrences_after(date, count))
+            .unwrap_or_default();
+        dates_to_json(&occurrences)
+    }
+
     #[wasm_bindgen]
     pub fn get_todos_json(&self) -> String {
         serde_json::to_string(&self.todos).unwrap_or_else(|_| "[]".to_string())
@@ -123,9 +450,64 @@ impl TodoApp {
 
     #[wasm_bindgen]
     pub fn get_todos_grouped_by_date_json(&self) -> String {
-        let grouped = self.group_todos_by_date();
-        let sorted_groups = self.sort_date_groups(grouped);
-        serde_json::to_string(&sorted_groups).unwrap_or_else(|_| "[]".to_string())
+        // The frontend expects `[date, todos]` pairs
+        let groups: Vec<(String, Vec<&TodoItem>)> = group_by_due_date(&self.todos)
+            .into_iter()
+            .map(|group| (group.date, group.todos))
+            .collect();
+        serde_json::to_string(&groups).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Groups open todos into Overdue, Today, Tomorrow, This Week, Later and
+    /// No Due Date, and done ones into Done, as `[{bucket, label, todos}]`.
+    /// Buckets are relative to the start of `today` (`YYYY-MM-DD`) or, when
+    /// that is empty, to now in `timezone` (the app's timezone when empty),
+    /// so todos due earlier today count as overdue. An invalid date or
+    /// timezone gives `[]`.
+    #[wasm_bindgen]
+    pub fn get_todos_grouped_by_bucket_json(&self, today: &str, timezone: &str) -> String {
+        let now = if !today.is_empty() {
+            parse_date(today).map(|today| today.and_time(NaiveTime::MIN))
+        } else if !timezone.is_empty() {
+            parse_timezone(timezone).ok().map(|timezone| now_in(timezone, Utc::now()))
+        } else {
+            Some(now_in(self.timezone, Utc::now()))
+        };
+        let groups = now
+            .map(|now| group_by_bucket(&self.todos, now))
+            .unwrap_or_default();
+        serde_json::to_string(&groups).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Searches todo text and checklist steps, forgiving typos, so things
+    /// can be found offline. Returns `[{todo, score, snippet}]`, best match
+    /// first, with matched words in the snippet wrapped in `<mark>`.
+    #[wasm_bindgen]
+    pub fn search_todos_json(&self, query: &str) -> String {
+        #[derive(Serialize)]
+        struct Hit<'a> {
+            todo: &'a TodoItem,
+            score: f64,
+            snippet: String,
+        }
+
+        let terms = search_terms(query);
+        let mut hits: Vec<Hit> = self
+            .todos
+            .iter()
+            .filter_map(|todo| {
+                let mut fields = vec![todo.text.as_str()];
+                fields.extend(todo.checklist.iter().map(|item| item.text.as_str()));
+                let found = match_fields(&terms, &fields)?;
+                Some(Hit {
+                    todo,
+                    score: found.score,
+                    snippet: found.snippet,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.todo.id.cmp(&a.todo.id)));
+        serde_json::to_string(&hits).unwrap_or_else(|_| "[]".to_string())
     }
 
     #[wasm_bindgen]
@@ -133,76 +515,337 @@ impl TodoApp {
         self.todos.len()
     }
 
+    /// Changes a todo's text, assignee and date, with the same checks as
+    /// `add_todo`. Clearing the date clears the due time too.
     #[wasm_bindgen]
-    pub fn edit_todo(&mut self, id: u32, text: &str, assignee: &str, date: &str) -> bool {
-        if let Some(todo) = self.find_todo_by_id_mut(id) {
-            todo.update(text, assignee, date);
-            self.sort_todos();
+    pub fn edit_todo(&mut self, id: u32, text: &str, assignee_id: Option<u32>, date: &str) -> bool {
+        if validate_todo_text(text).is_err() || !is_valid_date(date) {
+            return false;
+        }
+
+        let assignee_id = self.known_member_id(assignee_id);
+        let Some(todo) = self.find_todo_by_id(id) else {
+            return false;
+        };
+
+        let after = TodoFields {
+            text: text.to_string(),
+            assignee_id,
+            date: date.to_string(),
+            time: if date.is_empty() { String::new() } else { todo.time.clone() },
+        };
+        self.change_fields(id, after);
+        true
+    }
+
+    /// Sets the time (`HH:MM`) on a todo's due date it is due by, or clears
+    /// it when `time` is empty. Returns `false` for an unknown todo, a todo
+    /// without a due date, or a malformed time.
+    #[wasm_bindgen]
+    pub fn set_due_time(&mut self, id: u32, time: &str) -> bool {
+        let time = if time.is_empty() {
+            String::new()
+        } else {
+            match parse_time(time) {
+                Some(time) => time.format(TIME_FORMAT).to_string(),
+                None => return false,
+            }
+        };
+        let Some(todo) = self.find_todo_by_id(id) else {
+            return false;
+        };
+        if !time.is_empty() && todo.date.is_empty() {
+            return false;
+        }
+
+        let after = TodoFields { time, ..TodoFields::of(todo) };
+        self.change_fields(id, after);
+        true
+    }
+
+    /// Sets the IANA timezone, such as `Europe/London`, that "today" is
+    /// judged in. Returns `false`, keeping the current one, if it is unknown.
+    #[wasm_bindgen]
+    pub fn set_timezone(&mut self, timezone: &str) -> bool {
+        match parse_timezone(timezone) {
+            Ok(timezone) => {
+                self.timezone = timezone;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn timezone(&self) -> String {
+        self.timezone.name().to_string()
+    }
+
+    /// Reverts the most recent add, edit, toggle or delete. Returns `false`
+    /// when there is nothing to undo.
+    #[wasm_bindgen]
+    pub fn undo(&mut self) -> bool {
+        let Some(command) = self.undo_stack.pop() else {
+            return false;
+        };
+
+        self.revert(&command);
+        self.redo_stack.push(command);
+        self.sort_todos();
+        true
+    }
+
+    /// Re-applies the change most recently undone. Any new change clears
+    /// what could be redone.
+    #[wasm_bindgen]
+    pub fn redo(&mut self) -> bool {
+        let Some(command) = self.redo_stack.pop() else {
+            return false;
+        };
+
+        self.apply(&command);
+        self.undo_stack.push(command);
+        self.sort_todos();
+        true
+    }
+
+    #[wasm_bindgen]
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    #[wasm_bindgen]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Sets how many changes can be undone, forgetting the oldest ones past
+    /// it. `0` turns undo off.
+    #[wasm_bindgen]
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+        let excess = self.undo_stack.len().saturating_sub(depth);
+        self.undo_stack.drain(..excess);
+        self.redo_stack.truncate(depth);
+    }
+
+    /// Appends a step to a todo's checklist and returns its id, or `None` if
+    /// the todo does not exist or the text is blank.
+    #[wasm_bindgen]
+    pub fn add_checklist_item(&mut self, todo_id: u32, text: &str) -> Option<u32> {
+        if validate_todo_text(text).is_err() {
+            return None;
+        }
+
+        let id = self.next_checklist_item_id;
+        let todo = self.find_todo_by_id_mut(todo_id)?;
+        todo.checklist.push(ChecklistItem {
+            id,
+            text: text.to_string(),
+            completed: false,
+        });
+        todo.count_checklist();
+        self.next_checklist_item_id += 1;
+        Some(id)
+    }
+
+    /// Ticks or unticks a checklist item. Ticking the last open item of an
+    /// `auto_complete` todo completes the todo.
+    #[wasm_bindgen]
+    pub fn toggle_checklist_item(&mut self, todo_id: u32, item_id: u32) -> bool {
+        self.change_checklist(todo_id, |checklist| {
+            let Some(item) = checklist.iter_mut().find(|item| item.id == item_id) else {
+                return false;
+            };
+            item.completed = !item.completed;
+            true
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_checklist_item(&mut self, todo_id: u32, item_id: u32) -> bool {
+        self.change_checklist(todo_id, |checklist| {
+            let count_before = checklist.len();
+            checklist.retain(|item| item.id != item_id);
+            checklist.len() != count_before
+        })
+    }
+
+    /// Moves a checklist item to `index`, or to the end if `index` is past it.
+    #[wasm_bindgen]
+    pub fn move_checklist_item(&mut self, todo_id: u32, item_id: u32, index: usize) -> bool {
+        self.change_checklist(todo_id, |checklist| {
+            let Some(from) = checklist.iter().position(|item| item.id == item_id) else {
+                return false;
+            };
+            let item = checklist.remove(from);
+            checklist.insert(index.min(checklist.len()), item);
+            true
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn set_auto_complete(&mut self, todo_id: u32, auto_complete: bool) -> bool {
+        match self.find_todo_by_id_mut(todo_id) {
+            Some(todo) => {
+                todo.auto_complete = auto_complete;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds a family member and returns the new member's id.
+    #[wasm_bindgen]
+    pub fn add_member(&mut self, display_name: &str, color: &str) -> u32 {
+        let member = Member::new(self.next_member_id, display_name, color);
+        self.members.push(member);
+        self.next_member_id += 1;
+        self.next_member_id - 1
+    }
+
+    #[wasm_bindgen]
+    pub fn rename_member(&mut self, id: u32, display_name: &str) -> bool {
+        if let Some(member) = self.members.iter_mut().find(|member| member.id == id) {
+            member.rename(display_name);
             true
         } else {
             false
         }
     }
 
+    /// Removes a member; their todos are kept and become unassigned.
+    #[wasm_bindgen]
+    pub fn remove_member(&mut self, id: u32) -> bool {
+        let count_before = self.members.len();
+        self.members.retain(|member| member.id != id);
+        if self.members.len() == count_before {
+            return false;
+        }
+
+        for todo in self
+            .todos
+            .iter_mut()
+            .filter(|todo| todo.assignee_id == Some(id))
+        {
+            todo.assignee_id = None;
+        }
+        true
+    }
+
+    #[wasm_bindgen]
+    pub fn get_members_json(&self) -> String {
+        serde_json::to_string(&self.members).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    fn known_member_id(&self, assignee_id: Option<u32>) -> Option<u32> {
+        assignee_id.filter(|id| self.members.iter().any(|member| member.id == *id))
+    }
+
+    fn find_todo_by_id(&self, id: u32) -> Option<&TodoItem> {
+        self.todos.iter().find(|todo| todo.id == id)
+    }
+
     fn find_todo_by_id_mut(&mut self, id: u32) -> Option<&mut TodoItem> {
         self.todos.iter_mut().find(|todo| todo.id == id)
     }
 
-    fn group_todos_by_date(&self) -> HashMap<String, Vec<&TodoItem>> {
-        let mut grouped: HashMap<String, Vec<&TodoItem>> = HashMap::new();
+    /// Changes a todo's fields as one undoable edit.
+    fn change_fields(&mut self, id: u32, after: TodoFields) {
+        let Some(todo) = self.find_todo_by_id_mut(id) else {
+            return;
+        };
 
-        for todo in &self.todos {
-            let date_key = todo.date_group_key();
-            grouped.entry(date_key).or_default().push(todo);
-        }
+        let before = TodoFields::of(todo);
+        todo.update(&after);
+        self.record(Command::Edit { id, before, after });
+        self.sort_todos();
+    }
 
-        // Sort todos within each group
-        for todos in grouped.values_mut() {
-            todos.sort_by(|a, b| Self::compare_todos_for_sorting(a, b));
+    /// Applies `change` to a todo's checklist and re-counts it, completing
+    /// the todo if that finished it off.
+    fn change_checklist(
+        &mut self,
+        todo_id: u32,
+        change: impl FnOnce(&mut Vec<ChecklistItem>) -> bool,
+    ) -> bool {
+        let Some(todo) = self.find_todo_by_id_mut(todo_id) else {
+            return false;
+        };
+        if !change(&mut todo.checklist) {
+            return false;
         }
+        todo.count_checklist();
 
-        grouped
+        if todo.ready_to_auto_complete() {
+            self.toggle_todo(todo_id);
+        }
+        true
     }
 
-    fn sort_date_groups(
-        &self,
-        grouped: HashMap<String, Vec<&TodoItem>>,
-    ) -> Vec<(String, Vec<TodoItem>)> {
-        let mut date_groups: Vec<(String, Vec<TodoItem>)> = grouped
-            .into_iter()
-            .map(|(date, todos)| (date, todos.into_iter().cloned().collect()))
-            .collect();
-
-        date_groups.sort_by(Self::compare_date_groups);
-        date_groups
+    fn sort_todos(&mut self) {
+        self.todos.sort_by(compare_todos);
     }
 
-    fn compare_todos_for_sorting(a: &TodoItem, b: &TodoItem) -> std::cmp::Ordering {
-        match (a.completed, b.completed) {
-            (true, false) => std::cmp::Ordering::Greater,
-            (false, true) => std::cmp::Ordering::Less,
-            _ => a.id.cmp(&b.id),
+    /// Puts a new change on the undo stack, dropping the oldest past the
+    /// depth limit, and forgets anything that could have been redone.
+    fn record(&mut self, command: Command) {
+        self.redo_stack.clear();
+        if self.undo_depth == 0 {
+            return;
+        }
+
+        self.undo_stack.push(command);
+        if self.undo_stack.len() > self.undo_depth {
+            self.undo_stack.remove(0);
         }
     }
 
-    fn compare_date_groups(
-        a: &(String, Vec<TodoItem>),
-        b: &(String, Vec<TodoItem>),
-    ) -> std::cmp::Ordering {
-        match (a.0.as_str(), b.0.as_str()) {
-            (NO_DUE_DATE_GROUP, NO_DUE_DATE_GROUP) => std::cmp::Ordering::Equal,
-            (NO_DUE_DATE_GROUP, _) => std::cmp::Ordering::Less,
-            (_, NO_DUE_DATE_GROUP) => std::cmp::Ordering::Greater,
-            (date_a, date_b) => date_a.cmp(date_b),
+    fn apply(&mut self, command: &Command) {
+        match command {
+            Command::Add(todo) => self.todos.push(todo.clone()),
+            Command::Edit { id, after, .. } => {
+                if let Some(todo) = self.find_todo_by_id_mut(*id) {
+                    todo.update(after);
+                }
+            }
+            Command::Toggle { id, next_occurrence } => {
+                if let Some(todo) = self.find_todo_by_id_mut(*id) {
+                    todo.toggle_completion();
+                    if let Some(next) = next_occurrence {
+                        todo.next_occurrence_id = Some(next.id);
+                        self.todos.push(next.clone());
+                    }
+                }
+            }
+            Command::Delete(todo) => self.todos.retain(|kept| kept.id != todo.id),
         }
     }
 
-    fn sort_todos(&mut self) {
-        self.todos.sort_by(Self::compare_todos_for_sorting);
+    fn revert(&mut self, command: &Command) {
+        match command {
+            Command::Add(todo) => self.todos.retain(|kept| kept.id != todo.id),
+            Command::Edit { id, before, .. } => {
+                if let Some(todo) = self.find_todo_by_id_mut(*id) {
+                    todo.update(before);
+                }
+            }
+            Command::Toggle { id, next_occurrence } => {
+                if let Some(todo) = self.find_todo_by_id_mut(*id) {
+                    todo.toggle_completion();
+                    if let Some(next) = next_occurrence {
+                        todo.next_occurrence_id = None;
+                        self.todos.retain(|kept| kept.id != next.id);
+                    }
+                }
+            }
+            Command::Delete(todo) => self.todos.push(todo.clone()),
+        }
     }
 }
 
 #[cfg(test)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
 mod tests {
     use super::*;
     use wasm_bindgen_test::*;
@@ -216,24 +859,151 @@ mod tests {
     #[test]
     fn test_add_todo() {
         let mut app = TodoApp::new();
-        app.add_todo("Test task", "Joe", "2024-01-01");
+        let joe = app.add_member("Joe", "");
+        app.add_todo("Test task", Some(joe), "2024-01-01");
         assert_eq!(app.get_todo_count(), 1);
     }
 
     #[test]
     fn test_add_unassigned_todo() {
         let mut app = TodoApp::new();
-        app.add_todo("Unassigned task", "Unassigned", "2024-01-01");
+        app.add_todo("Unassigned task", None, "2024-01-01");
         assert_eq!(app.get_todo_count(), 1);
 
         let todos_json = app.get_todos_json();
-        assert!(todos_json.contains("\"assignee\":\"Unassigned\""));
+        assert!(todos_json.contains("\"assignee_id\":null"));
+    }
+
+    #[test]
+    fn test_add_todo_with_unknown_member_is_unassigned() {
+        let mut app = TodoApp::new();
+        app.add_todo("Orphan task", Some(42), "2024-01-01");
+
+        let todos_json = app.get_todos_json();
+        assert!(todos_json.contains("\"assignee_id\":null"));
+    }
+
+    #[test]
+    fn test_add_member_derives_initials_and_color() {
+        let mut app = TodoApp::new();
+        let id = app.add_member("Grandma Sue", "");
+
+        let members: Vec<Member> = serde_json::from_str(&app.get_members_json()).unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].id, id);
+        assert_eq!(members[0].initials, "GS");
+        assert_eq!(members[0].color, DEFAULT_MEMBER_COLOR);
+    }
+
+    #[test]
+    fn test_rename_member_keeps_assignments() {
+        let mut app = TodoApp::new();
+        let kid = app.add_member("Sam", "#ff8800");
+        app.add_todo("Feed the cat", Some(kid), "");
+
+        assert!(app.rename_member(kid, "Samantha"));
+
+        let todos: Vec<TodoItem> = serde_json::from_str(&app.get_todos_json()).unwrap();
+        assert_eq!(todos[0].assignee_id, Some(kid));
+    }
+
+    #[test]
+    fn test_remove_member_unassigns_todos() {
+        let mut app = TodoApp::new();
+        let joe = app.add_member("Joe", "");
+        app.add_todo("Mow the lawn", Some(joe), "");
+
+        assert!(app.remove_member(joe));
+        assert!(!app.remove_member(joe));
+
+        let todos: Vec<TodoItem> = serde_json::from_str(&app.get_todos_json()).unwrap();
+        assert_eq!(todos[0].assignee_id, None);
+    }
+
+    #[test]
+    fn test_add_todo_rejects_blank_text() {
+        let mut app = TodoApp::new();
+        assert!(!app.add_todo("   ", None, "2024-01-01"));
+        assert_eq!(app.get_todo_count(), 0);
+    }
+
+    #[test]
+    fn test_grouped_json_lists_no_due_date_first() {
+        let mut app = TodoApp::new();
+        app.add_todo("Later", None, "2024-01-02");
+        app.add_todo("Whenever", None, "");
+        app.add_todo("Sooner", None, "2024-01-01");
+
+        let groups: Vec<(String, Vec<TodoItem>)> =
+            serde_json::from_str(&app.get_todos_grouped_by_date_json()).unwrap();
+        let keys: Vec<&str> = groups.iter().map(|(date, _)| date.as_str()).collect();
+        assert_eq!(keys, vec!["No Due Date", "2024-01-01", "2024-01-02"]);
+    }
+
+    #[test]
+    fn test_bucket_json_is_relative_to_today() {
+        let mut app = TodoApp::new();
+        app.add_todo("Late", None, "2024-03-06");
+        app.add_todo("Whenever", None, "");
+        app.add_todo("Now", None, "2024-03-07");
+
+        let groups: serde_json::Value =
+            serde_json::from_str(&app.get_todos_grouped_by_bucket_json("2024-03-07", "")).unwrap();
+        let labels: Vec<&str> = groups
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|group| group["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["Overdue", "Today", "No Due Date"]);
+        assert_eq!(groups[0]["bucket"], "overdue");
+
+        // Without a date, today is taken from the clock, long after these were due
+        assert!(app.get_todos_grouped_by_bucket_json("", "Europe/Berlin").starts_with(r#"[{"bucket":"overdue""#));
+        assert_eq!(app.get_todos_grouped_by_bucket_json("", "Nowhere"), "[]");
+        assert_eq!(app.get_todos_grouped_by_bucket_json("07/03/2024", ""), "[]");
+    }
+
+    #[test]
+    fn test_dates_and_times_are_validated() {
+        let mut app = TodoApp::new();
+        assert!(!app.add_todo("Bins", None, "next tuesday"));
+        assert!(!app.add_todo("Bins", None, "2024-02-30"));
+        assert!(app.add_todo("Bins", None, "2024-03-05"));
+        assert!(!app.edit_todo(1, "Bins", None, "05/03/2024"));
+        assert_eq!(app.find_todo_by_id(1).unwrap().date, "2024-03-05");
+
+        assert!(!app.set_due_time(1, "7pm"));
+        assert!(app.set_due_time(1, "19:00"));
+        assert_eq!(app.find_todo_by_id(1).unwrap().time, "19:00");
+        assert!(app.undo());
+        assert_eq!(app.find_todo_by_id(1).unwrap().time, "");
+        assert!(app.redo());
+
+        // The time goes with the date
+        assert!(app.edit_todo(1, "Bins", None, ""));
+        assert_eq!(app.find_todo_by_id(1).unwrap().time, "");
+        assert!(!app.set_due_time(1, "19:00"));
+    }
+
+    #[test]
+    fn test_buckets_use_the_app_timezone() {
+        let mut app = TodoApp::new();
+        assert!(!app.set_timezone("Atlantis/Capital"));
+        assert!(app.set_timezone("Pacific/Kiritimati"));
+        assert_eq!(app.timezone(), "Pacific/Kiritimati");
+
+        let tomorrow = now_in(app.timezone, Utc::now()).date().succ_opt().unwrap();
+        app.add_todo("Swimming", None, &tomorrow.format(DATE_FORMAT).to_string());
+        let groups: serde_json::Value =
+            serde_json::from_str(&app.get_todos_grouped_by_bucket_json("", "")).unwrap();
+        assert_eq!(groups[0]["bucket"], "tomorrow");
     }
 
     #[test]
     fn test_toggle_todo() {
         let mut app = TodoApp::new();
-        app.add_todo("Test task", "Joe", "2024-01-01");
+        app.add_todo("Test task", None, "2024-01-01");
         app.toggle_todo(1);
 
         let todos_json = app.get_todos_json();
@@ -243,8 +1013,10 @@ mod tests {
     #[test]
     fn test_todo_sorting() {
         let mut app = TodoApp::new();
-        app.add_todo("Task 1", "Joe", "2024-01-01");
-        app.add_todo("Task 2", "Shannon", "2024-01-02");
+        let joe = app.add_member("Joe", "");
+        let shannon = app.add_member("Shannon", "");
+        app.add_todo("Task 1", Some(joe), "2024-01-01");
+        app.add_todo("Task 2", Some(shannon), "2024-01-02");
 
         app.toggle_todo(1);
 
@@ -264,37 +1036,213 @@ mod tests {
     #[wasm_bindgen_test]
     fn test_wasm_add_todo() {
         let mut app = TodoApp::new();
-        app.add_todo("WASM task", "Shannon", "2024-01-01");
+        app.add_todo("WASM task", None, "2024-01-01");
         assert_eq!(app.get_todo_count(), 1);
     }
 
+    #[test]
+    fn test_completing_recurring_todo_spawns_next_occurrence() {
+        let mut app = TodoApp::new();
+        // 2024-01-02 is a Tuesday
+        app.add_todo("Take out trash", None, "2024-01-02");
+        assert!(app.set_recurrence(1, r#"{"freq":"weekly","weekdays":["Tue"]}"#));
+
+        app.toggle_todo(1);
+        assert_eq!(app.get_todo_count(), 2);
+
+        let todos: Vec<TodoItem> = serde_json::from_str(&app.get_todos_json()).unwrap();
+        let next = todos.iter().find(|todo| todo.id == 2).unwrap();
+        assert_eq!(next.date, "2024-01-09");
+        assert!(!next.completed);
+
+        // Un-toggling and completing again must not spawn a duplicate
+        app.toggle_todo(1);
+        app.toggle_todo(1);
+        assert_eq!(app.get_todo_count(), 2);
+    }
+
+    #[test]
+    fn test_set_recurrence_requires_valid_rule_and_date() {
+        let mut app = TodoApp::new();
+        app.add_todo("No date", None, "");
+        app.add_todo("Dated", None, "2024-01-01");
+
+        assert!(!app.set_recurrence(1, r#"{"freq":"daily"}"#));
+        assert!(!app.set_recurrence(2, r#"{"freq":"weekly","weekdays":[]}"#));
+        assert!(!app.set_recurrence(2, "not json"));
+        assert!(app.set_recurrence(2, r#"{"freq":"daily"}"#));
+        assert!(app.set_recurrence(2, ""));
+    }
+
+    #[test]
+    fn test_checklist_progress_and_auto_complete() {
+        let mut app = TodoApp::new();
+        app.add_todo("Pack for the trip", None, "2024-01-02");
+        app.set_recurrence(1, r#"{"freq":"daily"}"#);
+        let socks = app.add_checklist_item(1, "Socks").unwrap();
+        let tickets = app.add_checklist_item(1, "Tickets").unwrap();
+        assert_eq!(app.add_checklist_item(1, "  "), None);
+        assert_eq!(app.add_checklist_item(99, "Hat"), None);
+
+        assert!(app.move_checklist_item(1, tickets, 0));
+        assert!(app.toggle_checklist_item(1, socks));
+        let todo = &app.todos[0];
+        assert_eq!(todo.checklist[0].text, "Tickets");
+        assert_eq!((todo.checklist_done(), todo.checklist_total()), (1, 2));
+        assert!(!todo.completed());
+
+        // Without auto-complete, finishing the checklist leaves the todo open
+        assert!(app.toggle_checklist_item(1, tickets));
+        assert!(!app.todos[0].completed());
+
+        app.toggle_checklist_item(1, tickets);
+        assert!(app.set_auto_complete(1, true));
+        app.toggle_checklist_item(1, tickets);
+        let todos: Vec<TodoItem> = serde_json::from_str(&app.get_todos_json()).unwrap();
+        let done = todos.iter().find(|todo| todo.id == 1).unwrap();
+        assert!(done.completed);
+
+        let next = todos.iter().find(|todo| todo.id == 2).unwrap();
+        assert_eq!((next.checklist_done(), next.checklist_total()), (0, 2));
+        assert!(next.checklist.iter().all(|item| item.id != socks && item.id != tickets));
+    }
+
+    #[test]
+    fn test_upcoming_occurrences() {
+        let mut app = TodoApp::new();
+        app.add_todo("Pay rent", None, "2024-01-31");
+        app.set_recurrence(1, r#"{"freq":"monthly","day":31}"#);
+
+        assert_eq!(
+            app.get_upcoming_occurrences_json(1, 3),
+            r#"["2024-02-29","2024-03-31","2024-04-30"]"#
+        );
+        assert_eq!(
+            app.preview_recurrence_json(r#"{"freq":"every_n_days","interval":3}"#, "2024-12-30", 2),
+            r#"["2025-01-02","2025-01-05"]"#
+        );
+    }
+
     #[test]
     fn test_edit_todo() {
         let mut app = TodoApp::new();
-        app.add_todo("Original task", "Joe", "2024-01-01");
+        let joe = app.add_member("Joe", "");
+        let shannon = app.add_member("Shannon", "");
+        app.add_todo("Original task", Some(joe), "2024-01-01");
 
-        let success = app.edit_todo(1, "Updated task", "Shannon", "2024-01-02");
+        let success = app.edit_todo(1, "Updated task", Some(shannon), "2024-01-02");
         assert!(success);
 
         let todos_json = app.get_todos_json();
         assert!(todos_json.contains("\"text\":\"Updated task\""));
-        assert!(todos_json.contains("\"assignee\":\"Shannon\""));
+        assert!(todos_json.contains(&format!("\"assignee_id\":{}", shannon)));
         assert!(todos_json.contains("\"date\":\"2024-01-02\""));
     }
 
     #[test]
     fn test_edit_nonexistent_todo() {
         let mut app = TodoApp::new();
-        let success = app.edit_todo(999, "New text", "Joe", "2024-01-01");
+        let success = app.edit_todo(999, "New text", None, "2024-01-01");
         assert!(!success);
     }
 
     #[wasm_bindgen_test]
     fn test_wasm_edit_todo() {
         let mut app = TodoApp::new();
-        app.add_todo("WASM task", "Joe", "2024-01-01");
+        let joe = app.add_member("Joe", "");
+        app.add_todo("WASM task", Some(joe), "2024-01-01");
 
-        let success = app.edit_todo(1, "Edited WASM task", "Shannon", "2024-01-02");
+        let success = app.edit_todo(1, "Edited WASM task", None, "2024-01-02");
         assert!(success);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut app = TodoApp::new();
+        assert!(!app.can_undo());
+        app.add_todo("Wash car", None, "2024-01-01");
+        app.edit_todo(1, "Wash and vacuum car", None, "2024-01-02");
+        app.toggle_todo(1);
+        assert!(app.delete_todo(1));
+        assert!(!app.delete_todo(1));
+        assert_eq!(app.get_todo_count(), 0);
+
+        assert!(app.undo());
+        let todo = app.todos[0].clone();
+        assert!(todo.completed);
+        assert!(app.undo());
+        assert!(!app.todos[0].completed);
+        assert!(app.undo());
+        assert_eq!(app.todos[0].text, "Wash car");
+        assert_eq!(app.todos[0].date, "2024-01-01");
+        assert!(app.undo());
+        assert_eq!(app.get_todo_count(), 0);
+        assert!(!app.can_undo());
+        assert!(!app.undo());
+
+        assert!(app.redo());
+        assert!(app.redo());
+        assert_eq!(app.todos[0].text, "Wash and vacuum car");
+        assert!(app.can_redo());
+
+        // A new change drops what could have been redone
+        app.toggle_todo(1);
+        assert!(!app.can_redo());
+        assert!(!app.redo());
+    }
+
+    #[test]
+    fn test_undoing_a_recurring_completion_removes_the_next_occurrence() {
+        let mut app = TodoApp::new();
+        app.add_todo("Take out trash", None, "2024-01-02");
+        assert!(app.set_recurrence(1, r#"{"freq":"weekly","weekdays":["Tue"]}"#));
+        app.toggle_todo(1);
+        assert_eq!(app.get_todo_count(), 2);
+
+        app.undo();
+        assert_eq!(app.get_todo_count(), 1);
+        assert_eq!(app.todos[0].next_occurrence_id, None);
+
+        // Redo brings back the same occurrence rather than a new one
+        app.redo();
+        assert_eq!(app.get_todo_count(), 2);
+        let todo = app.todos.iter().find(|todo| todo.id == 1).unwrap();
+        assert_eq!(todo.next_occurrence_id, Some(2));
+    }
+
+    #[test]
+    fn test_undo_depth() {
+        let mut app = TodoApp::new();
+        app.set_undo_depth(2);
+        app.add_todo("One", None, "");
+        app.add_todo("Two", None, "");
+        app.add_todo("Three", None, "");
+
+        assert!(app.undo());
+        assert!(app.undo());
+        assert!(!app.undo());
+        assert_eq!(app.get_todo_count(), 1);
+
+        app.set_undo_depth(0);
+        app.add_todo("Four", None, "");
+        assert!(!app.can_undo());
+    }
+
+    #[test]
+    fn test_search_todos_offline() {
+        let mut app = TodoApp::new();
+        app.add_todo("Call the dentist", None, "");
+        app.add_todo("Pack for the trip", None, "");
+        app.add_checklist_item(2, "Toothbrush");
+
+        let hits: serde_json::Value = serde_json::from_str(&app.search_todos_json("dentst")).unwrap();
+        assert_eq!(hits.as_array().unwrap().len(), 1);
+        assert_eq!(hits[0]["todo"]["id"], 1);
+        assert_eq!(hits[0]["snippet"], "Call the <mark>dentist</mark>");
+
+        let hits: serde_json::Value = serde_json::from_str(&app.search_todos_json("tooth")).unwrap();
+        assert_eq!(hits[0]["todo"]["id"], 2);
+        assert_eq!(app.search_todos_json("  "), "[]");
+    }
+
+}